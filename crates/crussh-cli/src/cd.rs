@@ -1,21 +1,454 @@
-use std::{env, path::Path};
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}};
 
-use home::home_dir;
+use crussh::{Builtin, Io, Shell};
 
-pub fn change_dir(args: &Vec<String>) -> Result<(), String> {
+/// `cd [-L|-P] [dir]`: `dir` defaults to `$HOME`, `-` means `$OLDPWD`, and
+/// relative names are looked up in `$CDPATH` first.
+pub struct Cd;
 
-    match args.len() {
-        0 => {
-            let home = home_dir().unwrap();
-            env::set_current_dir(home).map_err(|e| e.to_string())
+impl Builtin for Cd {
+    fn name(&self) -> &str {
+        "cd"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        match change_dir(shell, args) {
+            Ok(Some(dir)) => {
+                let _ = writeln!(io.stdout, "{}", dir);
+                0
+            },
+            Ok(None) => 0,
+            Err(msg) => {
+                let _ = writeln!(io.stderr, "crussh: cd: {}", msg);
+                1
+            }
+        }
+    }
+}
+
+/// Changes directory, returning the new one if it should be printed: after
+/// `cd -` and after finding the directory through `$CDPATH`.
+fn change_dir(shell: &mut Shell, args: &[String]) -> Result<Option<String>, String> {
+    let mut physical = false;
+    let mut operands = args;
+    while let Some((arg, rest)) = operands.split_first() {
+        match arg.as_str() {
+            "-L" => physical = false,
+            "-P" => physical = true,
+            "--" => {
+                operands = rest;
+                break;
+            },
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("{}: invalid option\ncd: usage: cd [-L|-P] [dir]", arg));
+            },
+            _ => break,
+        }
+        operands = rest;
+    }
+
+    let mut print = false;
+    let operand = match operands {
+        // As in bash, an empty `HOME` leaves the directory as it is.
+        [] => match shell.var("HOME") {
+            Some("") => return Ok(None),
+            Some(home) => home.to_string(),
+            None => return Err("HOME not set".to_string()),
         },
-        1 => {
-            let path = Path::new(args.get(0).unwrap());
-            env::set_current_dir(path).map_err(|e| e.to_string())
+        [dir] if dir == "-" => match shell.var("OLDPWD") {
+            Some(old) if !old.is_empty() => {
+                print = true;
+                old.to_string()
+            },
+            _ => return Err("OLDPWD not set".to_string()),
+        },
+        [dir] => dir.clone(),
+        _ => return Err("too many arguments".to_string()),
+    };
+
+    let target = match search_cdpath(shell, &operand) {
+        Some(found) => {
+            print = true;
+            found
+        },
+        None => PathBuf::from(&operand),
+    };
+
+    let target = shell.cwd().join(target);
+    let result = if physical {
+        fs::canonicalize(&target).and_then(|dir| shell.set_cwd(dir))
+    } else {
+        // Like bash, fall back to the physical path when the logical one
+        // does not lead anywhere.
+        shell.set_cwd(&target)
+            .or_else(|e| fs::canonicalize(&target).map_err(|_| e).and_then(|dir| shell.set_cwd(dir)))
+    };
+    result.map_err(|e| format!("{}: {}", operand, describe(&e)))?;
+
+    Ok(if print { Some(shell.cwd().display().to_string()) } else { None })
+}
+
+/// The directory `$CDPATH` names for `operand`, if it is a relative path
+/// that does not start with `.` or `..` and is found through a non-empty
+/// entry.
+fn search_cdpath(shell: &Shell, operand: &str) -> Option<PathBuf> {
+    let first = operand.split('/').next().unwrap_or("");
+    if operand.starts_with('/') || first == "." || first == ".." {
+        return None;
+    }
+
+    shell.var("CDPATH")?.split(':')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| Path::new(entry).join(operand))
+        .find(|candidate| shell.cwd().join(candidate).is_dir())
+}
+
+/// `pushd [-n] [dir | +N | -N]`: saves the working directory on the stack
+/// and changes to `dir`, or with no argument swaps the top two entries, or
+/// rotates entry `N` to the top.
+pub struct Pushd;
+
+impl Builtin for Pushd {
+    fn name(&self) -> &str {
+        "pushd"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (no_cd, args) = match args.split_first() {
+            Some((flag, rest)) if flag == "-n" => (true, rest),
+            _ => (false, args),
+        };
+
+        let result = match args {
+            [] => match shell.dir_stack().first().cloned() {
+                None => Err("no other directory".to_string()),
+                Some(_) if no_cd => Ok(()),
+                Some(top) => {
+                    let old = shell.cwd().to_path_buf();
+                    enter(shell, &top).map(|()| shell.dir_stack_mut()[0] = old)
+                },
+            },
+            [arg] if stack_index(arg).is_some() => match index(shell, arg) {
+                // With `-n` only the entries below the working directory turn.
+                Some(n) if no_cd => {
+                    shell.dir_stack_mut().rotate_left(n.saturating_sub(1));
+                    Ok(())
+                },
+                Some(n) => {
+                    let mut dirs = stack(shell);
+                    dirs.rotate_left(n);
+                    let (top, rest) = dirs.split_first().unwrap();
+                    enter(shell, top).map(|()| *shell.dir_stack_mut() = rest.to_vec())
+                },
+                None => Err(format!("{}: directory stack index out of range", arg)),
+            },
+            [dir] if no_cd => {
+                let dir = shell.cwd().join(dir);
+                shell.dir_stack_mut().insert(0, dir);
+                Ok(())
+            },
+            [dir] => {
+                let old = shell.cwd().to_path_buf();
+                change_dir(shell, std::slice::from_ref(dir)).map(|_| shell.dir_stack_mut().insert(0, old))
+            },
+            _ => Err("too many arguments".to_string()),
+        };
+
+        finish(shell, io, "pushd", result)
+    }
+}
+
+/// `popd [-n] [+N | -N]`: removes the top of the stack and changes to it,
+/// or removes entry `N`.
+pub struct Popd;
+
+impl Builtin for Popd {
+    fn name(&self) -> &str {
+        "popd"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (no_cd, args) = match args.split_first() {
+            Some((flag, rest)) if flag == "-n" => (true, rest),
+            _ => (false, args),
+        };
+
+        let n = match args {
+            _ if shell.dir_stack().is_empty() => {
+                return finish(shell, io, "popd", Err("directory stack empty".to_string()));
+            },
+            [] => 0,
+            [arg] if stack_index(arg).is_some() => match index(shell, arg) {
+                Some(n) => n,
+                None => {
+                    let e = format!("{}: directory stack index out of range", arg);
+                    return finish(shell, io, "popd", Err(e));
+                }
+            },
+            [arg] => return finish(shell, io, "popd", Err(format!("{}: invalid argument", arg))),
+            _ => return finish(shell, io, "popd", Err("too many arguments".to_string())),
+        };
+
+        let result = if n == 0 && !no_cd {
+            let top = shell.dir_stack()[0].clone();
+            enter(shell, &top).map(|()| {
+                shell.dir_stack_mut().remove(0);
+            })
+        } else {
+            shell.dir_stack_mut().remove(n.max(1) - 1);
+            Ok(())
+        };
+        finish(shell, io, "popd", result)
+    }
+}
+
+/// `dirs [-clpv] [+N | -N]`: shows the directory stack, starting with the
+/// working directory.
+pub struct Dirs;
+
+impl Builtin for Dirs {
+    fn name(&self) -> &str {
+        "dirs"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (mut clear, mut long, mut lines, mut numbered) = (false, false, false, false);
+        let mut only = None;
+
+        for arg in args {
+            if stack_index(arg).is_some() {
+                match index(shell, arg) {
+                    Some(n) => only = Some(n),
+                    None => {
+                        let _ = writeln!(io.stderr, "crussh: dirs: {}: directory stack index out of range", arg);
+                        return 1;
+                    }
+                }
+                continue;
+            }
+            match arg.strip_prefix('-').filter(|flags| !flags.is_empty()) {
+                Some(flags) if flags.chars().all(|c| "clpv".contains(c)) => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'c' => clear = true,
+                            'l' => long = true,
+                            'p' => lines = true,
+                            _ => numbered = true,
+                        }
+                    }
+                },
+                _ => {
+                    let _ = writeln!(io.stderr, "crussh: dirs: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "dirs: usage: dirs [-clpv] [+N] [-N]");
+                    return 2;
+                }
+            }
+        }
+        if clear {
+            shell.dir_stack_mut().clear();
+            return 0;
+        }
+
+        let dirs: Vec<String> = stack(shell).iter()
+            .map(|dir| if long { dir.display().to_string() } else { abbreviate(shell, dir) })
+            .collect();
+        let out = match only {
+            Some(n) => format!("{}\n", dirs[n]),
+            None if numbered => dirs.iter().enumerate().map(|(i, dir)| format!("{:2}  {}\n", i, dir)).collect(),
+            None if lines => dirs.iter().map(|dir| format!("{}\n", dir)).collect(),
+            None => format!("{}\n", dirs.join(" ")),
+        };
+        let _ = io.stdout.write_all(out.as_bytes());
+        0
+    }
+}
+
+/// The working directory followed by the directory stack.
+fn stack(shell: &Shell) -> Vec<PathBuf> {
+    std::iter::once(shell.cwd().to_path_buf()).chain(shell.dir_stack().iter().cloned()).collect()
+}
+
+/// Parses `+N` or `-N`, returning whether it counts from the end.
+fn stack_index(arg: &str) -> Option<(bool, usize)> {
+    let digits = arg.strip_prefix(['+', '-'])?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((arg.starts_with('-'), digits.parse().ok()?))
+}
+
+/// The position in [`stack`] that `+N` or `-N` refers to.
+fn index(shell: &Shell, arg: &str) -> Option<usize> {
+    let len = shell.dir_stack().len() + 1;
+    match stack_index(arg)? {
+        (true, n) => len.checked_sub(n + 1),
+        (false, n) => Some(n).filter(|&n| n < len),
+    }
+}
+
+/// Changes to a directory from the stack, which is already absolute.
+fn enter(shell: &mut Shell, dir: &Path) -> Result<(), String> {
+    shell.set_cwd(dir).map_err(|e| format!("{}: {}", dir.display(), describe(&e)))
+}
+
+/// `dir` with the home directory written as `~`.
+fn abbreviate(shell: &Shell, dir: &Path) -> String {
+    let dir = dir.display().to_string();
+    match shell.var("HOME").filter(|home| !home.is_empty() && *home != "/") {
+        Some(home) if dir == home => "~".to_string(),
+        Some(home) if dir.starts_with(&format!("{}/", home)) => format!("~{}", &dir[home.len()..]),
+        _ => dir,
+    }
+}
+
+/// Prints the stack after a successful `pushd` or `popd`, or the error.
+fn finish(shell: &Shell, io: &mut Io, name: &str, result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => {
+            let dirs: Vec<String> = stack(shell).iter().map(|dir| abbreviate(shell, dir)).collect();
+            let _ = writeln!(io.stdout, "{}", dirs.join(" "));
+            0
         },
-        _ => {
-            Err("crussh: cd: too many arguments".to_string())
+        Err(msg) => {
+            let _ = writeln!(io.stderr, "crussh: {}: {}", name, msg);
+            1
         }
     }
+}
+
+/// An I/O error as the C library words it, without the error number.
+fn describe(e: &io::Error) -> String {
+    let message = e.to_string();
+    match message.find(" (os error") {
+        Some(end) => message[..end].to_string(),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, os::unix::fs::symlink, path::PathBuf};
+
+    use crussh::{Shell, exec::Recorder};
 
-}
\ No newline at end of file
+    use super::{Cd, Dirs, Popd, Pushd};
+
+    fn shell(dir: &PathBuf) -> Shell {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.builtins_mut().insert(Cd);
+        shell.builtins_mut().insert(Pushd);
+        shell.builtins_mut().insert(Popd);
+        shell.builtins_mut().insert(Dirs);
+        shell.set_cwd(dir).unwrap();
+        shell
+    }
+
+    /// A directory holding `real/sub` and a link `link` to `real/sub`.
+    fn tree(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("crussh-cd-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("real/sub")).unwrap();
+        symlink(dir.join("real/sub"), dir.join("link")).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn tracks_pwd_and_oldpwd() {
+        let dir = tree("pwd");
+        let mut shell = shell(&dir);
+
+        shell.run("cd real").unwrap();
+        assert_eq!(shell.cwd(), dir.join("real"));
+        assert_eq!(shell.var("OLDPWD"), Some(dir.to_str().unwrap()));
+        assert_eq!(shell.var("PWD"), Some(dir.join("real").to_str().unwrap()));
+
+        shell.run("cd -").unwrap();
+        assert_eq!(shell.cwd(), dir);
+        assert_eq!(shell.var("OLDPWD"), Some(dir.join("real").to_str().unwrap()));
+        let captured = shell.capture("cd -").unwrap();
+        assert_eq!(captured.stdout_lossy(), format!("{}\n", dir.join("real").display()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn follows_links_logically_or_physically() {
+        let dir = tree("links");
+        let mut shell = shell(&dir);
+
+        shell.run("cd link && cd ..").unwrap();
+        assert_eq!(shell.cwd(), dir);
+        shell.run("cd -P link && cd ..").unwrap();
+        assert_eq!(shell.cwd(), dir.join("real"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn searches_cdpath() {
+        let dir = tree("cdpath");
+        let cdpath = |dir: &PathBuf| {
+            let mut shell = shell(&PathBuf::from("/"));
+            shell.set_var("CDPATH", format!(":{}", dir.join("real").display()));
+            shell
+        };
+
+        let captured = cdpath(&dir).capture("cd sub").unwrap();
+        assert_eq!(captured.stdout_lossy(), format!("{}\n", dir.join("real/sub").display()));
+        let captured = cdpath(&dir).capture("cd tmp").unwrap();
+        assert_eq!(captured.stdout_lossy(), "");
+        let mut shell = cdpath(&dir);
+        shell.run("cd sub").unwrap();
+        assert_eq!(shell.cwd(), dir.join("real/sub"));
+        shell.run("cd / && cd tmp").unwrap();
+        assert_eq!(shell.cwd(), PathBuf::from("/tmp"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut shell = shell(&PathBuf::from("/"));
+        shell.unset_var("HOME");
+        let captured = shell.capture("cd; cd /no/such; cd a b").unwrap();
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: cd: HOME not set\ncrussh: cd: /no/such: No such file or directory\ncrussh: cd: too many arguments\n"
+        );
+        assert_eq!(captured.status, 1);
+
+        let captured = shell.capture("HOME=; cd; echo $?; pwd").unwrap();
+        assert_eq!((captured.stdout_lossy(), captured.stderr_lossy()), ("0\n/\n".to_string(), String::new()));
+    }
+
+    #[test]
+    fn pushes_rotates_and_pops() {
+        let dir = tree("stack");
+        // A shell that has run `commands`, for a capture to start from.
+        let after = |commands: &str| {
+            let mut shell = shell(&dir);
+            shell.set_var("HOME", dir.to_string_lossy());
+            shell.run(commands).unwrap();
+            shell
+        };
+
+        let captured = after("").capture("pushd real; pushd sub; pushd +2; dirs -v").unwrap();
+        assert_eq!(
+            captured.stdout_lossy(),
+            "~/real ~\n~/real/sub ~/real ~\n~ ~/real/sub ~/real\n 0  ~\n 1  ~/real/sub\n 2  ~/real\n"
+        );
+
+        let mut shell = after("pushd real; pushd sub; pushd +2");
+        assert_eq!(shell.cwd(), dir);
+        let captured = shell.capture("echo ~1 ~-1; pushd; popd +1; dirs -l").unwrap();
+        let d = dir.display();
+        assert_eq!(captured.stdout_lossy(), format!("{d}/real/sub {d}/real/sub\n~/real/sub ~ ~/real\n~/real/sub ~/real\n{d}/real/sub {d}/real\n"));
+
+        let mut shell = after("pushd real; pushd sub; pushd +2; pushd; popd +1");
+        assert_eq!(shell.cwd(), dir.join("real/sub"));
+        let captured = shell.capture("popd; popd; pushd; pushd +3").unwrap();
+        assert_eq!(captured.stdout_lossy(), "~/real\n");
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: popd: directory stack empty\ncrussh: pushd: no other directory\ncrussh: pushd: +3: directory stack index out of range\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,85 @@
-use std::process::exit;
+use std::{io::Write, process::exit};
 
-pub fn terminate(args: &Vec<String>) -> Result<(), String> {
+use crussh::{Builtin, Io, Shell};
+
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name(&self) -> &str {
+        "exit"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        match terminate(shell, args) {
+            Ok(code) => {
+                shell.run_exit_trap();
+                // In a subshell or a pipeline stage only the child ends; the
+                // shell itself saves the history once the command is done.
+                if shell.is_subshell() {
+                    shell.stop(code);
+                    return code;
+                }
+                if shell.options().interactive {
+                    shell.hang_up_jobs();
+                }
+                shell.history_mut().set_status(code);
+                if let Err(e) = shell.save_history() {
+                    let _ = writeln!(io.stderr, "crussh: history: {}", e);
+                }
+                exit(code)
+            },
+            Err(msg) => {
+                let _ = writeln!(io.stderr, "{}", msg);
+                1
+            }
+        }
+    }
+}
+
+fn terminate(shell: &Shell, args: &[String]) -> Result<i32, String> {
     match args.len() {
         0 => {
-            exit(0);
+            Ok(shell.last_status())
         },
         1 => {
-            let code = args.get(0).unwrap();
+            let code = args.first().unwrap();
             let code: i32 = code.parse().map_err(|_| format!("crussh: exit: {}: numeric argument required", code))?;
-            exit(code)
+            Ok(code)
         },
         _ => {
             Err("crussh: exit: too many arguments".to_string())
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crussh::{Shell, exec::Recorder};
+
+    use super::Exit;
+
+    #[test]
+    fn ends_only_a_subshell() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.options_mut().interactive = true;
+        shell.builtins_mut().insert(Exit);
+        assert_eq!(shell.run("sleep 30 & (exit 1)"), Ok(1));
+        assert_eq!(shell.capture("(exit 2; echo not reached); echo $?; exit 3 | cat").unwrap().stdout_lossy(), "2\n");
+        assert_eq!(shell.exit_status(), None);
+        assert_eq!(recorder.killed(), []);
+    }
+
+    #[test]
+    fn leaves_the_history_to_the_shell_in_a_subshell() {
+        let file = env::temp_dir().join(format!("crussh-exit-history-{}", std::process::id()));
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.builtins_mut().insert(Exit);
+        shell.set_var("HISTFILE", file.to_string_lossy());
+        shell.add_history("(exit); exit 1 | cat");
+        assert_eq!(shell.run("(exit); exit 1 | cat"), Ok(0));
+        assert!(!file.exists());
+    }
+}
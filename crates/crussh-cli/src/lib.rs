@@ -1,48 +1,76 @@
 mod cd;
 mod exit;
 
-use std::{env, io::Write};
+use std::{io::Write, path::{Path, PathBuf}};
 
 use colored::{ColoredString, Colorize};
-use crussh::Parse;
+use crussh::Shell;
 use rustyline::{Editor, error::ReadlineError};
 
-pub fn exit() {
-    
+/// Adds the builtins of the interactive shell to `shell`.
+pub fn add_built_ins(shell: &mut Shell) {
+    shell.builtins_mut().insert(cd::Cd);
+    shell.builtins_mut().insert(cd::Pushd);
+    shell.builtins_mut().insert(cd::Popd);
+    shell.builtins_mut().insert(cd::Dirs);
+    shell.builtins_mut().insert(exit::Exit);
 }
 
-pub fn check_built_ins(parse: &Parse, rl: &mut Editor<()>) -> Result<(), String> {
-    match parse.get_filename() {
-        "cd" => {
-            cd::change_dir(parse.get_args())
-        },
-        "exit" => {
-            rl.save_history(&history_path()).unwrap();
-            exit::terminate(parse.get_args())
-        },
-        _ => Err("not a built-in".to_string())
+/// Runs a startup file such as `~/.crusshrc` if it exists. Errors are
+/// reported with the file and line, and do not stop the shell.
+pub fn run_startup_file(shell: &mut Shell, path: &Path) {
+    if !path.is_file() {
+        return;
     }
+    if let Err(e) = shell.run_script(path) {
+        eprintln!("crussh: {}", e);
+    }
+}
+
+/// `name` in the user's home directory, if it can be found.
+pub fn home_file(name: &str) -> Option<PathBuf> {
+    home().map(|home| home.join(name))
 }
 
-pub fn prompt(rl: &mut Editor<()>) -> Result<String, ReadlineError> {
+pub fn prompt(shell: &Shell, rl: &mut Editor<()>) -> Result<String, ReadlineError> {
     std::io::stdout().flush().unwrap();
-    let p_str = format!("\n[{}]\n{}({}){}", get_working_dir(), get_username(), get_hostname(), "-> ".yellow());
+    let p_str = format!("\n[{}]\n{}({}){}", get_working_dir(shell), get_username(), get_hostname(), "-> ".yellow());
     rl.readline(&p_str)
 }
 
-pub fn load_history(rl: &mut Editor<()>) -> Result<(), ReadlineError> {
-    rl.load_history(&history_path())
+/// Sets the history variables bash sets when they are not in the
+/// environment: the history is kept in `~/.crussh_history`, and both it
+/// and the file hold the last 500 entries. Without a home directory the
+/// history is not kept in a file.
+pub fn init_history(shell: &mut Shell) {
+    if shell.var("HISTFILE").is_none() {
+        if let Some(file) = home_file(".crussh_history") {
+            shell.set_var("HISTFILE", file.to_string_lossy());
+        }
+    }
+    for name in ["HISTSIZE", "HISTFILESIZE"] {
+        if shell.var(name).is_none() {
+            shell.set_var(name, "500");
+        }
+    }
 }
-pub fn save_history(rl: &mut Editor<()>) -> Result<(), ReadlineError> {
-    rl.save_history(&history_path())
+
+/// Makes the line editor's history that of the shell, which `history -c`
+/// and `history -d` may have changed.
+pub fn sync_history(shell: &Shell, rl: &mut Editor<()>) {
+    let history = rl.history_mut();
+    history.clear();
+    for (_, entry) in shell.history().iter() {
+        history.add(&entry.line);
+    }
 }
 
-fn get_working_dir() -> ColoredString {
-    let curr_dir = env::current_dir().unwrap();
-    let mut curr_dir = curr_dir.into_os_string().into_string().unwrap();
-    if curr_dir.starts_with(&home()) {
-        // str::replacen(&self, pat, to, count)
-        curr_dir = curr_dir.replacen(&home(), "~",1).as_str().to_string();
+fn get_working_dir(shell: &Shell) -> ColoredString {
+    let mut curr_dir = shell.cwd().to_string_lossy().into_owned();
+    if let Some(home) = shell.var("HOME").filter(|home| !home.is_empty()) {
+        if curr_dir.starts_with(home) {
+            curr_dir = curr_dir.replacen(home, "~", 1);
+        }
     }
     curr_dir.green()
 }
@@ -55,10 +83,6 @@ fn get_username() -> ColoredString {
     whoami::username().bold().blue()
 }
 
-fn home() -> String {
-    home::home_dir().unwrap().to_str().unwrap().to_string()
-}
-
-fn history_path() -> String {
-    format!("{}/{}", &home(),".crussh_history")
+fn home() -> Option<PathBuf> {
+    home::home_dir().filter(|home| !home.as_os_str().is_empty())
 }
\ No newline at end of file
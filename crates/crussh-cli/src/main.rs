@@ -1,21 +1,66 @@
+use std::{env, fs, io::{self, Read}, path::PathBuf, process};
+
 use rustyline::error::ReadlineError;
 use rustyline::Editor;
 
+const USAGE: &str = "usage: crussh [-l] [--norc] [--rcfile file] [--dump-ast] [-c command] | crussh fmt [file...]";
+
+/// What the command line asks for.
+#[derive(Default)]
+struct Invocation {
+    dump_ast: bool,
+    command: Option<String>,
+    /// Whether to read `~/.crussh_profile`: with `-l` or `--login`, or
+    /// when started with a `-` in front of its name, as `login` does.
+    login: bool,
+    norc: bool,
+    rcfile: Option<PathBuf>,
+}
+
 
 fn main() {
-    
+    let mut args = env::args();
+    let login = args.next().is_some_and(|name| name.starts_with('-'));
+    let args: Vec<String> = args.collect();
+    if args.first().is_some_and(|arg| arg == "fmt") {
+        process::exit(format(&args[1..]));
+    }
+    let mut invocation = match parse_args(&args) {
+        Ok(invocation) => invocation,
+        Err(status) => process::exit(status),
+    };
+    invocation.login |= login;
+    if invocation.command.is_some() || invocation.dump_ast {
+        process::exit(run_command(invocation));
+    }
+
     // `()` can be used when no completer is required
     let mut rl = Editor::<()>::new();
-    let mut last_command = if crussh_cli::load_history(&mut rl).is_err() {
-        println!("No previous history.");
-        String::from("")
-    } else {
-        rl.history().get(0).unwrap().clone()
-    };
+    let mut shell = crussh::Shell::new();
+    shell.options_mut().interactive = true;
+    crussh_cli::add_built_ins(&mut shell);
+    crussh_cli::init_history(&mut shell);
+    // Like bash, a login shell reads its profile and any other interactive
+    // shell its rc file.
+    if invocation.login {
+        if let Some(profile) = crussh_cli::home_file(".crussh_profile") {
+            crussh_cli::run_startup_file(&mut shell, &profile);
+        }
+    } else if !invocation.norc {
+        if let Some(rcfile) = invocation.rcfile.or_else(|| crussh_cli::home_file(".crusshrc")) {
+            crussh_cli::run_startup_file(&mut shell, &rcfile);
+        }
+    }
+    // The startup files may have changed where the history is kept.
+    if let Err(e) = shell.load_history() {
+        eprintln!("crussh: history: {}", e);
+    }
+    crussh_cli::sync_history(&shell, &mut rl);
+    let mut status = 0;
 
     
     loop {
-        let readline = crussh_cli::prompt(&mut rl);
+        let readline = crussh_cli::prompt(&shell, &mut rl);
 
             
         match readline {
@@ -24,20 +69,34 @@ fn main() {
                     continue;
                 }
 
-                let line = if line.eq("!!") {
-                    if last_command.is_empty() {
+                // Like bash, `!` references are expanded before the line is
+                // parsed, and the expanded line is shown and remembered.
+                let line = match shell.history().expand(&line) {
+                    Ok(Some(expanded)) => {
+                        println!("{}", expanded);
+                        expanded
+                    },
+                    Ok(None) => line,
+                    Err(e) => {
+                        eprintln!("crussh: {}", e);
                         continue;
-                    }
-                    println!("{}", last_command);
-                    last_command.clone()
-                } else {
-                    rl.add_history_entry(line.as_str());
-                    last_command = line.clone();
-                    line
+                    },
                 };
+                shell.add_history(&line);
+                crussh_cli::sync_history(&shell, &mut rl);
 
-                if let Err(e) = run(line.as_str(), &mut rl) {
-                    println!("{}", e);
+                status = run(line.as_str(), &mut shell);
+                // Each line goes to the history file once it has run, so
+                // other shells sharing the file see it right away.
+                shell.history_mut().set_status(status);
+                if let Err(e) = shell.save_history() {
+                    eprintln!("crussh: history: {}", e);
+                }
+                crussh_cli::sync_history(&shell, &mut rl);
+                // `set -e` or `set -u` stopped the shell.
+                if let Some(exit_status) = shell.exit_status() {
+                    status = exit_status;
+                    break;
                 }
             },
             Err(ReadlineError::Interrupted) => {
@@ -55,22 +114,126 @@ fn main() {
     }
 
 
-    rl.save_history("history.txt").unwrap();
-    
+    shell.run_exit_trap();
+    // Jobs marked with `disown -h`, or disowned, keep running.
+    shell.hang_up_jobs();
+    process::exit(status);
 }
 
 
-fn run(input: &str, rl: &mut Editor<()>) -> Result<(), String> {
-    let parse = crussh::parse(input).map_err(|msg| format!("Parse error: {}", msg))?;
+/// Reads the options, printing usage and returning the exit status if they
+/// are wrong.
+fn parse_args(args: &[String]) -> Result<Invocation, i32> {
+    let mut invocation = Invocation::default();
 
-    let res = crussh_cli::check_built_ins(&parse, rl);
-    
-    if res == Ok(()) || res != Err("not a built-in".to_string()) {
-        return res;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dump-ast" => invocation.dump_ast = true,
+            "-l" | "--login" => invocation.login = true,
+            "--norc" => invocation.norc = true,
+            "-c" | "--rcfile" => match args.next() {
+                Some(value) if arg == "-c" => invocation.command = Some(value.clone()),
+                Some(value) => invocation.rcfile = Some(PathBuf::from(value)),
+                None => {
+                    eprintln!("crussh: {}: option requires an argument", arg);
+                    return Err(2);
+                }
+            },
+            _ => {
+                eprintln!("crussh: {}: invalid option", arg);
+                eprintln!("{}", USAGE);
+                return Err(2);
+            }
+        }
     }
+    Ok(invocation)
+}
 
-    parse.execute()
-        .map_err(|msg| format!("Evaluation error: {}", msg))?;
+/// Handles a non-interactive invocation: `-c command` runs `command`, and
+/// `--dump-ast` prints its parse tree as JSON instead (reading standard
+/// input when `-c` is not given).
+fn run_command(invocation: Invocation) -> i32 {
+    let dump_ast = invocation.dump_ast;
+    let input = match invocation.command {
+        Some(command) => command,
+        None if dump_ast => {
+            let mut input = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut input) {
+                eprintln!("crussh: {}", e);
+                return 1;
+            }
+            input
+        },
+        None => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    if !dump_ast {
+        let mut shell = crussh::Shell::new();
+        crussh_cli::add_built_ins(&mut shell);
+        if invocation.login {
+            if let Some(profile) = crussh_cli::home_file(".crussh_profile") {
+                crussh_cli::run_startup_file(&mut shell, &profile);
+            }
+        }
+        let status = run(&input, &mut shell);
+        shell.run_exit_trap();
+        return shell.exit_status().unwrap_or(status);
+    }
+
+    match crussh::parse(&input) {
+        Ok(parse) => {
+            println!("{}", serde_json::to_string_pretty(parse.ast()).unwrap());
+            0
+        },
+        Err(e) => {
+            eprintln!("crussh: {}", e);
+            2
+        }
+    }
+}
 
-    Ok(())
-}
\ No newline at end of file
+/// `crussh fmt [file...]`: prints each file, or standard input, as
+/// normalized source. A file with comments is refused rather than printed
+/// without them.
+fn format(files: &[String]) -> i32 {
+    let inputs: Vec<(&str, io::Result<String>)> = if files.is_empty() {
+        let mut input = String::new();
+        let read = io::stdin().read_to_string(&mut input).map(|_| input);
+        vec![("-", read)]
+    } else {
+        files.iter().map(|file| (file.as_str(), fs::read_to_string(file))).collect()
+    };
+
+    let mut status = 0;
+    for (name, input) in inputs {
+        let parsed = input.map_err(|e| e.to_string()).and_then(|input| match crussh::comment_line(&input) {
+            Some(line) => Err(format!("line {}: cannot format a comment; fmt would drop it", line)),
+            None => crussh::parse(&input).map_err(|e| e.to_string()),
+        });
+        match parsed {
+            Ok(parse) if parse.ast().items.is_empty() => {},
+            Ok(parse) => println!("{}", parse),
+            Err(e) => {
+                eprintln!("crussh: {}: {}", name, e);
+                status = 2;
+            }
+        }
+    }
+    status
+}
+
+/// Runs one command line and returns its exit status, printing any failure
+/// the way bash does.
+fn run(input: &str, shell: &mut crussh::Shell) -> i32 {
+    match shell.run(input) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("crussh: {}", e);
+            e.status()
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Alias expansion, done on the source text before it is parsed, as bash
+//! does while reading tokens.
+
+use std::collections::BTreeMap;
+
+use crate::ast::{Assignment, Redirect, Word};
+use crate::utils;
+
+/// What the next word of the source is, as far as aliases go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    /// The first word of a command, which is replaced if it is an alias.
+    Command,
+    Argument,
+    /// The word after `case`, then the `in` after it.
+    CaseWord,
+    CaseIn,
+    /// A pattern of a `case` item.
+    Pattern,
+}
+
+/// Replaces every alias in command position in `source`. An alias is not
+/// expanded again inside its own value, and one whose value ends in a blank
+/// lets the word after it be expanded too. Text that does not lex is left
+/// for the parser to report.
+pub(crate) fn expand(source: &str, aliases: &BTreeMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return source.to_string();
+    }
+    let mut out = String::with_capacity(source.len());
+    expand_into(source, aliases, &mut Vec::new(), &mut out);
+    out
+}
+
+/// Expands `source` onto `out`, with the aliases in `active` left alone,
+/// and returns whether its last word was a blank-ended alias.
+fn expand_into(source: &str, aliases: &BTreeMap<String, String>, active: &mut Vec<String>, out: &mut String) -> bool {
+    let mut position = Position::Command;
+    let mut trailing_blank = false;
+    let mut s = source;
+
+    loop {
+        let (rest, blank) = utils::extract_whitespace(s);
+        out.push_str(blank);
+        s = rest;
+        if s.is_empty() {
+            return trailing_blank;
+        }
+        trailing_blank = false;
+
+        if let Some(op) = operator(s) {
+            out.push_str(op);
+            s = &s[op.len()..];
+            position = match (op, position) {
+                (";;", _) => Position::Pattern,
+                ("\n", Position::CaseWord | Position::CaseIn | Position::Pattern) => position,
+                ("|" | "(", Position::Pattern) => position,
+                _ => Position::Command,
+            };
+            continue;
+        }
+
+        let parsed = if Redirect::starts(s) {
+            Redirect::new(s).map(|(rest, _)| (rest, None))
+        } else if position == Position::Command && Assignment::starts(s) {
+            Assignment::new(s).map(|(rest, _)| (rest, None))
+        } else {
+            Word::new(s).map(|(rest, word)| (rest, Some(word)))
+        };
+        let (rest, word) = match parsed {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                out.push_str(s);
+                return false;
+            }
+        };
+        let text = &s[..s.len() - rest.len()];
+        s = rest;
+
+        let word = match word {
+            Some(word) => word,
+            // A redirection or an assignment leaves the position as it was.
+            None => {
+                out.push_str(text);
+                continue;
+            },
+        };
+        let literal = word.as_literal();
+
+        position = match (position, literal) {
+            // Only a word written without quotes names an alias.
+            (Position::Command, Some(name)) if name == text && is_expandable(name, aliases, active) => {
+                active.push(name.to_string());
+                let blank = expand_into(&aliases[name], aliases, active, out);
+                active.pop();
+                trailing_blank = blank || aliases[name].ends_with([' ', '\t']);
+                if trailing_blank { Position::Command } else { Position::Argument }
+            },
+            (Position::Command, Some("case")) => {
+                out.push_str(text);
+                Position::CaseWord
+            },
+            (Position::Command, Some("!" | "{" | "if" | "then" | "elif" | "else" | "while" | "until" | "do")) => {
+                out.push_str(text);
+                Position::Command
+            },
+            (Position::CaseWord, _) => {
+                out.push_str(text);
+                Position::CaseIn
+            },
+            (Position::CaseIn, Some("in")) | (Position::Pattern, _) => {
+                out.push_str(text);
+                if literal == Some("esac") { Position::Argument } else { Position::Pattern }
+            },
+            _ => {
+                out.push_str(text);
+                Position::Argument
+            },
+        };
+    }
+}
+
+fn is_expandable(name: &str, aliases: &BTreeMap<String, String>, active: &[String]) -> bool {
+    aliases.contains_key(name) && !active.iter().any(|a| a == name)
+}
+
+/// The control operator or newline at the start of `s`, if any.
+fn operator(s: &str) -> Option<&'static str> {
+    ["&&", "||", ";;", "\n", ";", "&", "|", "(", ")"].iter().copied().find(|op| s.starts_with(op))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::expand;
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn expands_command_words() {
+        let aliases = aliases(&[("ll", "ls -la"), ("g", "git")]);
+        assert_eq!(expand("ll /tmp | g log; x=1 ll && echo ll", &aliases), "ls -la /tmp | git log; x=1 ls -la && echo ll");
+        assert_eq!(expand("if ll; then 'll'; fi > ll", &aliases), "if ls -la; then 'll'; fi > ll");
+        assert_eq!(expand("case ll in ll) ll;; g|ll) ;; esac", &aliases), "case ll in ll) ls -la;; g|ll) ;; esac");
+    }
+
+    #[test]
+    fn stops_recursion() {
+        let aliases = aliases(&[("ls", "ls -F"), ("a", "b"), ("b", "a x")]);
+        assert_eq!(expand("ls", &aliases), "ls -F");
+        assert_eq!(expand("a", &aliases), "a x");
+    }
+
+    #[test]
+    fn continues_after_a_trailing_blank() {
+        let aliases = aliases(&[("sudo", "sudo "), ("ll", "ls -la"), ("e", "echo")]);
+        assert_eq!(expand("sudo ll", &aliases), "sudo  ls -la");
+        assert_eq!(expand("e ll", &aliases), "echo ll");
+    }
+}
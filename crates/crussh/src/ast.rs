@@ -0,0 +1,200 @@
+//! The parse tree produced by [`parse`](crate::parse).
+//!
+//! A command line is a [`List`] of and-or lists ([`AndOr`]), each made of
+//! [`Pipeline`]s, whose stages are [`Command`]s: simple commands, compound
+//! commands such as `if` or `{ ...; }`, and function definitions. Words keep
+//! their quoting as [`WordPart`]s, so tools can tell `$x` from `"$x"` and
+//! `'*'` from `*`.
+//!
+//! Every node implements `Display`, printing it back as normalized shell
+//! source that parses to the same tree.
+//!
+//! Use the [`visit::Visitor`] trait to walk a tree without matching on
+//! every node yourself.
+//!
+//! With the `serde` feature enabled every node implements `Serialize` and
+//! `Deserialize`, and a deserialized tree can be run through
+//! [`Parse::from`](crate::Parse).
+
+mod command;
+mod compound;
+mod format;
+mod list;
+mod pipeline;
+mod redirect;
+mod simple;
+mod word;
+pub mod visit;
+
+pub use command::{Command, FunctionDef};
+pub use compound::{CaseClause, CaseItem, CompoundCommand, ForClause, IfBranch, IfClause, LoopClause};
+pub use list::{AndOr, Connector, List, ListItem};
+pub use pipeline::{Pipeline, Timing};
+pub use redirect::{Redirect, RedirectOp};
+pub use simple::{Assignment, SimpleCommand};
+pub use word::{Word, WordPart};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ParseError, parse};
+
+    fn literal(text: &str) -> Word {
+        Word { parts: vec![WordPart::Literal(text.to_string())] }
+    }
+
+    fn simple(words: &[&str], redirects: Vec<Redirect>) -> Command {
+        Command::Simple(SimpleCommand {
+            assignments: Vec::new(),
+            words: words.iter().map(|w| literal(w)).collect(),
+            redirects,
+        })
+    }
+
+    fn redirect(op: RedirectOp, target: &str) -> Redirect {
+        Redirect { fd: None, op, target: literal(target) }
+    }
+
+    fn pipeline(commands: Vec<Command>) -> List {
+        List {
+            items: vec![ListItem {
+                and_or: AndOr { first: Pipeline { time: None, negated: false, commands }, rest: Vec::new() },
+                background: false,
+            }],
+        }
+    }
+
+    fn ast(s: &str) -> List {
+        parse(s).unwrap().into_ast()
+    }
+
+    #[test]
+    fn parse_simple_command() {
+        assert_eq!(ast("ls"), pipeline(vec![simple(&["ls"], vec![])]));
+    }
+
+    #[test]
+    fn parse_command_with_args() {
+        assert_eq!(ast("ls -l -a"), pipeline(vec![simple(&["ls", "-l", "-a"], vec![])]));
+    }
+
+    #[test]
+    fn parse_command_with_redirects() {
+        assert_eq!(
+            ast("wc < hello.c -a >out.txt"),
+            pipeline(vec![simple(
+                &["wc", "-a"],
+                vec![redirect(RedirectOp::Input, "hello.c"), redirect(RedirectOp::Output, "out.txt")],
+            )])
+        );
+    }
+
+    #[test]
+    fn parse_command_with_pipes() {
+        let mut grep = simple(&["grep"], vec![]);
+        if let Command::Simple(grep) = &mut grep {
+            grep.words.push(Word { parts: vec![WordPart::Quoted("hello world.c".to_string())] });
+        }
+        assert_eq!(
+            ast("ls -la | grep \"hello world.c\" | wc > hello.rs"),
+            pipeline(vec![
+                simple(&["ls", "-la"], vec![]),
+                grep,
+                simple(&["wc"], vec![redirect(RedirectOp::Output, "hello.rs")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_background() {
+        let list = ast("sleep 1 &");
+        assert!(list.items[0].background);
+
+        let backgrounds = |s| ast(s).items.iter().map(|item| item.background).collect::<Vec<_>>();
+        assert_eq!(backgrounds("sleep 5 & echo hi"), [true, false]);
+        assert_eq!(backgrounds("a | b & c && d &\ne; f &"), [true, true, false, true]);
+        assert!(matches!(
+            &ast("{ a & b & }").items[0].and_or.first.commands[0],
+            Command::Compound { command: CompoundCommand::BraceGroup(list), .. } if list.items.iter().all(|item| item.background)
+        ));
+        assert_eq!(parse("a & ; b").unwrap_err(), ParseError::Unexpected(";".to_string()));
+    }
+
+    #[test]
+    fn parse_time() {
+        let timings = |s| ast(s).items.iter().map(|item| (item.and_or.first.time, item.and_or.first.negated)).collect::<Vec<_>>();
+        assert_eq!(
+            timings("time a | b; time -p ! c; time -pq; d"),
+            [
+                (Some(Timing::Default), false),
+                (Some(Timing::Posix), true),
+                (Some(Timing::Default), false),
+                (None, false),
+            ]
+        );
+        assert!(matches!(&ast("time -pq").items[0].and_or.first.commands[0], Command::Simple(simple) if simple.words.len() == 1));
+
+        let bare = |s| ast(s).items.iter().map(|item| item.and_or.first.commands.len()).collect::<Vec<_>>();
+        assert_eq!(timings("! time c; time; ! time -p"), [(Some(Timing::Default), true), (Some(Timing::Default), false), (Some(Timing::Posix), true)]);
+        assert_eq!(bare("! time c; time; ! time -p\ntime &"), [1, 0, 0, 0]);
+        assert_eq!(bare("{ time; } && (time) || time || x"), [1]);
+        assert_eq!(parse("time |").unwrap_err(), ParseError::Unexpected("|".to_string()));
+    }
+
+    #[test]
+    fn parse_quoting() {
+        let list = ast("echo 'a'b\"$x\"$y");
+        let command = &list.items[0].and_or.first.commands[0];
+        let word = match command {
+            Command::Simple(simple) => &simple.words[1],
+            _ => panic!("not a simple command"),
+        };
+        assert_eq!(
+            word.parts,
+            vec![
+                WordPart::Literal("ab".to_string()),
+                WordPart::Parameter { name: "x".to_string(), quoted: true },
+                WordPart::Parameter { name: "y".to_string(), quoted: false },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_compound_commands() {
+        let list = ast("if true; then echo a; elif false; then :; else echo b; fi && f() { echo c; }");
+        let and_or = &list.items[0].and_or;
+        match &and_or.first.commands[0] {
+            Command::Compound { command: CompoundCommand::If(clause), .. } => {
+                assert_eq!(clause.branches.len(), 2);
+                assert!(clause.else_body.is_some());
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(and_or.rest[0].0, Connector::And);
+        assert!(matches!(&and_or.rest[0].1.commands[0], Command::FunctionDef(def) if def.name == "f"));
+
+        assert!(matches!(
+            &ast("for i in a b; do echo $i; done").items[0].and_or.first.commands[0],
+            Command::Compound { command: CompoundCommand::For(ForClause { words: Some(words), .. }), .. } if words.len() == 2
+        ));
+        assert!(matches!(
+            &ast("case x in a|b) echo;; *) ;; esac").items[0].and_or.first.commands[0],
+            Command::Compound { command: CompoundCommand::Case(clause), .. } if clause.items.len() == 2
+        ));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("echo 'unterminated").unwrap_err().is_incomplete());
+        assert!(parse("if true; then echo").unwrap_err().is_incomplete());
+        assert_eq!(parse("ls | | wc").unwrap_err(), ParseError::Unexpected("|".to_string()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let list = ast("for f in *.rs \"$HOME\"; do wc -l < $f 2>&1 || echo \"no $f\"; done");
+        let json = serde_json::to_string(&list).unwrap();
+        assert_eq!(serde_json::from_str::<List>(&json).unwrap(), list);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::{ParseError, ast::{CompoundCommand, Redirect, SimpleCommand}, utils};
+
+/// One stage of a [`Pipeline`](crate::ast::Pipeline).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Command {
+    Simple(SimpleCommand),
+    Compound { command: CompoundCommand, redirects: Vec<Redirect> },
+    FunctionDef(FunctionDef),
+}
+
+/// A `name() compound-command` function definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FunctionDef {
+    pub name: String,
+    pub body: CompoundCommand,
+    pub redirects: Vec<Redirect>,
+}
+
+impl Command {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+
+        match utils::peek_reserved(s) {
+            Some("{" | "if" | "while" | "until" | "for" | "case") => {},
+            Some(_) => return Err(utils::unexpected(s)),
+            None if s.starts_with('(') => {},
+            None if FunctionDef::starts(s) => {
+                let (s, function) = FunctionDef::new(s)?;
+                return Ok((s, Self::FunctionDef(function)));
+            },
+            None => {
+                let (s, simple) = SimpleCommand::new(s)?;
+                return Ok((s, Self::Simple(simple)));
+            }
+        }
+
+        let (s, command) = CompoundCommand::new(s)?;
+        let (s, redirects) = Redirect::many(s)?;
+        Ok((s, Self::Compound { command, redirects }))
+    }
+}
+
+impl FunctionDef {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, name) = utils::extract_name(s)?;
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag("(", s)?;
+        let (s, _) = utils::extract_whitespace(s);
+        let s = utils::tag(")", s)?;
+        let (s, _) = utils::extract_linebreak(s);
+
+        let (s, body) = CompoundCommand::new(s)?;
+        let (s, redirects) = Redirect::many(s)?;
+
+        Ok((s, Self { name: name.to_string(), body, redirects }))
+    }
+
+    pub(crate) fn starts(s: &str) -> bool {
+        match utils::extract_name(s) {
+            Ok((rest, _)) => utils::extract_whitespace(rest).0.starts_with('('),
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{ParseError, ast::{List, Word}, utils};
+
+/// A command built from other commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompoundCommand {
+    /// `{ list; }`
+    BraceGroup(List),
+    /// `( list )`, run in a child process.
+    Subshell(List),
+    If(IfClause),
+    /// `while condition; do body; done`
+    While(LoopClause),
+    /// `until condition; do body; done`
+    Until(LoopClause),
+    For(ForClause),
+    Case(CaseClause),
+}
+
+/// `if ...; then ...; elif ...; then ...; else ...; fi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfClause {
+    /// The `if` branch followed by any `elif` branches.
+    pub branches: Vec<IfBranch>,
+    pub else_body: Option<List>,
+}
+
+/// A condition and the list it guards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfBranch {
+    pub condition: List,
+    pub body: List,
+}
+
+/// The condition and body of a `while` or `until` loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LoopClause {
+    pub condition: List,
+    pub body: List,
+}
+
+/// `for name in words; do body; done`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForClause {
+    pub name: String,
+    /// `None` when the `in` part is left out and `"$@"` is iterated.
+    pub words: Option<Vec<Word>>,
+    pub body: List,
+}
+
+/// `case word in pattern) body;; esac`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseClause {
+    pub word: Word,
+    pub items: Vec<CaseItem>,
+}
+
+/// One `pattern | pattern) body ;;` arm of a [`CaseClause`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: List,
+}
+
+impl CompoundCommand {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, _) = utils::extract_whitespace(s);
+
+        if let Some(s) = s.strip_prefix('(') {
+            let (s, list) = body(s)?;
+            let (s, _) = utils::extract_linebreak(s);
+            let s = utils::tag(")", s).map_err(|_| close_error(s, "`)'"))?;
+            return Ok((s, Self::Subshell(list)));
+        }
+
+        match utils::peek_reserved(s) {
+            Some("{") => {
+                let (s, list) = body(utils::reserved("{", s)?)?;
+                let s = utils::reserved("}", s)?;
+                Ok((s, Self::BraceGroup(list)))
+            },
+            Some("if") => IfClause::new(s).map(|(s, clause)| (s, Self::If(clause))),
+            Some("while") => LoopClause::new("while", s).map(|(s, clause)| (s, Self::While(clause))),
+            Some("until") => LoopClause::new("until", s).map(|(s, clause)| (s, Self::Until(clause))),
+            Some("for") => ForClause::new(s).map(|(s, clause)| (s, Self::For(clause))),
+            Some("case") => CaseClause::new(s).map(|(s, clause)| (s, Self::Case(clause))),
+            _ => Err(utils::unexpected(s)),
+        }
+    }
+}
+
+impl IfClause {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut branches = Vec::new();
+        let mut s = utils::reserved("if", s)?;
+
+        loop {
+            let (rest, condition) = body(s)?;
+            let (rest, then_body) = body(utils::reserved("then", rest)?)?;
+            branches.push(IfBranch { condition, body: then_body });
+
+            let (rest, _) = utils::extract_linebreak(rest);
+            match utils::peek_reserved(rest) {
+                Some("elif") => s = utils::reserved("elif", rest)?,
+                Some("else") => {
+                    let (rest, else_body) = body(utils::reserved("else", rest)?)?;
+                    let rest = utils::reserved("fi", rest)?;
+                    return Ok((rest, Self { branches, else_body: Some(else_body) }));
+                },
+                _ => {
+                    let rest = utils::reserved("fi", rest)?;
+                    return Ok((rest, Self { branches, else_body: None }));
+                }
+            }
+        }
+    }
+}
+
+impl LoopClause {
+
+    pub(crate) fn new<'a>(keyword: &str, s: &'a str) -> Result<(&'a str, Self), ParseError> {
+        let s = utils::reserved(keyword, s)?;
+        let (s, condition) = body(s)?;
+        let (s, do_body) = do_group(s)?;
+
+        Ok((s, Self { condition, body: do_body }))
+    }
+}
+
+impl ForClause {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let s = utils::reserved("for", s)?;
+        let (s, _) = utils::extract_whitespace(s);
+        let (s, name) = utils::extract_name(s)?;
+        let (s, _) = utils::extract_linebreak(s);
+
+        let (s, words) = if utils::peek_reserved(s) == Some("in") {
+            let mut s = utils::reserved("in", s)?;
+            let mut words = Vec::new();
+            loop {
+                let (rest, _) = utils::extract_whitespace(s);
+                if rest.is_empty() || rest.starts_with(utils::is_metachar) {
+                    s = separator(rest)?;
+                    break;
+                }
+                let (rest, word) = Word::new(rest)?;
+                words.push(word);
+                s = rest;
+            }
+            (s, Some(words))
+        } else {
+            (s.strip_prefix(';').unwrap_or(s), None)
+        };
+
+        let (s, body) = do_group(s)?;
+        Ok((s, Self { name: name.to_string(), words, body }))
+    }
+}
+
+impl CaseClause {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let s = utils::reserved("case", s)?;
+        let (s, _) = utils::extract_whitespace(s);
+        let (s, word) = Word::new(s).map_err(|_| utils::unexpected(s))?;
+        let mut s = utils::reserved("in", s)?;
+
+        let mut items = Vec::new();
+        loop {
+            let (rest, _) = utils::extract_linebreak(s);
+            if utils::peek_reserved(rest) == Some("esac") {
+                s = utils::reserved("esac", rest)?;
+                break;
+            }
+
+            let (rest, item) = CaseItem::new(rest)?;
+            items.push(item);
+
+            let (rest, _) = utils::extract_linebreak(rest);
+            match rest.strip_prefix(";;") {
+                Some(rest) => s = rest,
+                None => {
+                    s = utils::reserved("esac", rest)?;
+                    break;
+                }
+            }
+        }
+
+        Ok((s, Self { word, items }))
+    }
+}
+
+impl CaseItem {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut s = s.strip_prefix('(').unwrap_or(s);
+        let mut patterns = Vec::new();
+
+        loop {
+            let (rest, _) = utils::extract_whitespace(s);
+            let (rest, pattern) = Word::new(rest).map_err(|_| utils::unexpected(rest))?;
+            patterns.push(pattern);
+
+            let (rest, _) = utils::extract_whitespace(rest);
+            match rest.strip_prefix('|') {
+                Some(rest) => s = rest,
+                None => {
+                    s = utils::tag(")", rest).map_err(|_| close_error(rest, "`)'"))?;
+                    break;
+                }
+            }
+        }
+
+        let (s, body) = List::new(s)?;
+        Ok((s, Self { patterns, body }))
+    }
+}
+
+/// A list that must contain at least one command.
+fn body(s: &str) -> Result<(&str, List), ParseError> {
+    let (rest, list) = List::new(s)?;
+    if list.items.is_empty() {
+        let (rest, _) = utils::extract_linebreak(rest);
+        return Err(utils::unexpected(rest));
+    }
+    Ok((rest, list))
+}
+
+fn do_group(s: &str) -> Result<(&str, List), ParseError> {
+    let (s, list) = body(utils::reserved("do", s)?)?;
+    let s = utils::reserved("done", s)?;
+    Ok((s, list))
+}
+
+/// Consumes the `;` or newline ending the word list of a `for`.
+fn separator(s: &str) -> Result<&str, ParseError> {
+    match s.chars().next() {
+        Some(';') | Some('\n') => Ok(&s[1..]),
+        None => Err(ParseError::Incomplete("`do'".to_string())),
+        Some(_) => Err(utils::unexpected(s)),
+    }
+}
+
+fn close_error(s: &str, what: &str) -> ParseError {
+    if s.is_empty() {
+        ParseError::Incomplete(what.to_string())
+    } else {
+        utils::unexpected(s)
+    }
+}
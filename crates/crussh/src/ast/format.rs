@@ -0,0 +1,580 @@
+//! Printing a parse tree back as shell source.
+//!
+//! The output is normalized: one space around `|`, `&&` and `||`, a space
+//! between a file redirection operator and its target, each entry of a
+//! compound command's body on its own line indented by four spaces, and
+//! the least quoting that keeps every word parsing back to the same parts.
+//! Parsing the output of any tree produced by [`parse`](crate::parse) gives
+//! that tree back. The original layout is not kept, and neither are
+//! comments, which the parser skips; see [`comment_line`](crate::comment_line).
+
+use std::fmt;
+
+use crate::{
+    ast::{
+        AndOr, Assignment, CaseClause, Command, CompoundCommand, Connector, FunctionDef, List,
+        Pipeline, Redirect, RedirectOp, SimpleCommand, Timing, Word, WordPart,
+    },
+    utils,
+};
+
+const INDENT: &str = "    ";
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    depth: usize,
+}
+
+impl Printer {
+
+    fn newline(&mut self) {
+        self.out.push('\n');
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Writes each entry of `list` on its own line.
+    fn block(&mut self, list: &List) {
+        for (i, item) in list.items.iter().enumerate() {
+            if i > 0 {
+                self.newline();
+            }
+            self.and_or(&item.and_or);
+            if item.background {
+                self.out.push_str(" &");
+            }
+        }
+    }
+
+    /// Writes `list` on one line, as in the condition of an `if`, followed
+    /// by the separator needed before the next reserved word.
+    fn inline(&mut self, list: &List) {
+        for item in &list.items {
+            self.and_or(&item.and_or);
+            self.out.push_str(if item.background { " & " } else { "; " });
+        }
+    }
+
+    /// Writes `list` indented on the lines after the current one, leaving
+    /// the cursor on a fresh line at the current depth.
+    fn body(&mut self, list: &List) {
+        self.depth += 1;
+        if !list.items.is_empty() {
+            self.newline();
+            self.block(list);
+        }
+        self.depth -= 1;
+        self.newline();
+    }
+
+    fn and_or(&mut self, and_or: &AndOr) {
+        self.pipeline(&and_or.first);
+        for (connector, pipeline) in &and_or.rest {
+            self.out.push_str(match connector {
+                Connector::And => " && ",
+                Connector::Or => " || ",
+            });
+            self.pipeline(pipeline);
+        }
+    }
+
+    fn pipeline(&mut self, pipeline: &Pipeline) {
+        if pipeline.commands.is_empty() {
+            // A bare `time`.
+            if pipeline.negated {
+                self.out.push_str("! ");
+            }
+            match pipeline.time {
+                Some(Timing::Posix) => self.out.push_str("time -p"),
+                _ => self.out.push_str("time"),
+            }
+            return;
+        }
+        match pipeline.time {
+            Some(Timing::Default) => self.out.push_str("time "),
+            Some(Timing::Posix) => self.out.push_str("time -p "),
+            None => {},
+        }
+        if pipeline.negated {
+            self.out.push_str("! ");
+        }
+        for (i, command) in pipeline.commands.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(" | ");
+            }
+            self.command(command);
+        }
+    }
+
+    fn command(&mut self, command: &Command) {
+        match command {
+            Command::Simple(simple) => self.simple(simple),
+            Command::Compound { command, redirects } => {
+                self.compound(command);
+                self.redirects(redirects);
+            },
+            Command::FunctionDef(function) => self.function(function),
+        }
+    }
+
+    fn simple(&mut self, command: &SimpleCommand) {
+        let mut tokens = Vec::new();
+        for assignment in &command.assignments {
+            tokens.push(assignment.to_string());
+        }
+        for (i, word) in command.words.iter().enumerate() {
+            let mut token = String::new();
+            if i == 0 && command_name_needs_escape(word, command.assignments.is_empty()) {
+                token.push('\\');
+            }
+            write_word(&mut token, word, true);
+            tokens.push(token);
+        }
+        for redirect in &command.redirects {
+            tokens.push(redirect.to_string());
+        }
+        self.out.push_str(&tokens.join(" "));
+    }
+
+    fn redirects(&mut self, redirects: &[Redirect]) {
+        for redirect in redirects {
+            self.out.push(' ');
+            self.out.push_str(&redirect.to_string());
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDef) {
+        self.out.push_str(&function.name);
+        self.out.push_str("() ");
+        self.compound(&function.body);
+        self.redirects(&function.redirects);
+    }
+
+    fn compound(&mut self, command: &CompoundCommand) {
+        match command {
+            CompoundCommand::BraceGroup(list) => {
+                self.out.push('{');
+                self.body(list);
+                self.out.push('}');
+            },
+            CompoundCommand::Subshell(list) => {
+                self.out.push('(');
+                self.body(list);
+                self.out.push(')');
+            },
+            CompoundCommand::If(clause) => {
+                for (i, branch) in clause.branches.iter().enumerate() {
+                    self.out.push_str(if i == 0 { "if " } else { "elif " });
+                    self.inline(&branch.condition);
+                    self.out.push_str("then");
+                    self.body(&branch.body);
+                }
+                if let Some(else_body) = &clause.else_body {
+                    self.out.push_str("else");
+                    self.body(else_body);
+                }
+                self.out.push_str("fi");
+            },
+            CompoundCommand::While(clause) | CompoundCommand::Until(clause) => {
+                let keyword = if matches!(command, CompoundCommand::While(_)) { "while " } else { "until " };
+                self.out.push_str(keyword);
+                self.inline(&clause.condition);
+                self.out.push_str("do");
+                self.body(&clause.body);
+                self.out.push_str("done");
+            },
+            CompoundCommand::For(clause) => {
+                self.out.push_str("for ");
+                self.out.push_str(&clause.name);
+                if let Some(words) = &clause.words {
+                    self.out.push_str(" in");
+                    for word in words {
+                        self.out.push(' ');
+                        write_word(&mut self.out, word, true);
+                    }
+                }
+                self.out.push_str("; do");
+                self.body(&clause.body);
+                self.out.push_str("done");
+            },
+            CompoundCommand::Case(clause) => self.case(clause),
+        }
+    }
+
+    fn case(&mut self, clause: &CaseClause) {
+        self.out.push_str("case ");
+        write_word(&mut self.out, &clause.word, true);
+        self.out.push_str(" in");
+
+        self.depth += 1;
+        for item in &clause.items {
+            self.newline();
+            for (i, pattern) in item.patterns.iter().enumerate() {
+                if i > 0 {
+                    self.out.push_str(" | ");
+                }
+                // `esac` first in an arm would close the whole `case`.
+                if i == 0 && pattern.as_literal() == Some("esac") {
+                    self.out.push('\\');
+                }
+                write_word(&mut self.out, pattern, true);
+            }
+            self.out.push(')');
+            self.body(&item.body);
+            self.out.push_str(INDENT);
+            self.out.push_str(";;");
+        }
+        self.depth -= 1;
+
+        self.newline();
+        self.out.push_str("esac");
+    }
+}
+
+/// Whether the first word of a simple command would be read as a reserved
+/// word or an assignment if written as is. Escaping its first character
+/// with a backslash avoids both without changing the parsed word, since
+/// that character is a letter.
+fn command_name_needs_escape(word: &Word, first_token: bool) -> bool {
+    let text = match word.parts.first() {
+        Some(WordPart::Literal(text)) => text,
+        _ => return false,
+    };
+    let reserved = first_token
+        && word.parts.len() == 1
+        && utils::is_reserved(text)
+        && text.starts_with(|c: char| c.is_ascii_alphabetic());
+    let assignment = matches!(utils::extract_name(text), Ok((rest, _)) if rest.starts_with('='));
+    reserved || assignment
+}
+
+/// Appends `word`, quoting only what needs it. `token_start` is set when
+/// the word begins a token, where a leading `#` would start a comment.
+fn write_word(out: &mut String, word: &Word, token_start: bool) {
+    if word.parts.is_empty() {
+        out.push_str("''");
+        return;
+    }
+
+    let parts = &word.parts;
+    let mut i = 0;
+    while i < parts.len() {
+        match &parts[i] {
+            WordPart::Literal(text) => {
+                write_literal(out, text, token_start && i == 0);
+                i += 1;
+            },
+            WordPart::Parameter { name, quoted: false } => {
+                write_parameter(out, name, parts.get(i + 1).and_then(first_char));
+                i += 1;
+            },
+            _ => {
+                let end = parts[i..].iter()
+                    .position(|part| !is_quoted(part))
+                    .map_or(parts.len(), |n| i + n);
+                write_quoted(out, &parts[i..end]);
+                i = end;
+            }
+        }
+    }
+}
+
+fn is_quoted(part: &WordPart) -> bool {
+    matches!(part, WordPart::Quoted(_) | WordPart::Parameter { quoted: true, .. })
+}
+
+fn first_char(part: &WordPart) -> Option<char> {
+    match part {
+        WordPart::Literal(text) | WordPart::Quoted(text) => text.chars().next(),
+        WordPart::Parameter { .. } => Some('$'),
+    }
+}
+
+fn write_literal(out: &mut String, text: &str, at_start: bool) {
+    for (i, c) in text.chars().enumerate() {
+        if c == '\n' {
+            out.push_str("'\n'");
+            continue;
+        }
+        if utils::is_metachar(c) || "'\"\\$`".contains(c) || (c == '#' && i == 0 && at_start) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+/// Writes `$name`, adding braces when the name would otherwise run into
+/// the character that follows it.
+fn write_parameter(out: &mut String, name: &str, next: Option<char>) {
+    let runs_on = utils::is_name(name) && next.is_some_and(|c| c == '_' || c.is_ascii_alphanumeric());
+    if runs_on || (name.len() > 1 && !utils::is_name(name)) {
+        out.push_str(&format!("${{{}}}", name));
+    } else {
+        out.push('$');
+        out.push_str(name);
+    }
+}
+
+/// Writes a run of quoted text and quoted parameters.
+fn write_quoted(out: &mut String, parts: &[WordPart]) {
+    let has_parameter = parts.iter().any(|part| matches!(part, WordPart::Parameter { .. }));
+    if let (false, [WordPart::Quoted(text)]) = (has_parameter, parts) {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c != '\n' => {
+                out.push('\\');
+                out.push(c);
+                return;
+            },
+            _ if !text.contains('\'') => {
+                out.push('\'');
+                out.push_str(text);
+                out.push('\'');
+                return;
+            },
+            _ => {}
+        }
+    }
+
+    out.push('"');
+    for (i, part) in parts.iter().enumerate() {
+        match part {
+            WordPart::Parameter { name, .. } => {
+                write_parameter(out, name, parts.get(i + 1).and_then(first_char));
+            },
+            WordPart::Quoted(text) | WordPart::Literal(text) => {
+                for c in text.chars() {
+                    if "$`\"\\".contains(c) {
+                        out.push('\\');
+                    }
+                    out.push(c);
+                }
+            }
+        }
+    }
+    out.push('"');
+}
+
+fn display(f: &mut fmt::Formatter<'_>, print: impl FnOnce(&mut Printer)) -> fmt::Result {
+    let mut printer = Printer::default();
+    print(&mut printer);
+    f.write_str(&printer.out)
+}
+
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.block(self))
+    }
+}
+
+impl fmt::Display for AndOr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.and_or(self))
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.pipeline(self))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.command(self))
+    }
+}
+
+impl fmt::Display for SimpleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.simple(self))
+    }
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.compound(self))
+    }
+}
+
+impl fmt::Display for FunctionDef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        display(f, |p| p.function(self))
+    }
+}
+
+impl fmt::Display for Assignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = format!("{}=", self.name);
+        if !self.value.parts.is_empty() {
+            write_word(&mut out, &self.value, false);
+        }
+        f.write_str(&out)
+    }
+}
+
+impl fmt::Display for Redirect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        if let Some(fd) = self.fd {
+            out.push_str(&fd.to_string());
+        }
+        out.push_str(self.op.as_str());
+        if !matches!(self.op, RedirectOp::DupInput | RedirectOp::DupOutput) {
+            out.push(' ');
+        }
+        write_word(&mut out, &self.target, true);
+        f.write_str(&out)
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        write_word(&mut out, self, true);
+        f.write_str(&out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{collection::vec, option, prelude::*};
+
+    use crate::{comment_line, parse, utils};
+
+    fn format(s: &str) -> String {
+        parse(s).unwrap().ast().to_string()
+    }
+
+    #[test]
+    fn normalizes_spacing() {
+        assert_eq!(format("ls   -la|grep  foo>out   2>&1&&wc<in"), "ls -la | grep foo > out 2>&1 && wc < in");
+        assert_eq!(format("a;b\n\nc &"), "a\nb\nc &");
+        assert_eq!(format("! time  ls;time;time  -p !"), "time ! ls\ntime\n! time -p");
+    }
+
+    #[test]
+    fn quotes_minimally() {
+        assert_eq!(format("echo 'abc' \"x\"y 'a b' \"$HOME\"/bin \"it's\" ''"), "echo abc xy 'a b' \"$HOME\"/bin \"it's\" ''");
+        assert_eq!(format("echo \\* ${x}y \"${1}0\" ${10}"), "echo \\* ${x}y \"$1\"0 ${10}");
+        assert_eq!(format("'if' x; 'a'=b c"), "\\if x\n\\a=b c");
+    }
+
+    #[test]
+    fn finds_comments() {
+        assert_eq!(comment_line("ls\n# list\n"), Some(2));
+        assert_eq!(comment_line("echo a;#b"), Some(1));
+        assert_eq!(comment_line("echo '\n#' \"#\" \\# a#b $# ${#x}"), None);
+    }
+
+    #[test]
+    fn indents_compound_commands() {
+        assert_eq!(
+            format("if true; then for i in a b; do echo $i; done; else f() { :; }; fi"),
+            "if true; then\n    for i in a b; do\n        echo $i\n    done\nelse\n    f() {\n        :\n    }\nfi"
+        );
+        assert_eq!(
+            format("case $x in a|b) echo;; *) esac"),
+            "case $x in\n    a | b)\n        echo\n        ;;\n    *)\n        ;;\nesac"
+        );
+    }
+
+    fn word() -> impl Strategy<Value = String> {
+        let fragment = prop_oneof![
+            "[a-z0-9_./=-]{1,3}",
+            "'[a-z $|;&\"\\\\*]{0,4}'",
+            "\"([a-z |;'*]|\\\\[$\"\\\\a]|\\$[a-z])*\"",
+            "\\$\\{?[a-z_][a-z0-9]?\\}?".prop_filter("unbalanced braces", |s| s.contains('{') == s.contains('}')),
+            "\\$[0-9?#]",
+            "\\\\[ $'\"|;&*a]",
+        ];
+        vec(fragment, 1..4)
+            .prop_map(|fragments| fragments.concat())
+            .prop_filter("reserved word", |word| !utils::is_reserved(word))
+    }
+
+    fn name() -> impl Strategy<Value = String> {
+        "[a-z_][a-z0-9_]{0,3}".prop_filter("reserved word", |name| !utils::is_reserved(name))
+    }
+
+    fn redirect() -> impl Strategy<Value = String> {
+        (option::of(0..10u8), prop_oneof![Just("<"), Just(">"), Just(">|"), Just(">>"), Just("<>"), Just("<&"), Just(">&")], word())
+            .prop_map(|(fd, op, target)| format!("{}{} {}", fd.map(|fd| fd.to_string()).unwrap_or_default(), op, target))
+    }
+
+    fn simple() -> impl Strategy<Value = String> {
+        let assignment = (name(), option::of(word())).prop_map(|(name, value)| format!("{}={}", name, value.unwrap_or_default()));
+        let command = prop_oneof![Just("echo"), Just("cat"), Just("x")];
+        (vec(assignment, 0..2), command, vec(word(), 0..3), vec(redirect(), 0..2)).prop_map(|(assignments, command, words, redirects)| {
+            let mut tokens = assignments;
+            tokens.push(command.to_string());
+            tokens.extend(words);
+            tokens.extend(redirects);
+            tokens.join(" ")
+        })
+    }
+
+    fn list(command: BoxedStrategy<String>) -> impl Strategy<Value = String> {
+        let time = prop_oneof![Just(""), Just("time "), Just("time -p ")];
+        let pipeline = (time, any::<bool>(), vec(command, 0..3)).prop_map(|(time, negated, commands)| {
+            let negated = if negated { "! " } else { "" };
+            if commands.is_empty() {
+                // A bare `time`.
+                let time = if time.is_empty() { "time" } else { time.trim_end() };
+                format!("{}{}", negated, time)
+            } else {
+                format!("{}{}{}", time, negated, commands.join(" | "))
+            }
+        });
+        let and_or = (pipeline.clone(), vec((prop_oneof![Just(" && "), Just(" ||\n")], pipeline), 0..2))
+            .prop_map(|(first, rest)| rest.into_iter().fold(first, |s, (op, p)| s + op + &p));
+        (vec(and_or, 1..3), prop_oneof![Just("; "), Just("\n"), Just(" & ")])
+            .prop_map(|(items, separator)| items.join(separator))
+    }
+
+    fn command() -> BoxedStrategy<String> {
+        simple().boxed().prop_recursive(3, 24, 3, |command| {
+            let list = list(command).boxed();
+            prop_oneof![
+                list.clone().prop_map(|l| format!("{{ {}; }}", l)),
+                list.clone().prop_map(|l| format!("({})", l)),
+                (list.clone(), list.clone(), option::of(list.clone()))
+                    .prop_map(|(c, b, e)| match e {
+                        Some(e) => format!("if {}; then {}; elif {}; then :; else {}; fi", c, b, c, e),
+                        None => format!("if {}\nthen {}\nfi > out", c, b),
+                    }),
+                (any::<bool>(), list.clone(), list.clone())
+                    .prop_map(|(until, c, b)| format!("{} {}; do {}; done", if until { "until" } else { "while" }, c, b)),
+                (name(), option::of(vec(word(), 0..3)), list.clone()).prop_map(|(n, words, b)| match words {
+                    Some(words) => format!("for {} in {}; do {}; done", n, words.join(" "), b),
+                    None => format!("for {}\ndo {}; done", n, b),
+                }),
+                (word(), vec((vec(word(), 1..3), option::of(list.clone())), 0..3)).prop_map(|(w, items)| {
+                    let items: Vec<String> = items.into_iter()
+                        .map(|(patterns, body)| format!("{}) {};;", patterns.join("|"), body.unwrap_or_default()))
+                        .collect();
+                    format!("case {} in {} esac", w, items.join(" "))
+                }),
+                (name(), list).prop_map(|(n, b)| format!("{}() {{ {}; }} 2> err", n, b)),
+            ]
+        }).boxed()
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(128))]
+
+        #[test]
+        fn format_round_trips(source in list(command()), background in any::<bool>()) {
+            let source = if background { source + " &" } else { source };
+            let ast = parse(&source).map_err(|e| TestCaseError::fail(format!("{}: {}", source, e)))?.into_ast();
+
+            let formatted = ast.to_string();
+            let reparsed = parse(&formatted).map_err(|e| TestCaseError::fail(format!("{}: {}", formatted, e)))?;
+            prop_assert_eq!(reparsed.ast(), &ast, "formatted as:\n{}", formatted);
+            prop_assert_eq!(comment_line(&formatted), None);
+            prop_assert_eq!(reparsed.ast().to_string(), formatted);
+        }
+    }
+}
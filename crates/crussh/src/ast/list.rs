@@ -0,0 +1,104 @@
+use crate::{ParseError, ast::Pipeline, utils};
+
+/// A sequence of and-or lists separated by `;`, `&` or newlines. This is
+/// what a whole command line parses into, as well as the body of every
+/// compound command.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct List {
+    pub items: Vec<ListItem>,
+}
+
+/// One entry of a [`List`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ListItem {
+    pub and_or: AndOr,
+    /// Whether the entry was terminated by `&`.
+    pub background: bool,
+}
+
+/// Pipelines joined by `&&` and `||`, evaluated left to right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+/// The operator between two pipelines of an [`AndOr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connector {
+    /// `&&`
+    And,
+    /// `||`
+    Or,
+}
+
+impl List {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut items = Vec::new();
+        let (mut s, _) = utils::extract_linebreak(s);
+
+        while !Self::at_end(s) {
+            let (rest, and_or) = AndOr::new(s)?;
+            let (rest, _) = utils::extract_whitespace(rest);
+
+            let mut chars = rest.chars();
+            let (rest, background, done) = match chars.next() {
+                Some('&') => (chars.as_str(), true, false),
+                Some(';') if !rest.starts_with(";;") => (chars.as_str(), false, false),
+                Some('\n') => (chars.as_str(), false, false),
+                _ => (rest, false, true),
+            };
+
+            items.push(ListItem { and_or, background });
+            s = rest;
+            if done {
+                break;
+            }
+            s = utils::extract_linebreak(s).0;
+        }
+
+        Ok((s, Self { items }))
+    }
+
+    /// Whether `s` starts with something that closes the enclosing construct.
+    fn at_end(s: &str) -> bool {
+        s.is_empty()
+            || s.starts_with(')')
+            || s.starts_with(";;")
+            || matches!(
+                utils::peek_reserved(s),
+                Some("then" | "elif" | "else" | "fi" | "do" | "done" | "esac" | "}")
+            )
+    }
+}
+
+impl AndOr {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (mut s, first) = Pipeline::new(s)?;
+        let mut rest = Vec::new();
+
+        loop {
+            let (r, _) = utils::extract_whitespace(s);
+            let (r, connector) = if let Some(r) = r.strip_prefix("&&") {
+                (r, Connector::And)
+            } else if let Some(r) = r.strip_prefix("||") {
+                (r, Connector::Or)
+            } else {
+                break;
+            };
+
+            let (r, _) = utils::extract_linebreak(r);
+            let (r, pipeline) = Pipeline::new(r)?;
+            rest.push((connector, pipeline));
+            s = r;
+        }
+
+        Ok((s, Self { first, rest }))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{ParseError, ast::Command, utils};
+
+/// Commands connected by `|`, optionally negated with `!` and timed with
+/// `time`. The commands are empty only for a bare `time`, which reports on
+/// the shell itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pipeline {
+    pub time: Option<Timing>,
+    pub negated: bool,
+    pub commands: Vec<Command>,
+}
+
+/// How `time` reports a pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Timing {
+    /// In the format `$TIMEFORMAT` gives.
+    Default,
+    /// `time -p`: in the format POSIX gives.
+    Posix,
+}
+
+impl Pipeline {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        // `time` and `!` may come in either order, as in bash.
+        let mut s = s;
+        let mut time = None;
+        let mut negated = false;
+        loop {
+            let (rest, _) = utils::extract_whitespace(s);
+            match utils::peek_reserved(rest) {
+                Some("time") if time.is_none() => {
+                    let (rest, _) = utils::extract_whitespace(utils::reserved("time", rest)?);
+                    s = match rest.strip_prefix("-p") {
+                        Some(r) if r.chars().next().is_none_or(utils::is_metachar) => {
+                            time = Some(Timing::Posix);
+                            r
+                        },
+                        _ => {
+                            time = Some(Timing::Default);
+                            rest
+                        },
+                    };
+                },
+                Some("!") if !negated => {
+                    s = utils::reserved("!", rest)?;
+                    negated = true;
+                },
+                _ => break,
+            }
+        }
+
+        let (rest, _) = utils::extract_whitespace(s);
+        let ends = rest.starts_with("||") || rest.chars().next().is_none_or(|c| "\n;&)".contains(c));
+        if time.is_some() && ends {
+            return Ok((s, Self { time, negated, commands: Vec::new() }));
+        }
+
+        let (mut s, first) = Command::new(s)?;
+        let mut commands = vec![first];
+
+        loop {
+            let (rest, _) = utils::extract_whitespace(s);
+            if !rest.starts_with('|') || rest.starts_with("||") {
+                break;
+            }
+            let (rest, _) = utils::extract_linebreak(&rest[1..]);
+            let (rest, command) = Command::new(rest)?;
+            commands.push(command);
+            s = rest;
+        }
+
+        Ok((s, Self { time, negated, commands }))
+    }
+}
@@ -0,0 +1,113 @@
+use std::os::unix::io::RawFd;
+
+use crate::{ParseError, ast::Word, utils};
+
+/// An I/O redirection such as `2>> log` or `<&-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Redirect {
+    /// The descriptor written before the operator, if any.
+    pub fd: Option<RawFd>,
+    pub op: RedirectOp,
+    /// The file name, or for the `&` forms a descriptor number or `-`.
+    pub target: Word,
+}
+
+/// The operator of a [`Redirect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RedirectOp {
+    /// `<`
+    Input,
+    /// `>`
+    Output,
+    /// `>|`, which overwrites a file even under `set -C`.
+    Clobber,
+    /// `>>`
+    Append,
+    /// `<>`
+    ReadWrite,
+    /// `<&`
+    DupInput,
+    /// `>&`
+    DupOutput,
+}
+
+impl RedirectOp {
+    const ALL: &'static [(&'static str, RedirectOp)] = &[
+        (">>", RedirectOp::Append),
+        ("<>", RedirectOp::ReadWrite),
+        ("<&", RedirectOp::DupInput),
+        (">&", RedirectOp::DupOutput),
+        (">|", RedirectOp::Clobber),
+        ("<", RedirectOp::Input),
+        (">", RedirectOp::Output),
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        Self::ALL.iter().find(|(_, op)| op == self).unwrap().0
+    }
+
+    /// The descriptor redirected when none is written.
+    pub fn default_fd(&self) -> RawFd {
+        match self {
+            Self::Input | Self::ReadWrite | Self::DupInput => 0,
+            Self::Output | Self::Clobber | Self::Append | Self::DupOutput => 1,
+        }
+    }
+}
+
+impl Redirect {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, digits) = utils::take_while(|c| c.is_ascii_digit(), s);
+        let fd = if digits.is_empty() {
+            None
+        } else {
+            Some(digits.parse().map_err(|_| ParseError::Unexpected(digits.to_string()))?)
+        };
+
+        let &(token, op) = RedirectOp::ALL.iter()
+            .find(|(token, _)| s.starts_with(token))
+            .ok_or_else(|| ParseError::Expected("a redirection operator".to_string()))?;
+        let s = &s[token.len()..];
+
+        let (s, _) = utils::extract_whitespace(s);
+        if s.is_empty() {
+            return Err(ParseError::Incomplete("a redirection target".to_string()));
+        }
+        if s.starts_with(utils::is_metachar) {
+            return Err(utils::unexpected(s));
+        }
+        let (s, target) = Word::new(s)?;
+
+        Ok((s, Self { fd, op, target }))
+    }
+
+    /// Whether `s` starts with a redirection, optionally preceded by a
+    /// descriptor number.
+    pub(crate) fn starts(s: &str) -> bool {
+        let (s, _) = utils::take_while(|c| c.is_ascii_digit(), s);
+        s.starts_with('<') || s.starts_with('>')
+    }
+
+    /// Parses any redirections following a compound command.
+    pub(crate) fn many(s: &str) -> Result<(&str, Vec<Self>), ParseError> {
+        let mut redirects = Vec::new();
+        let mut s = s;
+        loop {
+            let (rest, _) = utils::extract_whitespace(s);
+            if !Self::starts(rest) {
+                return Ok((s, redirects));
+            }
+            let (rest, redirect) = Self::new(rest)?;
+            redirects.push(redirect);
+            s = rest;
+        }
+    }
+
+    /// The descriptor this redirection applies to.
+    pub fn target_fd(&self) -> RawFd {
+        self.fd.unwrap_or_else(|| self.op.default_fd())
+    }
+}
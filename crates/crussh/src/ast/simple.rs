@@ -0,0 +1,81 @@
+use crate::{ParseError, ast::{Redirect, Word}, utils};
+
+/// A command name with its arguments, preceded by any variable
+/// assignments, with redirections allowed anywhere.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimpleCommand {
+    pub assignments: Vec<Assignment>,
+    /// The command name followed by its arguments; empty for a command made
+    /// only of assignments and redirections.
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+/// A `name=value` assignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assignment {
+    pub name: String,
+    pub value: Word,
+}
+
+impl SimpleCommand {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut command = Self::default();
+        let mut s = s;
+
+        loop {
+            let (rest, _) = utils::extract_whitespace(s);
+
+            if rest.is_empty() || (rest.starts_with(utils::is_metachar) && !Redirect::starts(rest)) {
+                break;
+            }
+
+            if Redirect::starts(rest) {
+                let (rest, redirect) = Redirect::new(rest)?;
+                command.redirects.push(redirect);
+                s = rest;
+            } else if command.words.is_empty() && Assignment::starts(rest) {
+                let (rest, assignment) = Assignment::new(rest)?;
+                command.assignments.push(assignment);
+                s = rest;
+            } else {
+                let (rest, word) = Word::new(rest)?;
+                command.words.push(word);
+                s = rest;
+            }
+        }
+
+        if command.is_empty() {
+            return Err(utils::unexpected(utils::extract_whitespace(s).0));
+        }
+
+        Ok((s, command))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.assignments.is_empty() && self.words.is_empty() && self.redirects.is_empty()
+    }
+}
+
+impl Assignment {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let (s, name) = utils::extract_name(s)?;
+        let s = utils::tag("=", s)?;
+
+        let (s, value) = if s.is_empty() || s.starts_with(utils::is_metachar) {
+            (s, Word::default())
+        } else {
+            Word::new(s)?
+        };
+
+        Ok((s, Self { name: name.to_string(), value }))
+    }
+
+    pub(crate) fn starts(s: &str) -> bool {
+        matches!(utils::extract_name(s), Ok((rest, _)) if rest.starts_with('='))
+    }
+}
@@ -0,0 +1,182 @@
+//! Read-only traversal of the parse tree.
+//!
+//! Implement the `visit_*` methods for the nodes you care about; the default
+//! implementations call the matching `walk_*` function, which visits the
+//! node's children. An override that still wants the children visited calls
+//! `walk_*` itself.
+//!
+//! ```
+//! use crussh::ast::{SimpleCommand, visit::{self, Visitor}};
+//!
+//! struct Names(Vec<String>);
+//!
+//! impl Visitor for Names {
+//!     fn visit_simple_command(&mut self, command: &SimpleCommand) {
+//!         if let Some(name) = command.words.first().and_then(|w| w.as_literal()) {
+//!             self.0.push(name.to_string());
+//!         }
+//!         visit::walk_simple_command(self, command);
+//!     }
+//! }
+//!
+//! let parse = crussh::parse("if test -f a; then cat a | wc -l; fi").unwrap();
+//! let mut names = Names(Vec::new());
+//! names.visit_list(parse.ast());
+//! assert_eq!(names.0, ["test", "cat", "wc"]);
+//! ```
+
+use crate::ast::{
+    AndOr, Assignment, CaseItem, Command, CompoundCommand, FunctionDef, List, Pipeline, Redirect,
+    SimpleCommand, Word, WordPart,
+};
+
+pub trait Visitor {
+    fn visit_list(&mut self, list: &List) {
+        walk_list(self, list)
+    }
+
+    fn visit_and_or(&mut self, and_or: &AndOr) {
+        walk_and_or(self, and_or)
+    }
+
+    fn visit_pipeline(&mut self, pipeline: &Pipeline) {
+        walk_pipeline(self, pipeline)
+    }
+
+    fn visit_command(&mut self, command: &Command) {
+        walk_command(self, command)
+    }
+
+    fn visit_simple_command(&mut self, command: &SimpleCommand) {
+        walk_simple_command(self, command)
+    }
+
+    fn visit_compound_command(&mut self, command: &CompoundCommand) {
+        walk_compound_command(self, command)
+    }
+
+    fn visit_case_item(&mut self, item: &CaseItem) {
+        walk_case_item(self, item)
+    }
+
+    fn visit_function_def(&mut self, function: &FunctionDef) {
+        walk_function_def(self, function)
+    }
+
+    fn visit_assignment(&mut self, assignment: &Assignment) {
+        walk_assignment(self, assignment)
+    }
+
+    fn visit_redirect(&mut self, redirect: &Redirect) {
+        walk_redirect(self, redirect)
+    }
+
+    fn visit_word(&mut self, word: &Word) {
+        walk_word(self, word)
+    }
+
+    fn visit_word_part(&mut self, _part: &WordPart) {}
+}
+
+pub fn walk_list<V: Visitor + ?Sized>(visitor: &mut V, list: &List) {
+    for item in &list.items {
+        visitor.visit_and_or(&item.and_or);
+    }
+}
+
+pub fn walk_and_or<V: Visitor + ?Sized>(visitor: &mut V, and_or: &AndOr) {
+    visitor.visit_pipeline(&and_or.first);
+    for (_, pipeline) in &and_or.rest {
+        visitor.visit_pipeline(pipeline);
+    }
+}
+
+pub fn walk_pipeline<V: Visitor + ?Sized>(visitor: &mut V, pipeline: &Pipeline) {
+    for command in &pipeline.commands {
+        visitor.visit_command(command);
+    }
+}
+
+pub fn walk_command<V: Visitor + ?Sized>(visitor: &mut V, command: &Command) {
+    match command {
+        Command::Simple(simple) => visitor.visit_simple_command(simple),
+        Command::Compound { command, redirects } => {
+            visitor.visit_compound_command(command);
+            for redirect in redirects {
+                visitor.visit_redirect(redirect);
+            }
+        },
+        Command::FunctionDef(function) => visitor.visit_function_def(function),
+    }
+}
+
+pub fn walk_simple_command<V: Visitor + ?Sized>(visitor: &mut V, command: &SimpleCommand) {
+    for assignment in &command.assignments {
+        visitor.visit_assignment(assignment);
+    }
+    for word in &command.words {
+        visitor.visit_word(word);
+    }
+    for redirect in &command.redirects {
+        visitor.visit_redirect(redirect);
+    }
+}
+
+pub fn walk_compound_command<V: Visitor + ?Sized>(visitor: &mut V, command: &CompoundCommand) {
+    match command {
+        CompoundCommand::BraceGroup(list) | CompoundCommand::Subshell(list) => visitor.visit_list(list),
+        CompoundCommand::If(clause) => {
+            for branch in &clause.branches {
+                visitor.visit_list(&branch.condition);
+                visitor.visit_list(&branch.body);
+            }
+            if let Some(else_body) = &clause.else_body {
+                visitor.visit_list(else_body);
+            }
+        },
+        CompoundCommand::While(clause) | CompoundCommand::Until(clause) => {
+            visitor.visit_list(&clause.condition);
+            visitor.visit_list(&clause.body);
+        },
+        CompoundCommand::For(clause) => {
+            for word in clause.words.iter().flatten() {
+                visitor.visit_word(word);
+            }
+            visitor.visit_list(&clause.body);
+        },
+        CompoundCommand::Case(clause) => {
+            visitor.visit_word(&clause.word);
+            for item in &clause.items {
+                visitor.visit_case_item(item);
+            }
+        },
+    }
+}
+
+pub fn walk_case_item<V: Visitor + ?Sized>(visitor: &mut V, item: &CaseItem) {
+    for pattern in &item.patterns {
+        visitor.visit_word(pattern);
+    }
+    visitor.visit_list(&item.body);
+}
+
+pub fn walk_function_def<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDef) {
+    visitor.visit_compound_command(&function.body);
+    for redirect in &function.redirects {
+        visitor.visit_redirect(redirect);
+    }
+}
+
+pub fn walk_assignment<V: Visitor + ?Sized>(visitor: &mut V, assignment: &Assignment) {
+    visitor.visit_word(&assignment.value);
+}
+
+pub fn walk_redirect<V: Visitor + ?Sized>(visitor: &mut V, redirect: &Redirect) {
+    visitor.visit_word(&redirect.target);
+}
+
+pub fn walk_word<V: Visitor + ?Sized>(visitor: &mut V, word: &Word) {
+    for part in &word.parts {
+        visitor.visit_word_part(part);
+    }
+}
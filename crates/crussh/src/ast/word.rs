@@ -0,0 +1,184 @@
+use crate::{ParseError, utils};
+
+/// A shell word, kept as the sequence of segments it was written with so
+/// that quoting can still be told apart after parsing.
+///
+/// Adjacent segments of the same kind are merged, and quoted text that
+/// would mean the same thing unquoted is stored as [`WordPart::Literal`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+/// One segment of a [`Word`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WordPart {
+    /// Unquoted text, subject to tilde expansion.
+    Literal(String),
+    /// Text protected by single quotes, double quotes or a backslash.
+    Quoted(String),
+    /// A `$name` or `${name}` expansion, `quoted` when inside double quotes.
+    Parameter { name: String, quoted: bool },
+}
+
+impl Word {
+
+    pub(crate) fn new(s: &str) -> Result<(&str, Self), ParseError> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+
+        loop {
+            let mut chars = rest.chars();
+            let c = match chars.next() {
+                Some(c) if !utils::is_metachar(c) => c,
+                _ => break,
+            };
+
+            match c {
+                '\'' => {
+                    let (r, text) = utils::take_while(|c| c != '\'', chars.as_str());
+                    rest = utils::tag("'", r)
+                        .map_err(|_| ParseError::Incomplete("matching `''".to_string()))?;
+                    push(&mut parts, WordPart::Quoted(text.to_string()));
+                },
+                '"' => {
+                    rest = double_quoted(chars.as_str(), &mut parts)?;
+                },
+                '\\' => match chars.next() {
+                    Some('\n') => rest = chars.as_str(),
+                    Some(c) => {
+                        rest = chars.as_str();
+                        push(&mut parts, WordPart::Quoted(c.to_string()));
+                    },
+                    None => {
+                        rest = chars.as_str();
+                        push(&mut parts, WordPart::Quoted("\\".to_string()));
+                    }
+                },
+                '$' => {
+                    rest = dollar(rest, false, &mut parts)?;
+                },
+                c => {
+                    rest = chars.as_str();
+                    push(&mut parts, WordPart::Literal(c.to_string()));
+                }
+            }
+        }
+
+        if rest.len() == s.len() {
+            return Err(ParseError::Expected("a word".to_string()));
+        }
+
+        Ok((rest, Self::canonical(parts)))
+    }
+
+    /// The text of a word made of a single unquoted segment, as needed to
+    /// recognise names and reserved words.
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Demotes quoted text that needs no quoting and drops empty quotes
+    /// next to other segments, so equivalent spellings parse the same.
+    fn canonical(parts: Vec<WordPart>) -> Self {
+        let only_part = parts.len() == 1;
+        let mut canonical = Vec::new();
+        for part in parts {
+            match part {
+                WordPart::Quoted(text) if text.is_empty() && !only_part => {},
+                WordPart::Quoted(text) if !text.is_empty() && text.chars().all(is_plain) => {
+                    push(&mut canonical, WordPart::Literal(text))
+                },
+                part => push(&mut canonical, part),
+            }
+        }
+        Self { parts: canonical }
+    }
+}
+
+/// Characters that mean the same thing quoted and unquoted anywhere in a word.
+pub(crate) fn is_plain(c: char) -> bool {
+    c.is_alphanumeric() || "_-./,:+@%".contains(c)
+}
+
+fn push(parts: &mut Vec<WordPart>, part: WordPart) {
+    match (parts.last_mut(), part) {
+        (Some(WordPart::Literal(text)), WordPart::Literal(more)) => text.push_str(&more),
+        (Some(WordPart::Quoted(text)), WordPart::Quoted(more)) => text.push_str(&more),
+        (_, part) => parts.push(part),
+    }
+}
+
+/// Parses the inside of a double-quoted string, after the opening quote.
+fn double_quoted<'a>(s: &'a str, parts: &mut Vec<WordPart>) -> Result<&'a str, ParseError> {
+    let mut rest = s;
+    // An empty pair of quotes still makes an (empty) argument.
+    push(parts, WordPart::Quoted(String::new()));
+
+    loop {
+        let mut chars = rest.chars();
+        match chars.next() {
+            None => return Err(ParseError::Incomplete("matching `\"'".to_string())),
+            Some('"') => return Ok(chars.as_str()),
+            Some('\\') => match chars.next() {
+                Some('\n') => rest = chars.as_str(),
+                Some(c) if "$`\"\\".contains(c) => {
+                    rest = chars.as_str();
+                    push(parts, WordPart::Quoted(c.to_string()));
+                },
+                _ => {
+                    rest = &rest[1..];
+                    push(parts, WordPart::Quoted("\\".to_string()));
+                }
+            },
+            Some('$') => rest = dollar(rest, true, parts)?,
+            Some(c) => {
+                rest = chars.as_str();
+                push(parts, WordPart::Quoted(c.to_string()));
+            }
+        }
+    }
+}
+
+/// Parses a parameter expansion starting at `$`. A `$` that does not start
+/// one is kept as a quoted `$`.
+fn dollar<'a>(s: &'a str, quoted: bool, parts: &mut Vec<WordPart>) -> Result<&'a str, ParseError> {
+    let s = &s[1..];
+
+    if let Some(inner) = s.strip_prefix('{') {
+        let (rest, name) = utils::take_while(|c| c != '}', inner);
+        let rest = rest.strip_prefix('}')
+            .ok_or_else(|| ParseError::Incomplete("matching `}'".to_string()))?;
+        if !is_parameter_name(name) {
+            return Err(ParseError::Unexpected(format!("${{{}}}", name)));
+        }
+        parts.push(WordPart::Parameter { name: name.to_string(), quoted });
+        return Ok(rest);
+    }
+
+    let (rest, name) = match s.chars().next() {
+        Some(c) if c.is_ascii_digit() || "?$#@*-!".contains(c) => (&s[1..], &s[..1]),
+        Some(c) if c == '_' || c.is_ascii_alphabetic() => {
+            let (rest, name) = utils::take_while(|c| c == '_' || c.is_ascii_alphanumeric(), s);
+            (rest, name)
+        },
+        _ => {
+            push(parts, WordPart::Quoted("$".to_string()));
+            return Ok(s);
+        }
+    };
+
+    parts.push(WordPart::Parameter { name: name.to_string(), quoted });
+    Ok(rest)
+}
+
+fn is_parameter_name(name: &str) -> bool {
+    utils::is_name(name)
+        || (!name.is_empty() && name.chars().all(|c| c.is_ascii_digit()))
+        || (name.len() == 1 && "?$#@*-!".contains(name))
+}
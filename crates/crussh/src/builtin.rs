@@ -0,0 +1,252 @@
+//! Commands the shell runs itself instead of starting a program.
+
+mod alias;
+mod command;
+mod echo;
+mod eval;
+mod exec;
+mod getopts;
+mod hash;
+mod history;
+mod job;
+mod printf;
+mod pwd;
+mod read;
+mod set;
+mod source;
+mod status;
+mod test;
+mod times;
+mod ulimit;
+mod umask;
+mod trap;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::Shell;
+
+/// A command run inside the shell, with access to its state.
+///
+/// Builtins are looked up after functions and before `PATH`. They take
+/// part in pipelines and honour redirections like any other command; when
+/// one is not the last stage of a pipeline it runs in a child of the
+/// shell, so changes it makes to the shell are lost.
+///
+/// ```
+/// use crussh::{Builtin, Io, Shell};
+///
+/// struct Greet;
+///
+/// impl Builtin for Greet {
+///     fn name(&self) -> &str {
+///         "greet"
+///     }
+///
+///     fn run(&self, shell: &mut Shell, args: &[String], _io: &mut Io) -> i32 {
+///         shell.set_var("greeted", args.join(" "));
+///         0
+///     }
+/// }
+///
+/// let mut shell = Shell::new();
+/// shell.builtins_mut().insert(Greet);
+/// shell.run("greet world").unwrap();
+/// assert_eq!(shell.var("greeted"), Some("world"));
+/// ```
+pub trait Builtin {
+    fn name(&self) -> &str;
+
+    /// Runs the builtin with the arguments after its name and returns its
+    /// exit status. Errors are reported on `io.stderr`.
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32;
+}
+
+/// The standard streams of a builtin, after redirections.
+pub struct Io {
+    pub stdin: Box<dyn Input>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Io {
+    /// Reports an error the way the shell's own builtins do.
+    pub(crate) fn error(&mut self, builtin: &str, message: impl fmt::Display) {
+        let _ = writeln!(self.stderr, "crussh: {}: {}", builtin, message);
+    }
+}
+
+/// A builtin's standard input, which may be a terminal or pipe that has
+/// nothing to read yet.
+pub trait Input: Read {
+    /// Waits up to `timeout` for something to read, returning whether
+    /// there is (end of file counts).
+    fn ready(&mut self, _timeout: Duration) -> io::Result<bool> {
+        Ok(true)
+    }
+
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// The builtins a shell knows, by name.
+#[derive(Clone, Default)]
+pub struct Builtins(BTreeMap<String, Rc<dyn Builtin>>);
+
+impl Builtins {
+
+    /// The builtins every shell starts with: `echo`, `printf`, `pwd`,
+    /// `true`, `false`, `:`, `test`, `[`, `read`, `alias`, `unalias`,
+    /// `source`, `.`, `type`, `command`, `hash`, `exec`, `trap`, `kill`,
+    /// `wait`, `disown`, `set`, `shopt`, `times`, `ulimit`, `umask`,
+    /// `eval`, `let`, `getopts`, `shift` and `history`.
+    pub fn standard() -> Self {
+        let mut builtins = Self::default();
+        builtins.insert(echo::Echo);
+        builtins.insert(printf::Printf);
+        builtins.insert(pwd::Pwd);
+        builtins.insert(status::Status { name: "true", status: 0 });
+        builtins.insert(status::Status { name: "false", status: 1 });
+        builtins.insert(status::Status { name: ":", status: 0 });
+        builtins.insert(test::Test { bracket: false });
+        builtins.insert(test::Test { bracket: true });
+        builtins.insert(read::Read);
+        builtins.insert(alias::Alias);
+        builtins.insert(alias::Unalias);
+        builtins.insert(source::Source { dot: false });
+        builtins.insert(source::Source { dot: true });
+        builtins.insert(command::Type);
+        builtins.insert(command::Command);
+        builtins.insert(hash::Hash);
+        builtins.insert(exec::Exec);
+        builtins.insert(trap::Trap);
+        builtins.insert(job::Kill);
+        builtins.insert(job::Wait);
+        builtins.insert(job::Disown);
+        builtins.insert(set::Set);
+        builtins.insert(set::Shopt);
+        builtins.insert(times::Times);
+        builtins.insert(ulimit::Ulimit);
+        builtins.insert(umask::Umask);
+        builtins.insert(eval::Eval);
+        builtins.insert(eval::Let);
+        builtins.insert(getopts::Getopts);
+        builtins.insert(getopts::Shift);
+        builtins.insert(history::History);
+        builtins
+    }
+
+    /// Adds a builtin, replacing any with the same name.
+    pub fn insert(&mut self, builtin: impl Builtin + 'static) {
+        self.0.insert(builtin.name().to_string(), Rc::new(builtin));
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Builtin>> {
+        self.0.get(name).cloned()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    /// The names of the builtins, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::{Builtin, Io};
+    use crate::Shell;
+    use crate::ast::RedirectOp;
+    use crate::exec::{Fd, Recorder};
+
+    struct Say;
+
+    impl Builtin for Say {
+        fn name(&self) -> &str {
+            "say"
+        }
+
+        fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+            let greeting = shell.var("GREETING").unwrap_or("hello").to_string();
+            writeln!(io.stdout, "{} {}", greeting, args.join(" ")).is_err() as i32
+        }
+    }
+
+    struct Slurp;
+
+    impl Builtin for Slurp {
+        fn name(&self) -> &str {
+            "slurp"
+        }
+
+        fn run(&self, shell: &mut Shell, _args: &[String], io: &mut Io) -> i32 {
+            let mut input = String::new();
+            io.stdin.read_to_string(&mut input).unwrap();
+            shell.set_var("slurped", input);
+            0
+        }
+    }
+
+    fn shell(recorder: &Recorder) -> Shell {
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.builtins_mut().insert(Say);
+        shell.builtins_mut().insert(Slurp);
+        shell
+    }
+
+    #[test]
+    fn runs_instead_of_programs() {
+        let recorder = Recorder::new();
+        let mut shell = shell(&recorder);
+        assert_eq!(shell.run("GREETING=hi say there; say you"), Ok(0));
+
+        assert!(recorder.spawned().is_empty());
+        assert_eq!(recorder.written(&Fd::Inherited(1)), b"hi there\nhello you\n");
+        assert_eq!(shell.var("GREETING"), None);
+    }
+
+    #[test]
+    fn honours_redirections() {
+        let recorder = Recorder::new();
+        let mut shell = shell(&recorder);
+        shell.run("say to file > out").unwrap();
+
+        let out = Fd::File { path: "out".to_string(), op: RedirectOp::Output };
+        assert_eq!(recorder.written(&out), b"hello to file\n");
+        assert!(recorder.written(&Fd::Inherited(1)).is_empty());
+    }
+
+    #[test]
+    fn takes_part_in_pipelines() {
+        let recorder = Recorder::new().with_output("ls", b"a\nb\n");
+        let mut shell = shell(&recorder);
+        shell.run("say piped | wc; ls | slurp").unwrap();
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[0].argv, ["wc"]);
+        assert_eq!(spawned[0].fds[&0], Fd::PipeRead(0));
+        assert_eq!(shell.var("slurped"), Some("a\nb\n"));
+        assert_eq!(shell.capture("say x | slurp; say $slurped").unwrap().stdout, b"hello hello x\n");
+    }
+
+    #[test]
+    fn yields_to_functions() {
+        let recorder = Recorder::new();
+        let mut shell = shell(&recorder);
+        shell.run("say() { print function; }; say").unwrap();
+        assert_eq!(recorder.argvs(), [["print", "function"]]);
+    }
+}
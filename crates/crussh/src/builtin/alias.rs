@@ -0,0 +1,142 @@
+//! `alias [-p] [name[=value]...]` and `unalias [-a] name...`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+pub(crate) struct Alias;
+
+impl Builtin for Alias {
+    fn name(&self) -> &str {
+        "alias"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let mut print_all = args.is_empty();
+        while let Some((arg, rest)) = args.split_first() {
+            match arg.as_str() {
+                "-p" => print_all = true,
+                "--" => {
+                    args = rest;
+                    break;
+                },
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    io.error("alias", format!("{}: invalid option", arg));
+                    let _ = writeln!(io.stderr, "alias: usage: alias [-p] [name[=value] ... ]");
+                    return 2;
+                },
+                _ => break,
+            }
+            args = rest;
+        }
+
+        if print_all {
+            for (name, value) in &shell.aliases {
+                let _ = writeln!(io.stdout, "alias {}={}", name, quote(value));
+            }
+        }
+
+        let mut status = 0;
+        for arg in args {
+            match arg.split_once('=') {
+                Some((name, _)) if !is_alias_name(name) => {
+                    io.error("alias", format!("`{}': invalid alias name", name));
+                    status = 1;
+                },
+                Some((name, value)) => shell.set_alias(name, value),
+                None => match shell.alias(arg) {
+                    Some(value) => {
+                        let _ = writeln!(io.stdout, "alias {}={}", arg, quote(value));
+                    },
+                    None => {
+                        io.error("alias", format!("{}: not found", arg));
+                        status = 1;
+                    }
+                },
+            }
+        }
+        status
+    }
+}
+
+pub(crate) struct Unalias;
+
+impl Builtin for Unalias {
+    fn name(&self) -> &str {
+        "unalias"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let names = match args.first().map(String::as_str) {
+            Some("-a") => {
+                shell.aliases.clear();
+                return 0;
+            },
+            Some("--") => &args[1..],
+            Some(arg) if arg.starts_with('-') && arg.len() > 1 => {
+                io.error("unalias", format!("{}: invalid option", arg));
+                let _ = writeln!(io.stderr, "unalias: usage: unalias [-a] name [name ...]");
+                return 2;
+            },
+            _ => args,
+        };
+        if names.is_empty() {
+            let _ = writeln!(io.stderr, "unalias: usage: unalias [-a] name [name ...]");
+            return 2;
+        }
+
+        let mut status = 0;
+        for name in names {
+            if !shell.unset_alias(name) {
+                io.error("unalias", format!("{}: not found", name));
+                status = 1;
+            }
+        }
+        status
+    }
+}
+
+/// Whether `name` can be an alias: a non-empty word without characters the
+/// shell would read as quoting, expansion or an operator.
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| " \t\n|&;<>()'\"\\$`/=".contains(c))
+}
+
+/// `value` in single quotes, as `alias` prints it.
+pub(crate) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn defines_lists_and_removes() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.run("alias ll='ls -la' say=\"echo 'it is'\" g=git").unwrap();
+        assert_eq!(shell.alias("ll"), Some("ls -la"));
+
+        let captured = shell.capture("alias -p; alias g").unwrap();
+        assert_eq!(captured.stdout_lossy(), "alias g='git'\nalias ll='ls -la'\nalias say='echo '\\''it is'\\'''\nalias g='git'\n");
+        assert_eq!(shell.capture("say so").unwrap().stdout_lossy(), "it is so\n");
+
+        let captured = shell.capture("unalias g nope; alias a/b=c g").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: unalias: nope: not found\ncrussh: alias: `a/b': invalid alias name\ncrussh: alias: g: not found\n");
+        assert_eq!(captured.status, 1);
+
+        shell.run("unalias -a").unwrap();
+        assert_eq!(shell.alias("ll"), None);
+    }
+
+    #[test]
+    fn expands_on_later_lines() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.run("alias hi='echo hi; echo' loud='echo HEY '").unwrap();
+        let captured = shell.capture("hi there | loud hi").unwrap();
+        assert_eq!(captured.stdout_lossy(), "hi\nHEY echo hi\n\n");
+    }
+}
@@ -0,0 +1,271 @@
+//! `type [-aftpP] name...` and `command [-vV] name [arg...]`, which tell
+//! what a name runs. Running `command name` itself is done by the
+//! evaluator, which then skips functions.
+
+use std::io::Write;
+use std::path::Path;
+
+use nix::unistd::{AccessFlags, access};
+
+use crate::Shell;
+use crate::ast::FunctionDef;
+use crate::builtin::{Builtin, Io};
+use crate::utils;
+
+/// Something a command name refers to, in the order the shell looks.
+enum Found {
+    Alias(String),
+    Keyword,
+    Function(FunctionDef),
+    Builtin,
+    File { path: String, hashed: bool },
+}
+
+impl Found {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Alias(_) => "alias",
+            Self::Keyword => "keyword",
+            Self::Function(_) => "function",
+            Self::Builtin => "builtin",
+            Self::File { .. } => "file",
+        }
+    }
+
+    /// How `type` and `command -V` describe it.
+    fn describe(&self, name: &str) -> String {
+        match self {
+            Self::Alias(value) => format!("{} is aliased to `{}'", name, value),
+            Self::Keyword => format!("{} is a shell keyword", name),
+            Self::Function(function) => format!("{} is a function\n{}", name, function),
+            Self::Builtin => format!("{} is a shell builtin", name),
+            Self::File { path, hashed: true } => format!("{} is hashed ({})", name, path),
+            Self::File { path, .. } => format!("{} is {}", name, path),
+        }
+    }
+}
+
+/// What `name` refers to, or with `all` everything it could, files
+/// included. `functions` says whether to look at aliases and functions.
+fn find(shell: &mut Shell, name: &str, all: bool, functions: bool) -> Vec<Found> {
+    let mut found = Vec::new();
+    if functions {
+        if let Some(value) = shell.alias(name) {
+            found.push(Found::Alias(value.to_string()));
+        }
+    }
+    if utils::is_reserved(name) {
+        found.push(Found::Keyword);
+    }
+    if functions {
+        if let Some(function) = shell.function(name) {
+            found.push(Found::Function(function.clone()));
+        }
+    }
+    if shell.builtins().contains(name) {
+        found.push(Found::Builtin);
+    }
+    if !found.is_empty() && !all {
+        found.truncate(1);
+        return found;
+    }
+
+    if all && !name.contains('/') {
+        found.extend(search_path(shell, name).into_iter().map(|path| Found::File { path, hashed: false }));
+    } else if let Ok(path) = shell.lookup_program(name) {
+        let hashed = shell.hashed(name).is_some();
+        found.push(Found::File { path, hashed });
+    }
+    found
+}
+
+/// Every executable file named `name` in the `PATH` directories.
+fn search_path(shell: &Shell, name: &str) -> Vec<String> {
+    let path = shell.var("PATH").unwrap_or_default();
+    path.split(':')
+        .map(|dir| Path::new(dir).join(name))
+        .filter(|file| {
+            let file = shell.cwd().join(file);
+            file.is_file() && access(&file, AccessFlags::X_OK).is_ok()
+        })
+        .map(|file| file.to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Splits off leading option words made of the letters in `allowed`.
+fn options<'a>(args: &'a [String], allowed: &str) -> Result<(String, &'a [String]), String> {
+    let mut letters = String::new();
+    let mut rest = args;
+    while let Some((arg, tail)) = rest.split_first() {
+        if arg == "--" {
+            rest = tail;
+            break;
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            break;
+        }
+        if let Some(c) = arg[1..].chars().find(|c| !allowed.contains(*c)) {
+            return Err(format!("-{}: invalid option", c));
+        }
+        letters.push_str(&arg[1..]);
+        rest = tail;
+    }
+    Ok((letters, rest))
+}
+
+pub(crate) struct Type;
+
+impl Builtin for Type {
+    fn name(&self) -> &str {
+        "type"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (letters, names) = match options(args, "afptP") {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                io.error("type", e);
+                let _ = writeln!(io.stderr, "type: usage: type [-afptP] name [name ...]");
+                return 2;
+            }
+        };
+        let all = letters.contains('a');
+        let path_only = letters.contains('P');
+
+        let mut status = 0;
+        for name in names {
+            let mut found = find(shell, name, all, !letters.contains('f'));
+            if path_only {
+                // `-P` searches `PATH` even for names that are something else.
+                found.retain(|found| matches!(found, Found::File { .. }));
+                if found.is_empty() && !all {
+                    found.extend(shell.lookup_program(name).ok().map(|path| Found::File { path, hashed: false }));
+                }
+            }
+            if found.is_empty() {
+                if !letters.contains('t') && !letters.contains('p') && !path_only {
+                    io.error("type", format!("{}: not found", name));
+                }
+                status = 1;
+            }
+
+            for found in &found {
+                let line = match found {
+                    _ if letters.contains('t') => found.kind().to_string(),
+                    Found::File { path, .. } if letters.contains('p') || path_only => path.clone(),
+                    _ if letters.contains('p') => continue,
+                    _ => found.describe(name),
+                };
+                let _ = writeln!(io.stdout, "{}", line);
+            }
+        }
+        status
+    }
+}
+
+pub(crate) struct Command;
+
+impl Builtin for Command {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (letters, names) = match options(args, "vV") {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                io.error("command", e);
+                let _ = writeln!(io.stderr, "command: usage: command [-vV] command [arg ...]");
+                return 2;
+            }
+        };
+        let verbose = letters.contains('V');
+        if !verbose && !letters.contains('v') {
+            // `command name` is run by the evaluator; only a name it would
+            // not take for one gets here.
+            return match names.first() {
+                Some(name) => {
+                    io.error("command", format!("{}: command not found", name));
+                    127
+                },
+                None => 0,
+            };
+        }
+
+        let mut status = 0;
+        for name in names {
+            let found = find(shell, name, false, true);
+            let line = match found.first() {
+                None => {
+                    if verbose {
+                        io.error("command", format!("{}: not found", name));
+                    }
+                    status = 1;
+                    continue;
+                },
+                Some(found) if verbose => found.describe(name),
+                Some(Found::Alias(value)) => format!("alias {}='{}'", name, value.replace('\'', r"'\''")),
+                Some(Found::File { path, .. }) => path.clone(),
+                Some(_) => name.clone(),
+            };
+            let _ = writeln!(io.stdout, "{}", line);
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    fn shell() -> Shell {
+        let mut shell = Shell::new();
+        shell.set_var("PATH", "/no/such/dir:/bin:/usr/bin");
+        shell.run("alias ll='ls -l'; f() { echo hi; }").unwrap();
+        shell
+    }
+
+    #[test]
+    fn describes_names() {
+        let mut shell = shell();
+        let captured = shell.capture("type ll if f echo sh; type -t ll if f echo sh nope").unwrap();
+        let sh = captured.stdout_lossy().lines().nth(7).unwrap().to_string();
+        assert!(sh.starts_with("sh is /") && sh.ends_with("/sh"), "{}", sh);
+        assert_eq!(
+            captured.stdout_lossy(),
+            format!(
+                "ll is aliased to `ls -l'\nif is a shell keyword\nf is a function\nf() {{\n    echo hi\n}}\n\
+                 echo is a shell builtin\n{}\nalias\nkeyword\nfunction\nbuiltin\nfile\n",
+                sh
+            )
+        );
+        assert_eq!(captured.status, 1);
+
+        let captured = shell.capture("type nope").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: type: nope: not found\n");
+    }
+
+    #[test]
+    fn lists_all_with_a() {
+        let mut shell = shell();
+        shell.set_var("PATH", "/usr/bin");
+        let captured = shell.capture("f() { :; }; alias test=true; type -a test; type -P test").unwrap();
+        assert_eq!(
+            captured.stdout_lossy(),
+            "test is aliased to `true'\ntest is a shell builtin\ntest is /usr/bin/test\n/usr/bin/test\n"
+        );
+    }
+
+    #[test]
+    fn bypasses_functions_and_prints_lookups() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("ls() { echo function; }; command ls -a; command -- ls").unwrap();
+        assert_eq!(recorder.argvs(), [vec!["ls", "-a"], vec!["ls"]]);
+
+        shell.run("alias ll='ls -l'").unwrap();
+        let captured = shell.capture("command -v ll ls echo cat; command -V echo").unwrap();
+        assert_eq!(captured.stdout_lossy(), "alias ll='ls -l'\nls\necho\ncat\necho is a shell builtin\n");
+    }
+}
@@ -0,0 +1,87 @@
+//! `echo [-neE] [arg...]`.
+
+use crate::Shell;
+use crate::builtin::printf::{Escapes, unescape};
+use crate::builtin::{Builtin, Io};
+
+pub(crate) struct Echo;
+
+impl Builtin for Echo {
+    fn name(&self) -> &str {
+        "echo"
+    }
+
+    fn run(&self, _shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut newline = true;
+        let mut escapes = false;
+
+        // Only words made of option letters are options, as in bash.
+        let options = args.iter()
+            .take_while(|arg| arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| "neE".contains(c)))
+            .count();
+        for arg in &args[..options] {
+            for c in arg[1..].chars() {
+                match c {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false,
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        for (i, arg) in args[options..].iter().enumerate() {
+            if i > 0 {
+                out.push(b' ');
+            }
+            if !escapes {
+                out.extend_from_slice(arg.as_bytes());
+                continue;
+            }
+            let (bytes, stop) = unescape(arg, Escapes::Echo);
+            out.extend(bytes);
+            if stop {
+                newline = false;
+                break;
+            }
+        }
+        if newline {
+            out.push(b'\n');
+        }
+
+        match io.stdout.write_all(&out) {
+            Ok(()) => 0,
+            Err(e) => {
+                io.error("echo", format!("write error: {}", e));
+                1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    fn echo(args: &str) -> String {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.capture(&format!("echo {}", args)).unwrap().stdout_lossy()
+    }
+
+    #[test]
+    fn prints_arguments() {
+        assert_eq!(echo("a  'b  c'"), "a b  c\n");
+        assert_eq!(echo("-n a"), "a");
+        assert_eq!(echo("-nx a"), "-nx a\n");
+        assert_eq!(echo("- a"), "- a\n");
+    }
+
+    #[test]
+    fn interprets_escapes_with_e() {
+        assert_eq!(echo(r"'a\tb\n'"), "a\\tb\\n\n");
+        assert_eq!(echo(r"-e 'a\tb\x41\0101'"), "a\tbAA\n");
+        assert_eq!(echo(r"-e 'a\cb' c"), "a");
+        assert_eq!(echo(r"-eE 'a\n'"), "a\\n\n");
+    }
+}
@@ -0,0 +1,81 @@
+//! `eval [arg...]` and `let expression...`.
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::eval::arith;
+
+pub(crate) struct Eval;
+
+impl Builtin for Eval {
+    fn name(&self) -> &str {
+        "eval"
+    }
+
+    /// Runs the arguments, joined with spaces, as a command line in the
+    /// shell.
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let args = match args.first() {
+            Some(arg) if arg == "--" => &args[1..],
+            _ => args,
+        };
+        let command = args.join(" ");
+        if command.trim().is_empty() {
+            return 0;
+        }
+        match shell.parse(&command) {
+            Ok(parse) => shell.exec_list(parse.ast()),
+            Err(e) => {
+                io.error("eval", e);
+                2
+            },
+        }
+    }
+}
+
+pub(crate) struct Let;
+
+impl Builtin for Let {
+    fn name(&self) -> &str {
+        "let"
+    }
+
+    /// Evaluates each argument as an arithmetic expression. The status is
+    /// 0 if the last one is not zero.
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        if args.is_empty() {
+            io.error("let", "expression expected");
+            return 1;
+        }
+        let mut value = 0;
+        for arg in args {
+            value = match arith::evaluate(shell, arg) {
+                Ok(value) => value,
+                Err(e) => {
+                    io.error("let", format!("{}: {}", arg, e));
+                    return 1;
+                },
+            };
+        }
+        (value == 0) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn evaluates_commands_and_arithmetic() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let captured = shell.capture("cmd='echo a; echo b'; eval \"$cmd\" '|' wc; eval 'x=$((' 2>/dev/null; eval; echo $?").unwrap();
+        assert_eq!(captured.stdout_lossy(), "a\n0\n");
+
+        assert_eq!(shell.run("let 'i = 3' 'j = i * 2'"), Ok(0));
+        assert_eq!(shell.var("j"), Some("6"));
+        assert_eq!(shell.run("let 'i -= 3'"), Ok(1));
+        let captured = shell.capture("let 'j / i'").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: let: j / i: division by 0\n");
+        assert_eq!(captured.status, 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! `exec [-c] [-a name] [command [arg...]]`.
+
+use std::io::Write;
+
+use crate::builtin::{Builtin, Io};
+use crate::{ExecError, Shell};
+
+pub(crate) struct Exec;
+
+impl Builtin for Exec {
+    fn name(&self) -> &str {
+        "exec"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut clear_env = false;
+        let mut name = None;
+        let mut args = args;
+        while let Some((arg, rest)) = args.split_first() {
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            args = rest;
+            match arg.as_str() {
+                "--" => break,
+                "-c" => clear_env = true,
+                "-a" => match rest.split_first() {
+                    Some((value, rest)) => {
+                        name = Some(value.clone());
+                        args = rest;
+                    },
+                    None => {
+                        io.error("exec", "-a: option requires an argument");
+                        return 2;
+                    }
+                },
+                _ => {
+                    io.error("exec", format!("{}: invalid option", arg));
+                    let _ = writeln!(io.stderr, "exec: usage: exec [-c] [-a name] [command [argument ...]]");
+                    return 2;
+                }
+            }
+        }
+
+        // Without a command the redirections apply to the shell from now on.
+        if args.is_empty() {
+            shell.keep_redirects = true;
+            return 0;
+        }
+
+        let program = match shell.find_program(&args[0]) {
+            Ok(program) => program,
+            Err(e) => {
+                let message = match &e {
+                    ExecError::CommandNotFound(name) => format!("{}: not found", name),
+                    e => e.to_string(),
+                };
+                io.error("exec", message);
+                return failed(shell, e.status());
+            }
+        };
+        let mut argv = args.to_vec();
+        if let Some(name) = name {
+            argv[0] = name;
+        }
+        // Assignments before `exec` are already exported by now.
+        let env = if clear_env { Vec::new() } else { shell.child_env(&[]) };
+        let _ = io.stdout.flush();
+
+        let dir = shell.cwd().to_path_buf();
+        match shell.executor.exec(&program, &argv, &env, &dir) {
+            Ok(status) => status,
+            Err(e) => {
+                io.error("exec", &e);
+                failed(shell, e.status())
+            }
+        }
+    }
+}
+
+/// A command `exec` cannot run ends the shell too, as in bash, unless it
+/// is interactive.
+fn failed(shell: &mut Shell, status: i32) -> i32 {
+    if !shell.options().interactive || shell.is_subshell() {
+        shell.stop(status);
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::Shell;
+    use crate::exec::{Fd, Recorder};
+    use crate::ast::RedirectOp;
+
+    #[test]
+    fn keeps_redirections_for_later_commands() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("exec >log 2>&1; ls; { exec 3<in; } 2>err; cat").unwrap();
+
+        let log = Fd::File { path: "log".to_string(), op: RedirectOp::Output };
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[0].fds[&1], log);
+        assert_eq!(spawned[0].fds[&2], log);
+        assert_eq!(spawned[1].fds[&2], log);
+        assert_eq!(spawned[1].fds[&3], Fd::File { path: "in".to_string(), op: RedirectOp::Input });
+    }
+
+    #[test]
+    fn replaces_the_shell() {
+        let recorder = Recorder::new().with_status("fail", 1);
+        let mut shell = Shell::with_executor(recorder.clone());
+        assert_eq!(shell.run("X=1 exec fail arg > out"), Ok(1));
+        shell.run("exec -c -a name prog").unwrap();
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[0].argv, ["fail", "arg"]);
+        assert_eq!((spawned[1].program.as_str(), spawned[1].argv.as_slice()), ("prog", &["name".to_string()][..]));
+        assert!(spawned[1].env.is_empty());
+        assert_eq!(spawned[0].var("X"), Some("1"));
+        assert_eq!(spawned[0].fds[&1], Fd::File { path: "out".to_string(), op: RedirectOp::Output });
+        assert_eq!(shell.var("X"), None);
+
+        let captured = Shell::new().capture("exec /bin/sh -c 'echo replaced; exit 3'; echo not reached").unwrap();
+        assert_eq!((captured.stdout_lossy().as_str(), captured.status), ("replaced\n", 3));
+        let captured = Shell::new().capture("exec no-such-program; echo $?").unwrap();
+        assert_eq!((captured.stdout_lossy().as_str(), captured.status), ("", 127));
+        assert_eq!(captured.stderr_lossy(), "crussh: exec: no-such-program: not found\n");
+        let recorder = Recorder::new().without_program("nope");
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.options_mut().interactive = true;
+        assert_eq!(shell.run("exec nope; a"), Ok(0));
+        assert_eq!((recorder.argvs(), shell.exit_status()), (vec![vec!["a".to_string()]], None));
+
+        let dir = env::temp_dir().join(format!("crussh-exec-{}", std::process::id()));
+        let mut shell = Shell::new();
+        shell.set_var("LOG", dir.to_string_lossy());
+        let captured = shell.capture("exec 2>$LOG; echo hidden >&2; echo shown").unwrap();
+        assert_eq!((captured.stdout_lossy().as_str(), captured.stderr_lossy().as_str()), ("shown\n", ""));
+        assert_eq!(fs::read_to_string(&dir).unwrap(), "hidden\n");
+        fs::remove_file(&dir).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+//! `getopts optstring name [arg...]` and `shift [n]`.
+
+use std::io::Write;
+
+use crate::builtin::{Builtin, Io};
+use crate::{Shell, utils};
+
+pub(crate) struct Getopts;
+
+impl Builtin for Getopts {
+    fn name(&self) -> &str {
+        "getopts"
+    }
+
+    /// Puts the next option in the arguments, or the positional parameters
+    /// without any, in the variable `name` and its argument in `$OPTARG`,
+    /// and moves `$OPTIND` past them. An unknown option or a missing
+    /// argument sets `name` to `?`, or, when `optstring` starts with `:`,
+    /// to `?` or `:` with the option in `$OPTARG` and nothing printed.
+    ///
+    /// There is no `$(( ))` expansion, so a script drops the options it
+    /// has parsed with `let n=OPTIND-1; shift $n` rather than
+    /// `shift $((OPTIND-1))`.
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (optstring, name, args) = match args {
+            [optstring, name, args @ ..] => (optstring, name, args),
+            _ => {
+                let _ = writeln!(io.stderr, "getopts: usage: getopts optstring name [arg ...]");
+                return 2;
+            },
+        };
+        if !utils::is_name(name) {
+            io.error("getopts", format!("`{}': not a valid identifier", name));
+            return 1;
+        }
+        let args = if args.is_empty() { shell.positional.clone() } else { args.to_vec() };
+        let (silent, optstring) = match optstring.strip_prefix(':') {
+            Some(optstring) => (true, optstring),
+            None => (false, optstring.as_str()),
+        };
+        let report = !silent && shell.var("OPTERR") != Some("0");
+
+        let optind = shell.var("OPTIND").and_then(|optind| optind.parse().ok()).filter(|&optind| optind > 0).unwrap_or(1);
+        // The offset only holds if nobody has changed `$OPTIND` since.
+        let offset = match shell.getopts_offset {
+            Some((at, offset)) if at == optind => offset,
+            _ => 1,
+        };
+        let arg = match args.get(optind - 1) {
+            Some(arg) if arg == "--" => {
+                shell.set_var("OPTIND", (optind + 1).to_string());
+                return finish(shell, name);
+            },
+            Some(arg) if arg.len() > 1 && arg.starts_with('-') && offset < arg.len() => arg,
+            _ => return finish(shell, name),
+        };
+
+        let option = arg[offset..].chars().next().unwrap_or_default();
+        let rest = &arg[offset + option.len_utf8()..];
+        let (next, next_offset) = if rest.is_empty() { (optind + 1, 1) } else { (optind, offset + option.len_utf8()) };
+        let takes_argument = match optstring.find(option).filter(|_| option != ':') {
+            Some(i) => optstring[i + option.len_utf8()..].starts_with(':'),
+            None => {
+                if report {
+                    let _ = writeln!(io.stderr, "crussh: illegal option -- {}", option);
+                }
+                shell.getopts_offset = Some((next, next_offset));
+                shell.set_var("OPTIND", next.to_string());
+                return found(shell, name, "?", silent.then(|| option.to_string()));
+            },
+        };
+        if !takes_argument {
+            shell.getopts_offset = Some((next, next_offset));
+            shell.set_var("OPTIND", next.to_string());
+            return found(shell, name, &option.to_string(), None);
+        }
+
+        // The argument is the rest of this word, or else the next one.
+        let (argument, next) = if rest.is_empty() {
+            (args.get(optind).cloned(), optind + 2)
+        } else {
+            (Some(rest.to_string()), optind + 1)
+        };
+        shell.getopts_offset = None;
+        match argument {
+            Some(argument) => {
+                shell.set_var("OPTIND", next.to_string());
+                found(shell, name, &option.to_string(), Some(argument))
+            },
+            None => {
+                shell.set_var("OPTIND", (optind + 1).to_string());
+                if report {
+                    let _ = writeln!(io.stderr, "crussh: option requires an argument -- {}", option);
+                }
+                let value = if silent { ":" } else { "?" };
+                found(shell, name, value, silent.then(|| option.to_string()))
+            },
+        }
+    }
+}
+
+fn found(shell: &mut Shell, name: &str, value: &str, optarg: Option<String>) -> i32 {
+    shell.set_var(name, value);
+    match optarg {
+        Some(optarg) => shell.set_var("OPTARG", optarg),
+        None => shell.unset_var("OPTARG"),
+    }
+    0
+}
+
+/// Ends the options, setting `name` to `?`.
+fn finish(shell: &mut Shell, name: &str) -> i32 {
+    shell.getopts_offset = None;
+    shell.set_var(name, "?");
+    shell.unset_var("OPTARG");
+    1
+}
+
+pub(crate) struct Shift;
+
+impl Builtin for Shift {
+    fn name(&self) -> &str {
+        "shift"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let count = match args.first() {
+            None => 1,
+            Some(count) => match count.parse::<i64>() {
+                Ok(count) if count < 0 => {
+                    io.error("shift", format!("{}: shift count out of range", count));
+                    return 1;
+                },
+                Ok(count) => count as usize,
+                Err(_) => {
+                    io.error("shift", format!("{}: numeric argument required", count));
+                    return 1;
+                },
+            },
+        };
+        if count > shell.positional.len() {
+            return 1;
+        }
+        shell.positional.drain(..count);
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn parses_options() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_args(["-a", "-bone", "-cb", "two", "--", "-a", "rest"].iter().map(|arg| arg.to_string()).collect());
+        let captured = shell
+            .capture("while getopts ab:c opt; do echo \"$opt:$OPTARG:$OPTIND\"; done; echo \"$opt $OPTIND\"; let n=OPTIND-1; shift $n; echo \"$@\"")
+            .unwrap();
+        assert_eq!(captured.stdout_lossy(), "a::2\nb:one:3\nc::3\nb:two:5\n? 6\n-a rest\n");
+
+        let script = "OPTIND=1; while getopts :a:x opt -x -y -a; do echo \"$opt $OPTARG\"; done";
+        assert_eq!(shell.capture(script).unwrap().stdout_lossy(), "x \n? y\n: a\n");
+        let captured = shell.capture("OPTIND=1; getopts a opt -z; echo $opt; OPTIND=1; getopts a: opt -a; echo $opt").unwrap();
+        assert_eq!(captured.stdout_lossy(), "?\n?\n");
+        assert_eq!(captured.stderr_lossy(), "crussh: illegal option -- z\ncrussh: option requires an argument -- a\n");
+
+        let script = "f() { OPTIND=1; while getopts vo: opt; do case $opt in v) v=1;; o) o=$OPTARG;; esac; done; \
+                      let n=OPTIND-1; shift $n; echo \"$v $o $*\"; }; f -v -o out a b";
+        assert_eq!(shell.capture(script).unwrap().stdout_lossy(), "1 out a b\n");
+    }
+
+    #[test]
+    fn shifts_parameters() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_args(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        let captured = shell.capture("shift; echo $@; shift 5; echo $? $@; shift x; shift -1; shift 2; echo $#").unwrap();
+        assert_eq!(captured.stdout_lossy(), "b c\n1 b c\n0\n");
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: shift: x: numeric argument required\ncrussh: shift: -1: shift count out of range\n"
+        );
+    }
+}
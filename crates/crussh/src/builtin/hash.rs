@@ -0,0 +1,124 @@
+//! `hash [-r] [-p path] [-dt] [name...]`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::hash::Hashed;
+
+pub(crate) struct Hash;
+
+impl Builtin for Hash {
+    fn name(&self) -> &str {
+        "hash"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (mut reset, mut delete, mut print) = (false, false, false);
+        let mut path = None;
+        let mut names = args;
+        while let Some((arg, rest)) = names.split_first() {
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            names = rest;
+            match arg.as_str() {
+                "--" => break,
+                "-r" => reset = true,
+                "-d" => delete = true,
+                "-t" => print = true,
+                "-p" => match rest.split_first() {
+                    Some((value, rest)) => {
+                        path = Some(value.clone());
+                        names = rest;
+                    },
+                    None => {
+                        io.error("hash", "-p: option requires an argument");
+                        return 2;
+                    }
+                },
+                _ => {
+                    io.error("hash", format!("{}: invalid option", arg));
+                    let _ = writeln!(io.stderr, "hash: usage: hash [-r] [-p pathname] [-dt] [name ...]");
+                    return 2;
+                }
+            }
+        }
+
+        if reset {
+            shell.hashed_programs().clear();
+        }
+        if names.is_empty() {
+            if !reset {
+                list(shell, io);
+            }
+            return 0;
+        }
+
+        let mut status = 0;
+        for name in names {
+            let done = if let Some(path) = &path {
+                shell.hashed_programs().insert(name.clone(), Hashed { path: path.clone(), hits: 0 });
+                true
+            } else if delete {
+                shell.hashed_programs().remove(name).is_some()
+            } else if print {
+                match shell.hashed(name) {
+                    Some(hashed) if names.len() > 1 => {
+                        let _ = writeln!(io.stdout, "{}\t{}", name, hashed.path);
+                        true
+                    },
+                    Some(hashed) => {
+                        let _ = writeln!(io.stdout, "{}", hashed.path);
+                        true
+                    },
+                    None => false,
+                }
+            } else if name.contains('/') || shell.builtins().contains(name) || shell.function(name).is_some() {
+                true
+            } else {
+                shell.rehash(name).is_ok()
+            };
+            if !done {
+                io.error("hash", format!("{}: not found", name));
+                status = 1;
+            }
+        }
+        status
+    }
+}
+
+fn list(shell: &mut Shell, io: &mut Io) {
+    let programs = shell.hashed_programs();
+    if programs.is_empty() {
+        let _ = writeln!(io.stdout, "hash: hash table empty");
+        return;
+    }
+    let _ = writeln!(io.stdout, "hits\tcommand");
+    for hashed in programs.values() {
+        let _ = writeln!(io.stdout, "{:4}\t{}", hashed.hits, hashed.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn shows_and_edits_the_table() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let captured = shell.capture("hash; ls; ls; hash cat echo; hash; hash -t ls cat").unwrap();
+        assert_eq!(
+            captured.stdout_lossy(),
+            "hash: hash table empty\nhits\tcommand\n   0\tcat\n   2\tls\nls\tls\ncat\tcat\n"
+        );
+
+        let captured = shell.capture("hash -d cat; hash -p /opt/ls ls; hash -t ls; hash -t cat").unwrap();
+        assert_eq!(captured.stdout_lossy(), "/opt/ls\n");
+        assert_eq!(captured.stderr_lossy(), "crussh: hash: cat: not found\n");
+
+        shell.run("hash -r").unwrap();
+        assert_eq!(shell.capture("hash").unwrap().stdout_lossy(), "hash: hash table empty\n");
+    }
+}
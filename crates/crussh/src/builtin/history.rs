@@ -0,0 +1,169 @@
+//! `history [n]`, `history -c`, `history -d offset`, `history -arw [file]`
+//! and `history -p arg...`.
+
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::io::Write;
+
+use nix::libc;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+const USAGE: &str = "history: usage: history [-c] [-d offset] [n] or history -arw [filename] or history -p arg [arg ...]";
+
+pub(crate) struct History;
+
+impl Builtin for History {
+    fn name(&self) -> &str {
+        "history"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (option, args) = match args.split_first() {
+            Some((option, args)) if option.starts_with('-') && option.len() > 1 && option.parse::<i64>().is_err() => {
+                (option.as_str(), args)
+            },
+            _ => ("", args),
+        };
+        match option {
+            "" => list(shell, args, io),
+            "-c" => {
+                shell.history.clear();
+                0
+            },
+            "-d" => {
+                let number = args.first().and_then(|offset| offset.parse::<i64>().ok()).and_then(|offset| {
+                    let last = shell.history.last_number() as i64;
+                    // A negative offset counts back from the end.
+                    let number = if offset < 0 { last + 1 + offset } else { offset };
+                    usize::try_from(number).ok()
+                });
+                match number {
+                    Some(number) if shell.history.remove(number) => 0,
+                    _ => {
+                        let offset = args.first().map_or("", String::as_str);
+                        io.error("history", format!("{}: history position out of range", offset));
+                        1
+                    },
+                }
+            },
+            "-p" => {
+                let mut status = 0;
+                for arg in args {
+                    match shell.history.expand(arg) {
+                        Ok(expanded) => {
+                            let _ = writeln!(io.stdout, "{}", expanded.as_deref().unwrap_or(arg));
+                        },
+                        Err(e) => {
+                            io.error("history", e);
+                            status = 1;
+                        },
+                    }
+                }
+                status
+            },
+            "-a" | "-r" | "-w" => {
+                let path = match args.first().map(|file| shell.cwd().join(file)).or_else(|| shell.history_file()) {
+                    Some(path) => path,
+                    None => {
+                        io.error("history", "HISTFILE: parameter null or not set");
+                        return 1;
+                    },
+                };
+                let result = match option {
+                    "-a" => shell.history.append(&path, None),
+                    "-r" => shell.history.read(&path),
+                    _ => shell.history.write(&path),
+                };
+                match result {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        io.error("history", format!("{}: {}", path.display(), e));
+                        1
+                    },
+                }
+            },
+            _ => {
+                io.error("history", format!("{}: invalid option", option));
+                let _ = writeln!(io.stderr, "{}", USAGE);
+                2
+            },
+        }
+    }
+}
+
+/// Lists the entries, or the last `n` of them.
+fn list(shell: &Shell, args: &[String], io: &mut Io) -> i32 {
+    let count = match args.first() {
+        None => shell.history.len(),
+        Some(n) => match n.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => {
+                io.error("history", format!("{}: numeric argument required", n));
+                return 1;
+            },
+        },
+    };
+    let skip = shell.history.len().saturating_sub(count);
+    // With `$HISTTIMEFORMAT` set, each entry shows when it was entered.
+    let time_format = shell.var("HISTTIMEFORMAT");
+    for (number, entry) in shell.history.iter().skip(skip) {
+        let time = match (time_format, entry.time) {
+            (Some(format), Some(time)) => format_time(format, time),
+            _ => String::new(),
+        };
+        let _ = writeln!(io.stdout, "{:5}  {}{}", number, time, entry.line);
+    }
+    0
+}
+
+/// Formats seconds since the Unix epoch in local time with `strftime`.
+fn format_time(format: &str, time: u64) -> String {
+    let (format, time) = match (CString::new(format), libc::time_t::try_from(time)) {
+        (Ok(format), Ok(time)) => (format, time),
+        _ => return String::new(),
+    };
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return String::new();
+    }
+    let mut buf = [0u8; 256];
+    let len = unsafe { libc::strftime(buf.as_mut_ptr().cast(), buf.len(), format.as_ptr(), &tm) };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::exec::Recorder;
+    use crate::{HistoryEntry, Shell};
+
+    #[test]
+    fn lists_deletes_and_saves_entries() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        for line in ["ls -l", "cd /tmp", "echo hi", "make"] {
+            shell.history_mut().add(line);
+        }
+        let captured = shell.capture("history 2; history -d 2; history -d -1; history; history -p '!!:0' '!e:$' '!x'").unwrap();
+        assert_eq!(captured.stdout_lossy(), "    3  echo hi\n    4  make\n    1  ls -l\n    2  echo hi\necho\nhi\n");
+        assert_eq!(captured.stderr_lossy(), "crussh: history: !x: event not found\n");
+        assert_eq!(shell.capture("history -d 7").unwrap().stderr_lossy(), "crussh: history: 7: history position out of range\n");
+
+        let file = env::temp_dir().join(format!("crussh-history-{}", std::process::id()));
+        shell.set_var("HISTFILE", file.to_string_lossy());
+        assert_eq!(shell.run("history -w; history -c; history -r"), Ok(0));
+        assert_eq!(shell.history().iter().map(|(_, entry)| entry.line.as_str()).collect::<Vec<_>>(), ["ls -l", "echo hi"]);
+        fs::remove_file(&file).unwrap();
+        assert_eq!(shell.capture("history -z").unwrap().status, 2);
+
+        shell.history_mut().push(HistoryEntry { line: "date".to_string(), time: Some(86400), status: None });
+        assert_eq!(shell.capture("HISTTIMEFORMAT='%s: '; history 1").unwrap().stdout_lossy(), "    3  86400: date\n");
+        assert_eq!(shell.run("history -a"), Ok(0));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "#86400\ndate\n");
+        fs::remove_file(&file).unwrap();
+        shell.unset_var("HISTFILE");
+        assert_eq!(shell.capture("history -w").unwrap().stderr_lossy(), "crussh: history: HISTFILE: parameter null or not set\n");
+    }
+}
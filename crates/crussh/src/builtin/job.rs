@@ -0,0 +1,311 @@
+//! `kill`, `wait` and `disown`, which take process ids and job specs such
+//! as `%1`.
+
+use std::io::Write;
+
+use nix::sys::signal::Signal;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::job::Target;
+use crate::trap;
+
+const KILL_USAGE: &str = "kill: usage: kill [-s sigspec | -n signum | -sigspec] pid | jobspec ... or kill -l [sigspec]";
+
+pub(crate) struct Kill;
+
+impl Builtin for Kill {
+    fn name(&self) -> &str {
+        "kill"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut signal = Some(Signal::SIGTERM);
+        let mut args = args;
+        match args.first().map(String::as_str) {
+            Some("-l" | "-L") => return list(&args[1..], io),
+            Some("-s" | "-n") => {
+                let spec = match args.get(1) {
+                    Some(spec) => spec,
+                    None => {
+                        io.error("kill", format!("{}: option requires an argument", args[0]));
+                        let _ = writeln!(io.stderr, "{}", KILL_USAGE);
+                        return 2;
+                    },
+                };
+                match parse(spec) {
+                    Ok(parsed) => signal = parsed,
+                    Err(()) => {
+                        io.error("kill", format!("{}: invalid signal specification", spec));
+                        return 1;
+                    },
+                }
+                args = &args[2..];
+            },
+            Some("--") => args = &args[1..],
+            Some(arg) if arg.starts_with('-') && arg.len() > 1 => {
+                match parse(&arg[1..]) {
+                    Ok(parsed) => signal = parsed,
+                    Err(()) => {
+                        io.error("kill", format!("{}: invalid signal specification", &arg[1..]));
+                        return 1;
+                    },
+                }
+                args = &args[1..];
+            },
+            _ => {},
+        }
+        if args.is_empty() {
+            let _ = writeln!(io.stderr, "{}", KILL_USAGE);
+            return 2;
+        }
+
+        let mut status = 0;
+        for arg in args {
+            // A job is signalled as a whole, through its process group.
+            let sent = match shell.target(arg) {
+                Ok(Target::Job(index)) => shell.executor.kill(shell.jobs[index].pid, signal, true),
+                Ok(Target::Pid(pid)) => shell.executor.kill(pid, signal, false),
+                Err(e) => {
+                    io.error("kill", e);
+                    status = 1;
+                    continue;
+                },
+            };
+            if let Err(e) = sent {
+                io.error("kill", e);
+                status = 1;
+            }
+        }
+        status
+    }
+}
+
+/// A signal as `kill` takes it, where 0 is no signal at all.
+fn parse(spec: &str) -> Result<Option<Signal>, ()> {
+    if spec == "0" {
+        return Ok(None);
+    }
+    trap::parse_signal(spec).map(Some).ok_or(())
+}
+
+/// `kill -l`: every signal, or the name of each signal or exit status
+/// given by number and the number of each given by name.
+fn list(args: &[String], io: &mut Io) -> i32 {
+    if args.is_empty() {
+        let _ = trap::list_signals(&mut io.stdout);
+        return 0;
+    }
+    let mut status = 0;
+    for arg in args {
+        // A status of 128 plus a number is that of a process the signal
+        // killed.
+        let line = match arg.parse::<i32>() {
+            Ok(number) => trap::parse_signal(&(if number > 128 { number - 128 } else { number }).to_string())
+                .map(|signal| signal.as_str()[3..].to_string()),
+            Err(_) => trap::parse_signal(arg).map(|signal| (signal as i32).to_string()),
+        };
+        match line {
+            Some(line) => {
+                let _ = writeln!(io.stdout, "{}", line);
+            },
+            None => {
+                io.error("kill", format!("{}: invalid signal specification", arg));
+                status = 1;
+            },
+        }
+    }
+    status
+}
+
+pub(crate) struct Wait;
+
+impl Builtin for Wait {
+    fn name(&self) -> &str {
+        "wait"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let args = match args.first() {
+            Some(arg) if arg == "--" => &args[1..],
+            _ => args,
+        };
+        if args.is_empty() {
+            for job in std::mem::take(&mut shell.jobs) {
+                let _ = shell.wait_child(job.pid);
+            }
+            shell.finished.clear();
+            return 0;
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let pid = match shell.target(arg) {
+                Ok(Target::Job(index)) => shell.jobs[index].pid,
+                Ok(Target::Pid(pid)) => pid,
+                Err(e) => {
+                    io.error("wait", e);
+                    status = 127;
+                    continue;
+                },
+            };
+            // A job that has been reaped already left its status behind.
+            status = match shell.finished.remove(&pid) {
+                Some(finished) => finished,
+                None => match shell.jobs.iter().position(|job| job.pid == pid) {
+                    Some(index) => {
+                        shell.jobs.remove(index);
+                        shell.wait_child(pid).unwrap_or_else(|e| {
+                            io.error("wait", e);
+                            127
+                        })
+                    },
+                    None => {
+                        io.error("wait", format!("pid {} is not a child of this shell", pid));
+                        127
+                    },
+                },
+            };
+        }
+        status
+    }
+}
+
+pub(crate) struct Disown;
+
+impl Builtin for Disown {
+    fn name(&self) -> &str {
+        "disown"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let (mut nohup, mut all) = (false, false);
+        while let Some((arg, rest)) = args.split_first() {
+            if arg == "--" {
+                args = rest;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            for c in arg[1..].chars() {
+                match c {
+                    'h' => nohup = true,
+                    'a' => all = true,
+                    // Every job the shell knows of is running.
+                    'r' => {},
+                    _ => {
+                        io.error("disown", format!("-{}: invalid option", c));
+                        let _ = writeln!(io.stderr, "disown: usage: disown [-h] [-ar] [jobspec ... | pid ...]");
+                        return 2;
+                    },
+                }
+            }
+            args = rest;
+        }
+
+        let mut status = 0;
+        let mut pids = Vec::new();
+        if all {
+            pids.extend(shell.jobs.iter().map(|job| job.pid));
+        } else if args.is_empty() {
+            match shell.find_job("%+") {
+                Ok(index) => pids.push(shell.jobs[index].pid),
+                Err(_) => {
+                    io.error("disown", "current: no such job");
+                    status = 1;
+                },
+            }
+        }
+        for arg in args {
+            match shell.target(arg) {
+                Ok(Target::Job(index)) => pids.push(shell.jobs[index].pid),
+                Ok(Target::Pid(pid)) if shell.jobs.iter().any(|job| job.pid == pid) => pids.push(pid),
+                Ok(Target::Pid(_)) => {
+                    io.error("disown", format!("{}: no such job", arg));
+                    status = 1;
+                },
+                Err(e) => {
+                    io.error("disown", e);
+                    status = 1;
+                },
+            }
+        }
+
+        if nohup {
+            shell.jobs.iter_mut().filter(|job| pids.contains(&job.pid)).for_each(|job| job.nohup = true);
+        } else {
+            shell.jobs.retain(|job| !pids.contains(&job.pid));
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::sys::signal::Signal;
+
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn kills_processes_and_jobs() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.add_job(10, "sleep 10".to_string());
+        shell.add_job(11, "make".to_string());
+        shell.run("kill 5; kill -INT %1; kill -s hup %make 6; kill -n 9 %%; kill -0 7").unwrap();
+        assert_eq!(recorder.killed(), [
+            (5, Some(Signal::SIGTERM)),
+            (10, Some(Signal::SIGINT)),
+            (11, Some(Signal::SIGHUP)),
+            (6, Some(Signal::SIGHUP)),
+            (11, Some(Signal::SIGKILL)),
+            (7, None),
+        ]);
+
+        let captured = shell.capture("kill -l 15 143 INT; kill -FOO 1; kill %3").unwrap();
+        assert_eq!(captured.stdout_lossy(), "TERM\nTERM\n2\n");
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: kill: FOO: invalid signal specification\ncrussh: kill: %3: no such job\n"
+        );
+        assert_eq!(captured.status, 1);
+    }
+
+    #[test]
+    fn waits_for_jobs() {
+        let recorder = Recorder::new().with_status("fail", 3).with_status("slow", 4);
+        let mut shell = Shell::with_executor(recorder);
+        shell.run("fail &").unwrap();
+        shell.run("slow &").unwrap();
+        // Recorded jobs are done by the next command line, which reaps them
+        // and keeps their statuses for `wait`.
+        assert_eq!(shell.run("wait $!"), Ok(4));
+        assert_eq!(shell.run("wait $!"), Ok(127));
+        assert_eq!(shell.run("wait %1"), Ok(127));
+
+        shell.add_job(10, "sleep 10".to_string());
+        shell.add_job(11, "sleep 20".to_string());
+        assert_eq!(shell.run("wait %?10"), Ok(0));
+        assert_eq!(shell.jobs().len(), 1);
+        assert_eq!(shell.run("wait"), Ok(0));
+        assert!(shell.jobs().is_empty());
+    }
+
+    #[test]
+    fn disowns_jobs() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        for (pid, command) in [(10, "a"), (11, "b"), (12, "c")] {
+            shell.add_job(pid, command.to_string());
+        }
+        shell.run("disown -h %a; disown; disown 11").unwrap();
+        assert_eq!(shell.jobs().iter().map(|job| (job.pid, job.nohup)).collect::<Vec<_>>(), [(10, true)]);
+
+        shell.add_job(13, "d".to_string());
+        shell.hang_up_jobs();
+        assert_eq!(recorder.killed(), [(13, Some(Signal::SIGHUP)), (13, Some(Signal::SIGCONT))]);
+    }
+}
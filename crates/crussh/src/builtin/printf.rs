@@ -0,0 +1,594 @@
+//! `printf [-v var] format [arg...]`, with the escapes shared by `echo -e`.
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::utils;
+
+pub(crate) struct Printf;
+
+impl Builtin for Printf {
+    fn name(&self) -> &str {
+        "printf"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let mut var = None;
+        loop {
+            match args.first().map(String::as_str) {
+                Some("-v") if args.len() > 1 => {
+                    var = Some(args[1].clone());
+                    args = &args[2..];
+                },
+                Some("-v") => {
+                    io.error("printf", "-v: option requires an argument");
+                    return 2;
+                },
+                Some("--") => {
+                    args = &args[1..];
+                    break;
+                },
+                _ => break,
+            }
+        }
+        let (format, args) = match args.split_first() {
+            Some(split) => split,
+            None => {
+                io.error("printf", "usage: printf [-v var] format [arguments]");
+                return 2;
+            }
+        };
+        if let Some(var) = &var {
+            if !utils::is_name(var) {
+                io.error("printf", format!("`{}': not a valid identifier", var));
+                return 2;
+            }
+        }
+
+        let mut formatter = Formatter { args, next: 0, out: Vec::new(), errors: Vec::new() };
+        // The format is reused for as long as it consumes arguments.
+        let result = loop {
+            let start = formatter.next;
+            match formatter.format(format) {
+                Ok(true) if formatter.next > start && formatter.next < args.len() => continue,
+                Ok(_) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        let mut status = 0;
+        for e in formatter.errors {
+            io.error("printf", e);
+            status = 1;
+        }
+        if let Err(e) = result {
+            io.error("printf", e);
+            status = 1;
+        }
+
+        match var {
+            Some(var) => shell.set_var(&var, String::from_utf8_lossy(&formatter.out).into_owned()),
+            None => {
+                if let Err(e) = io.stdout.write_all(&formatter.out) {
+                    io.error("printf", format!("write error: {}", e));
+                    return 1;
+                }
+            }
+        }
+        status
+    }
+}
+
+/// Where an escape sequence appears: octal escapes are `\0nnn` and `\c`
+/// ends the output for `echo -e` and `%b`, and they are `\nnn` in a format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Escapes {
+    Echo,
+    Format,
+}
+
+/// Expands backslash escapes in `s`, returning the bytes and whether a
+/// `\c` asked for output to stop there.
+pub(crate) fn unescape(s: &str, escapes: Escapes) -> (Vec<u8>, bool) {
+    let mut out = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        let escaped = match chars.peek() {
+            Some(&escaped) => escaped,
+            None => {
+                out.push(b'\\');
+                break;
+            }
+        };
+        chars.next();
+        match escaped {
+            'a' => out.push(0x07),
+            'b' => out.push(0x08),
+            'e' | 'E' => out.push(0x1b),
+            'f' => out.push(0x0c),
+            'n' => out.push(b'\n'),
+            'r' => out.push(b'\r'),
+            't' => out.push(b'\t'),
+            'v' => out.push(0x0b),
+            '\\' => out.push(b'\\'),
+            'c' if escapes == Escapes::Echo => return (out, true),
+            '"' | '\'' if escapes == Escapes::Format => out.push(escaped as u8),
+            '0' if escapes == Escapes::Echo => out.push(digits(&mut chars, 8, 3) as u8),
+            '0'..='7' if escapes == Escapes::Format => {
+                let (rest, count) = digits_counted(&mut chars, 8, 2);
+                out.push((escaped.to_digit(8).unwrap() * 8u32.pow(count) + rest) as u8);
+            },
+            'x' if chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) => {
+                out.push(digits(&mut chars, 16, 2) as u8);
+            },
+            'u' | 'U' if chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) => {
+                let value = digits(&mut chars, 16, if escaped == 'u' { 4 } else { 8 });
+                let c = char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER);
+                let mut buf = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            },
+            _ => {
+                out.push(b'\\');
+                let mut buf = [0; 4];
+                out.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    (out, false)
+}
+
+fn digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: u32) -> u32 {
+    digits_counted(chars, radix, max).0
+}
+
+/// Reads up to `max` digits, returning their value and how many there were.
+fn digits_counted(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: u32) -> (u32, u32) {
+    let (mut value, mut count) = (0, 0);
+    while count < max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            Some(digit) => {
+                value = value * radix + digit;
+                count += 1;
+                chars.next();
+            },
+            None => break,
+        }
+    }
+    (value, count)
+}
+
+/// A conversion specification: `%[flags][width][.precision]conversion`.
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+struct Formatter<'a> {
+    args: &'a [String],
+    next: usize,
+    out: Vec<u8>,
+    /// Bad arguments, which are reported but do not stop the output.
+    errors: Vec<String>,
+}
+
+impl Formatter<'_> {
+
+    /// Writes `format` once, returning `Ok(false)` if a `\c` stopped the
+    /// output.
+    fn format(&mut self, format: &str) -> Result<bool, String> {
+        let chars: Vec<char> = format.chars().collect();
+        let mut i = 0;
+        let mut literal = String::new();
+
+        while i < chars.len() {
+            if chars[i] != '%' {
+                literal.push(chars[i]);
+                i += 1;
+                continue;
+            }
+            self.out.extend(unescape(&literal, Escapes::Format).0);
+            literal.clear();
+
+            let start = i;
+            i += 1;
+            if chars.get(i) == Some(&'%') {
+                self.out.push(b'%');
+                i += 1;
+                continue;
+            }
+
+            let mut spec = Spec::default();
+            while let Some(&flag) = chars.get(i) {
+                match flag {
+                    '-' => spec.left = true,
+                    '+' => spec.plus = true,
+                    ' ' => spec.space = true,
+                    '#' => spec.alternate = true,
+                    '0' => spec.zero = true,
+                    _ => break,
+                }
+                i += 1;
+            }
+            if chars.get(i) == Some(&'*') {
+                let width = self.next_int();
+                spec.left |= width < 0;
+                spec.width = width.unsigned_abs() as usize;
+                i += 1;
+            } else {
+                spec.width = number(&chars, &mut i);
+            }
+            if chars.get(i) == Some(&'.') {
+                i += 1;
+                spec.precision = Some(if chars.get(i) == Some(&'*') {
+                    i += 1;
+                    self.next_int().max(0) as usize
+                } else {
+                    number(&chars, &mut i)
+                });
+            }
+
+            let conversion = match chars.get(i) {
+                Some(&c) => c,
+                None => {
+                    let directive: String = chars[start..].iter().collect();
+                    return Err(format!("`{}': missing format character", directive));
+                }
+            };
+            i += 1;
+            if !self.convert(conversion, &spec)? {
+                return Ok(false);
+            }
+        }
+
+        self.out.extend(unescape(&literal, Escapes::Format).0);
+        Ok(true)
+    }
+
+    /// Writes one argument by `spec`, returning `Ok(false)` if it was a
+    /// `%b` argument with `\c`.
+    fn convert(&mut self, conversion: char, spec: &Spec) -> Result<bool, String> {
+        match conversion {
+            's' => {
+                let arg = self.next_arg();
+                let arg = match spec.precision {
+                    Some(precision) => arg.chars().take(precision).collect(),
+                    None => arg,
+                };
+                self.pad(spec, "", arg.as_bytes(), false);
+            },
+            'b' => {
+                let (mut bytes, stop) = unescape(&self.next_arg(), Escapes::Echo);
+                if let Some(precision) = spec.precision {
+                    bytes.truncate(precision);
+                }
+                self.pad(spec, "", &bytes, false);
+                if stop {
+                    return Ok(false);
+                }
+            },
+            'c' => {
+                let c: String = self.next_arg().chars().take(1).collect();
+                self.pad(spec, "", c.as_bytes(), false);
+            },
+            'q' => {
+                let quoted = utils::quote(&self.next_arg());
+                self.pad(spec, "", quoted.as_bytes(), false);
+            },
+            'd' | 'i' => {
+                let value = self.next_int();
+                let sign = if value < 0 { "-" } else if spec.plus { "+" } else if spec.space { " " } else { "" };
+                self.integer(spec, sign, value.unsigned_abs(), 10, false);
+            },
+            'o' | 'u' | 'x' | 'X' => {
+                let value = self.next_int() as u64;
+                let radix = match conversion { 'o' => 8, 'u' => 10, _ => 16 };
+                self.integer(spec, "", value, radix, conversion == 'X');
+            },
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = self.next_float();
+                self.float(spec, conversion, value);
+            },
+            _ => return Err(format!("`{}': invalid format character", conversion)),
+        }
+        Ok(true)
+    }
+
+    fn integer(&mut self, spec: &Spec, sign: &str, value: u64, radix: u32, upper: bool) {
+        let mut digits = match radix {
+            8 => format!("{:o}", value),
+            16 if upper => format!("{:X}", value),
+            16 => format!("{:x}", value),
+            _ => value.to_string(),
+        };
+        if let Some(precision) = spec.precision {
+            if precision == 0 && value == 0 {
+                digits.clear();
+            }
+            while digits.len() < precision {
+                digits.insert(0, '0');
+            }
+        }
+
+        let mut prefix = sign.to_string();
+        if spec.alternate {
+            match radix {
+                8 if !digits.starts_with('0') => digits.insert(0, '0'),
+                16 if value != 0 => prefix.push_str(if upper { "0X" } else { "0x" }),
+                _ => {},
+            }
+        }
+        let zero = spec.zero && spec.precision.is_none();
+        self.pad(spec, &prefix, digits.as_bytes(), zero);
+    }
+
+    fn float(&mut self, spec: &Spec, conversion: char, value: f64) {
+        let upper = conversion.is_ascii_uppercase();
+        let sign = if value.is_sign_negative() && !value.is_nan() {
+            "-"
+        } else if spec.plus {
+            "+"
+        } else if spec.space {
+            " "
+        } else {
+            ""
+        };
+
+        if !value.is_finite() {
+            let body = if value.is_nan() { "nan" } else { "inf" };
+            let body = if upper { body.to_uppercase() } else { body.to_string() };
+            self.pad(spec, sign, body.as_bytes(), false);
+            return;
+        }
+
+        let value = value.abs();
+        let precision = spec.precision.unwrap_or(6);
+        let body = match conversion.to_ascii_lowercase() {
+            'f' => fixed(value, precision, spec.alternate),
+            'e' => exponential(value, precision, spec.alternate),
+            _ => {
+                let precision = precision.max(1);
+                let exponent = if value == 0.0 { 0 } else { exponent_of(value, precision - 1) };
+                let body = if exponent < -4 || exponent >= precision as i32 {
+                    exponential(value, precision - 1, spec.alternate)
+                } else {
+                    fixed(value, (precision as i32 - 1 - exponent) as usize, spec.alternate)
+                };
+                if spec.alternate { body } else { strip_zeros(&body) }
+            }
+        };
+        let body = if upper { body.to_uppercase() } else { body };
+        self.pad(spec, sign, body.as_bytes(), spec.zero);
+    }
+
+    /// Writes `prefix` and `body` padded to the width of `spec`, with
+    /// zeros between them if `zero`.
+    fn pad(&mut self, spec: &Spec, prefix: &str, body: &[u8], zero: bool) {
+        let len = prefix.len() + String::from_utf8_lossy(body).chars().count();
+        let fill = spec.width.saturating_sub(len);
+        if spec.left {
+            self.out.extend_from_slice(prefix.as_bytes());
+            self.out.extend_from_slice(body);
+            self.out.extend(std::iter::repeat_n(b' ', fill));
+        } else if zero {
+            self.out.extend_from_slice(prefix.as_bytes());
+            self.out.extend(std::iter::repeat_n(b'0', fill));
+            self.out.extend_from_slice(body);
+        } else {
+            self.out.extend(std::iter::repeat_n(b' ', fill));
+            self.out.extend_from_slice(prefix.as_bytes());
+            self.out.extend_from_slice(body);
+        }
+    }
+
+    fn next_arg(&mut self) -> String {
+        let arg = self.args.get(self.next).cloned().unwrap_or_default();
+        self.next += 1;
+        arg
+    }
+
+    fn next_int(&mut self) -> i64 {
+        let arg = self.next_arg();
+        let (value, valid) = parse_int(&arg);
+        if !valid {
+            self.errors.push(format!("{}: invalid number", arg));
+        }
+        value
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let arg = self.next_arg();
+        if let Some(code) = char_code(&arg) {
+            return code as f64;
+        }
+        let trimmed = arg.trim_start();
+        if trimmed.is_empty() {
+            return 0.0;
+        }
+        match trimmed.parse() {
+            Ok(value) => value,
+            Err(_) => {
+                let (value, valid) = parse_int(&arg);
+                if !valid {
+                    self.errors.push(format!("{}: invalid number", arg));
+                }
+                value as f64
+            }
+        }
+    }
+}
+
+fn number(chars: &[char], i: &mut usize) -> usize {
+    let mut n = 0;
+    while let Some(digit) = chars.get(*i).and_then(|c| c.to_digit(10)) {
+        n = n * 10 + digit as usize;
+        *i += 1;
+    }
+    n
+}
+
+/// The value of a numeric argument: decimal, `0x` hex, `0` octal, or the
+/// character code after a leading quote. A bad argument gives the value of
+/// its longest valid prefix and `false`.
+fn parse_int(arg: &str) -> (i64, bool) {
+    if let Some(code) = char_code(arg) {
+        return (code as i64, true);
+    }
+    let s = arg.trim_start();
+    if s.is_empty() {
+        return (0, true);
+    }
+
+    let (negative, s) = match s.as_bytes()[0] {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let (radix, digits) = if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        (16, hex)
+    } else if s.len() > 1 && s.starts_with('0') {
+        (8, &s[1..])
+    } else {
+        (10, s)
+    };
+
+    let mut value: i64 = 0;
+    let mut count = 0;
+    for c in digits.chars() {
+        match c.to_digit(radix) {
+            Some(digit) => {
+                value = value.wrapping_mul(radix as i64).wrapping_add(digit as i64);
+                count += 1;
+            },
+            None => break,
+        }
+    }
+    let valid = count == digits.chars().count() && (count > 0 || radix == 8);
+    (if negative { value.wrapping_neg() } else { value }, valid)
+}
+
+/// The code of the character after a leading `'` or `"`.
+fn char_code(arg: &str) -> Option<u32> {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some('\'') | Some('"') => Some(chars.next().map_or(0, |c| c as u32)),
+        _ => None,
+    }
+}
+
+fn fixed(value: f64, precision: usize, alternate: bool) -> String {
+    let mut s = format!("{:.*}", precision, value);
+    if alternate && precision == 0 {
+        s.push('.');
+    }
+    s
+}
+
+/// `value` as `d.ddde+XX`, the way C writes it.
+fn exponential(value: f64, precision: usize, alternate: bool) -> String {
+    let s = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = s.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    let dot = if alternate && precision == 0 { "." } else { "" };
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, dot, sign, exponent.abs())
+}
+
+/// The decimal exponent of `value` once rounded to `precision` digits after
+/// the first.
+fn exponent_of(value: f64, precision: usize) -> i32 {
+    let s = format!("{:.*e}", precision, value);
+    s.split_once('e').unwrap().1.parse().unwrap()
+}
+
+/// Removes trailing zeros after the decimal point, as `%g` does.
+fn strip_zeros(s: &str) -> String {
+    let (number, exponent) = match s.find('e') {
+        Some(e) => s.split_at(e),
+        None => (s, ""),
+    };
+    if !number.contains('.') {
+        return s.to_string();
+    }
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", number, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    fn printf(args: &str) -> (String, String, i32) {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let captured = shell.capture(&format!("printf {}", args)).unwrap();
+        (captured.stdout_lossy(), captured.stderr_lossy(), captured.status)
+    }
+
+    fn out(args: &str) -> String {
+        printf(args).0
+    }
+
+    #[test]
+    fn formats_strings() {
+        assert_eq!(out(r"'%s-%s\n' a b"), "a-b\n");
+        assert_eq!(out(r"'[%5s][%-5s][%.2s]' ab cd xyz"), "[   ab][cd   ][xy]");
+        assert_eq!(out(r"'%c%c' hello w"), "hw");
+        assert_eq!(out(r"'%b|%s' 'a\tb' 'a\tb'"), "a\tb|a\\tb");
+        assert_eq!(out(r"'%b%s' 'x\cy' z"), "x");
+        assert_eq!(out(r"'%q %q' simple 'it is'"), r"simple 'it is'");
+        assert_eq!(out(r"'100%%\101\x42\n'"), "100%AB\n");
+    }
+
+    #[test]
+    fn formats_integers() {
+        assert_eq!(out("'%d %i %5d|%-4d|%05d' 42 -7 3 4 -5"), "42 -7     3|4   |-0005");
+        assert_eq!(out("'%+d % d %.3d' 1 2 3"), "+1  2 003");
+        assert_eq!(out("'%x %X %#x %o %#o %u' 255 255 255 8 8 -1"), "ff FF 0xff 10 010 18446744073709551615");
+        assert_eq!(out("'%d %d %d' 0x10 010 \\'A"), "16 8 65");
+        assert_eq!(out("'%*d|%-*d|%.*d' 4 1 3 2 3 5"), "   1|2  |005");
+    }
+
+    #[test]
+    fn formats_floats() {
+        assert_eq!(out("'%f %.2f %8.3f' 1.5 2.345 -3.14159"), "1.500000 2.35   -3.142");
+        assert_eq!(out("'%e %.2E' 12345.678 0.000123"), "1.234568e+04 1.23E-04");
+        assert_eq!(out("'%g %g %g %g' 100000 1000000 0.0001 0.00001"), "100000 1e+06 0.0001 1e-05");
+        assert_eq!(out("'%g %#g %G' 2.5 2.5 1e-10"), "2.5 2.50000 1E-10");
+        assert_eq!(out("'%05.1f|%+.0f' 2.25 3"), "002.2|+3");
+    }
+
+    #[test]
+    fn reuses_the_format() {
+        assert_eq!(out(r"'%s=%s\n' a 1 b 2 c"), "a=1\nb=2\nc=\n");
+        assert_eq!(out(r"'x\n' a b"), "x\n");
+        assert_eq!(out("'%s'"), "");
+    }
+
+    #[test]
+    fn reports_bad_numbers() {
+        let (stdout, stderr, status) = printf("'%d %d' 12abc x");
+        assert_eq!(stdout, "12 0");
+        assert_eq!(stderr, "crussh: printf: 12abc: invalid number\ncrussh: printf: x: invalid number\n");
+        assert_eq!(status, 1);
+        assert_eq!(printf("'%z'").2, 1);
+    }
+
+    #[test]
+    fn assigns_with_v() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.run("printf -v out '%03d' 7").unwrap();
+        assert_eq!(shell.var("out"), Some("007"));
+    }
+}
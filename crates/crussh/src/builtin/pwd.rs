@@ -0,0 +1,98 @@
+//! `pwd [-LP]`.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+pub(crate) struct Pwd;
+
+impl Builtin for Pwd {
+    fn name(&self) -> &str {
+        "pwd"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut physical = false;
+        for arg in args {
+            match arg.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                "--" => break,
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    io.error("pwd", format!("{}: invalid option", arg));
+                    let _ = writeln!(io.stderr, "pwd: usage: pwd [-LP]");
+                    return 2;
+                },
+                _ => break,
+            }
+        }
+
+        let dir = if physical {
+            match fs::canonicalize(shell.cwd()) {
+                Ok(dir) => dir.to_string_lossy().into_owned(),
+                Err(e) => {
+                    io.error("pwd", e);
+                    return 1;
+                }
+            }
+        } else {
+            logical(shell).unwrap_or_else(|| shell.cwd().to_string_lossy().into_owned())
+        };
+
+        match writeln!(io.stdout, "{}", dir) {
+            Ok(()) => 0,
+            Err(e) => {
+                io.error("pwd", format!("write error: {}", e));
+                1
+            }
+        }
+    }
+}
+
+/// `$PWD`, if it is an absolute path without `.` or `..` that names the
+/// working directory.
+fn logical(shell: &Shell) -> Option<String> {
+    let pwd = shell.var("PWD")?;
+    let path = Path::new(pwd);
+    let plain = path.is_absolute() && !pwd.split('/').any(|part| part == "." || part == "..");
+    let same = plain && fs::canonicalize(path).ok()? == fs::canonicalize(shell.cwd()).ok()?;
+    if same { Some(pwd.to_string()) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn prints_the_working_directory() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_cwd("/").unwrap();
+        shell.set_var("PWD", "/nowhere");
+        assert_eq!(shell.capture("pwd; pwd -P").unwrap().stdout_lossy(), "/\n/\n");
+
+        shell.set_var("PWD", "/./");
+        assert_eq!(shell.capture("pwd -L").unwrap().stdout_lossy(), "/\n");
+        assert_eq!(shell.capture("pwd -x").unwrap().status, 2);
+    }
+
+    #[test]
+    fn keeps_symbolic_links_with_l() {
+        let dir = std::env::temp_dir().join(format!("crussh-pwd-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("real")).unwrap();
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("link")).unwrap();
+        let real = dir.join("real").canonicalize().unwrap();
+
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_cwd(&real).unwrap();
+        shell.set_var("PWD", dir.join("link").to_string_lossy());
+        let captured = shell.capture("pwd; pwd -P").unwrap().stdout_lossy();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(captured, format!("{}\n{}\n", dir.join("link").display(), real.display()));
+    }
+}
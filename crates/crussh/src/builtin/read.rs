@@ -0,0 +1,262 @@
+//! `read [-r] [-p prompt] [-t timeout] [-d delim] [name...]`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::utils;
+
+pub(crate) struct Read;
+
+/// The status of a `read` that timed out, 128 plus `SIGALRM` as in bash.
+const TIMED_OUT: i32 = 142;
+
+const DEFAULT_IFS: &str = " \t\n";
+
+#[derive(Default)]
+struct Options {
+    raw: bool,
+    prompt: Option<String>,
+    timeout: Option<Duration>,
+    delimiter: Option<u8>,
+}
+
+impl Builtin for Read {
+    fn name(&self) -> &str {
+        "read"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (options, names) = match parse_options(args) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                io.error("read", e);
+                let _ = writeln!(io.stderr, "read: usage: read [-r] [-p prompt] [-t timeout] [-d delim] [name ...]");
+                return 2;
+            }
+        };
+        if let Some(name) = names.iter().find(|name| !utils::is_name(name)) {
+            io.error("read", format!("`{}': not a valid identifier", name));
+            return 1;
+        }
+
+        if options.timeout == Some(Duration::ZERO) {
+            return match io.stdin.ready(Duration::ZERO) {
+                Ok(true) => 0,
+                _ => 1,
+            };
+        }
+        if let Some(prompt) = &options.prompt {
+            if io.stdin.is_terminal() {
+                let _ = write!(io.stderr, "{}", prompt);
+                let _ = io.stderr.flush();
+            }
+        }
+
+        let (line, status) = read_line(io, &options);
+        let ifs = shell.var("IFS").unwrap_or(DEFAULT_IFS).to_string();
+        if names.is_empty() {
+            let bytes: Vec<u8> = line.iter().map(|&(byte, _)| byte).collect();
+            shell.set_var("REPLY", String::from_utf8_lossy(&bytes));
+        } else {
+            for (name, value) in names.iter().zip(split(&line, ifs.as_bytes(), names.len())) {
+                shell.set_var(name, value);
+            }
+        }
+        status
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(Options, &[String]), String> {
+    let mut options = Options::default();
+    let mut i = 0;
+
+    while let Some(arg) = args.get(i) {
+        if arg == "--" {
+            i += 1;
+            break;
+        }
+        if !arg.starts_with('-') || arg.len() == 1 {
+            break;
+        }
+        i += 1;
+
+        for (j, flag) in arg.char_indices().skip(1) {
+            if flag == 'r' {
+                options.raw = true;
+                continue;
+            }
+            if !"ptd".contains(flag) {
+                return Err(format!("-{}: invalid option", flag));
+            }
+            // The value is the rest of the word, or the next argument.
+            let value = if j + 1 < arg.len() {
+                arg[j + 1..].to_string()
+            } else {
+                i += 1;
+                args.get(i - 1).cloned().ok_or(format!("-{}: option requires an argument", flag))?
+            };
+            match flag {
+                'p' => options.prompt = Some(value),
+                't' => {
+                    let seconds: f64 = value.parse().ok().filter(|s: &f64| *s >= 0.0 && s.is_finite())
+                        .ok_or(format!("{}: invalid timeout specification", value))?;
+                    options.timeout = Some(Duration::from_secs_f64(seconds));
+                },
+                _ => options.delimiter = Some(value.bytes().next().unwrap_or(0)),
+            }
+            break;
+        }
+    }
+    Ok((options, &args[i..]))
+}
+
+/// Reads up to the delimiter a byte at a time, so that no input meant for
+/// the next command is used up. Each byte comes with whether a backslash
+/// quoted it. The status is 1 at end of file and [`TIMED_OUT`] if the
+/// timeout passed.
+fn read_line(io: &mut Io, options: &Options) -> (Vec<(u8, bool)>, i32) {
+    let delimiter = options.delimiter.unwrap_or(b'\n');
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut line = Vec::new();
+    let mut escaped = false;
+    let mut byte = [0];
+
+    loop {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !matches!(io.stdin.ready(remaining), Ok(true)) {
+                return (line, TIMED_OUT);
+            }
+        }
+        match io.stdin.read(&mut byte) {
+            Ok(1) => {},
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            _ => return (line, 1),
+        }
+
+        let byte = byte[0];
+        if escaped {
+            escaped = false;
+            // A quoted newline continues the line.
+            if byte != b'\n' {
+                line.push((byte, true));
+            }
+        } else if byte == b'\\' && !options.raw {
+            escaped = true;
+        } else if byte == delimiter {
+            return (line, 0);
+        } else {
+            line.push((byte, false));
+        }
+    }
+}
+
+/// Splits `line` into at most `count` fields at unquoted `ifs` characters;
+/// the last field takes the rest of the line. Whitespace in `ifs` is
+/// trimmed from the ends and runs of it separate fields, while each other
+/// `ifs` character ends one field, as in word splitting.
+fn split(line: &[(u8, bool)], ifs: &[u8], count: usize) -> Vec<String> {
+    let is_space = |&(byte, quoted): &(u8, bool)| !quoted && ifs.contains(&byte) && b" \t\n".contains(&byte);
+    let is_delimiter = |&(byte, quoted): &(u8, bool)| !quoted && ifs.contains(&byte);
+    let text = |bytes: &[(u8, bool)]| {
+        String::from_utf8_lossy(&bytes.iter().map(|&(byte, _)| byte).collect::<Vec<u8>>()).into_owned()
+    };
+
+    let mut fields = Vec::new();
+    let mut rest = line;
+    while rest.first().is_some_and(is_space) {
+        rest = &rest[1..];
+    }
+
+    while fields.len() + 1 < count && !rest.is_empty() {
+        let end = rest.iter().position(is_delimiter).unwrap_or(rest.len());
+        fields.push(text(&rest[..end]));
+        rest = &rest[end..];
+
+        while rest.first().is_some_and(is_space) {
+            rest = &rest[1..];
+        }
+        if rest.first().is_some_and(is_delimiter) {
+            rest = &rest[1..];
+            while rest.first().is_some_and(is_space) {
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    while rest.last().is_some_and(is_space) {
+        rest = &rest[..rest.len() - 1];
+    }
+    fields.push(text(rest));
+    fields.resize(count, String::new());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    fn read(input: &str, command: &str) -> (Shell, i32) {
+        let recorder = Recorder::new().with_output("input", input.as_bytes());
+        let mut shell = Shell::with_executor(recorder);
+        let status = shell.run(&format!("input | {}", command)).unwrap();
+        (shell, status)
+    }
+
+    #[test]
+    fn splits_into_variables() {
+        let (shell, status) = read("  one  two three  \nnext\n", "read a b");
+        assert_eq!(status, 0);
+        assert_eq!(shell.var("a"), Some("one"));
+        assert_eq!(shell.var("b"), Some("two three"));
+
+        let (shell, _) = read("x\n", "read a b c");
+        assert_eq!((shell.var("a"), shell.var("b"), shell.var("c")), (Some("x"), Some(""), Some("")));
+
+        let (shell, _) = read("a:b::c\n", "IFS=: read w x y z");
+        assert_eq!(
+            [shell.var("w"), shell.var("x"), shell.var("y"), shell.var("z")],
+            [Some("a"), Some("b"), Some(""), Some("c")]
+        );
+    }
+
+    #[test]
+    fn handles_backslashes() {
+        let (shell, _) = read("a\\ b c\\\nd\n", "read x y");
+        assert_eq!((shell.var("x"), shell.var("y")), (Some("a b"), Some("cd")));
+
+        let (shell, _) = read("a\\ b\n", "read -r x y");
+        assert_eq!((shell.var("x"), shell.var("y")), (Some("a\\"), Some("b")));
+    }
+
+    #[test]
+    fn uses_reply_and_delimiters() {
+        let (shell, _) = read("  kept as is  \n", "read");
+        assert_eq!(shell.var("REPLY"), Some("  kept as is  "));
+
+        let (shell, status) = read("a,b;c", "read -d ';' x");
+        assert_eq!((shell.var("x"), status), (Some("a,b"), 0));
+
+        let (shell, status) = read("no newline", "read x");
+        assert_eq!((shell.var("x"), status), (Some("no newline"), 1));
+    }
+
+    #[test]
+    fn checks_arguments() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        assert_eq!(shell.run("read -q"), Ok(2));
+        assert_eq!(shell.run("read -t soon"), Ok(2));
+        assert_eq!(shell.run("read 1x"), Ok(1));
+    }
+
+    #[test]
+    fn times_out() {
+        let mut shell = Shell::new();
+        let captured = shell.capture("sleep 0.5 | { read -t 0.1 -p 'prompt? ' x; echo $?; }; read -t 0 < /dev/null; echo $?").unwrap();
+        assert_eq!(captured.stdout_lossy(), "142\n0\n");
+        assert_eq!(captured.stderr_lossy(), "");
+    }
+}
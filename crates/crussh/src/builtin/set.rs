@@ -0,0 +1,227 @@
+//! `set [-efnuxC] [-o option] [--] [arg...]` and `shopt [-pqsu] [-o]
+//! [option...]`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::options::{SET_OPTIONS, SHOPT_OPTIONS};
+use crate::utils;
+
+const SET_USAGE: &str = "set: usage: set [-efnuxC] [-o option-name] [--] [-] [arg ...]";
+
+pub(crate) struct Set;
+
+impl Builtin for Set {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        if args.is_empty() {
+            let mut vars: Vec<(&String, &str)> = shell.vars.iter().map(|(name, var)| (name, var.value.as_str())).collect();
+            vars.sort();
+            for (name, value) in vars {
+                let _ = writeln!(io.stdout, "{}={}", name, utils::quote(value));
+            }
+            return 0;
+        }
+
+        let mut args = args.iter();
+        let mut positional = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--" => {
+                    positional = Some(args.by_ref().cloned().collect());
+                    break;
+                },
+                // `set -` ends the options too, but leaves the parameters
+                // alone unless it is followed by some, and turns off `-x`.
+                "-" => {
+                    shell.options.xtrace = false;
+                    let rest: Vec<String> = args.by_ref().cloned().collect();
+                    positional = Some(rest).filter(|rest| !rest.is_empty());
+                    break;
+                },
+                _ if arg.len() > 1 && (arg.starts_with('-') || arg.starts_with('+')) => {
+                    let on = arg.starts_with('-');
+                    for letter in arg[1..].chars() {
+                        let name = match letter {
+                            'o' => match args.next() {
+                                Some(name) => name.as_str(),
+                                None => {
+                                    list(shell, on, io);
+                                    continue;
+                                },
+                            },
+                            letter => match SET_OPTIONS.iter().find(|(l, _)| *l == letter) {
+                                Some((_, name)) => name,
+                                None => {
+                                    io.error("set", format!("{}{}: invalid option", &arg[..1], letter));
+                                    let _ = writeln!(io.stderr, "{}", SET_USAGE);
+                                    return 2;
+                                },
+                            },
+                        };
+                        match shell.options.get_mut(name).filter(|_| !SHOPT_OPTIONS.contains(&name)) {
+                            Some(option) => *option = on,
+                            None => {
+                                io.error("set", format!("{}: invalid option name", name));
+                                return 2;
+                            },
+                        }
+                    }
+                },
+                _ => {
+                    positional = Some(std::iter::once(arg).chain(args).cloned().collect());
+                    break;
+                },
+            }
+        }
+        if let Some(positional) = positional {
+            shell.set_args(positional);
+        }
+        0
+    }
+}
+
+/// `set -o` lists the options as a table, and `set +o` as the commands
+/// that would set them again.
+fn list(shell: &mut Shell, table: bool, io: &mut Io) {
+    for (_, name) in SET_OPTIONS {
+        let on = shell.options.get_mut(name).is_some_and(|option| *option);
+        let _ = if table {
+            writeln!(io.stdout, "{:<15}\t{}", name, if on { "on" } else { "off" })
+        } else {
+            writeln!(io.stdout, "set {}o {}", if on { '-' } else { '+' }, name)
+        };
+    }
+}
+
+pub(crate) struct Shopt;
+
+impl Builtin for Shopt {
+    fn name(&self) -> &str {
+        "shopt"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let (mut set, mut unset, mut print, mut quiet, mut set_options) = (false, false, false, false, false);
+        while let Some((arg, rest)) = args.split_first() {
+            if arg == "--" {
+                args = rest;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            for c in arg[1..].chars() {
+                match c {
+                    's' => set = true,
+                    'u' => unset = true,
+                    'p' => print = true,
+                    'q' => quiet = true,
+                    'o' => set_options = true,
+                    _ => {
+                        io.error("shopt", format!("-{}: invalid option", c));
+                        let _ = writeln!(io.stderr, "shopt: usage: shopt [-pqsu] [-o] [optname ...]");
+                        return 2;
+                    },
+                }
+            }
+            args = rest;
+        }
+        if set && unset {
+            io.error("shopt", "cannot set and unset shell options simultaneously");
+            return 1;
+        }
+
+        let known: Vec<&str> = if set_options {
+            SET_OPTIONS.iter().map(|(_, name)| *name).collect()
+        } else {
+            SHOPT_OPTIONS.to_vec()
+        };
+        let names: Vec<&str> = if args.is_empty() { known.clone() } else { args.iter().map(String::as_str).collect() };
+
+        let mut status = 0;
+        for name in names {
+            let option = match shell.options.get_mut(name).filter(|_| known.contains(&name)) {
+                Some(option) => option,
+                None => {
+                    io.error("shopt", format!("{}: invalid shell option name", name));
+                    status = 1;
+                    continue;
+                },
+            };
+            if (set || unset) && !args.is_empty() {
+                *option = set;
+                continue;
+            }
+            let on = *option;
+            // Without names, `-s` and `-u` list the options that are on or off.
+            if (set || unset) && on != set {
+                continue;
+            }
+            if !on && !args.is_empty() {
+                status = 1;
+            }
+            if quiet {
+                continue;
+            }
+            let _ = match (print, set_options) {
+                (true, false) => writeln!(io.stdout, "shopt {} {}", if on { "-s" } else { "-u" }, name),
+                (true, true) => writeln!(io.stdout, "set {}o {}", if on { '-' } else { '+' }, name),
+                (false, _) => writeln!(io.stdout, "{:<15}\t{}", name, if on { "on" } else { "off" }),
+            };
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::{Fd, Recorder};
+
+    #[test]
+    fn sets_options_and_parameters() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("set -eu -o noclobber +e; set -- a 'b c'; echo $- $#").unwrap();
+        assert!(shell.options().nounset && shell.options().noclobber && !shell.options().errexit);
+        shell.run("set -x -; set - d; echo $1 $-; set +o").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&recorder.written(&Fd::Inherited(1))),
+            "uC 2\nd uC\nset +o errexit\nset -o noclobber\nset +o noexec\nset +o noglob\nset -o nounset\nset +o xtrace\n"
+        );
+
+        let captured = shell.capture("set -z").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: set: -z: invalid option\nset: usage: set [-efnuxC] [-o option-name] [--] [-] [arg ...]\n");
+        let captured = shell.capture("set -o dotglob").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: set: dotglob: invalid option name\n");
+        assert_eq!(captured.status, 2);
+    }
+
+    #[test]
+    fn sets_and_queries_shell_options() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.run("shopt -s nullglob dotglob; shopt -u expand_aliases").unwrap();
+        assert!(shell.options().nullglob && shell.options().dotglob && !shell.options().expand_aliases);
+
+        let captured = shell.capture("shopt; shopt -p nullglob; shopt -u; shopt -po errexit").unwrap();
+        assert_eq!(
+            captured.stdout_lossy(),
+            "dotglob        \ton\nexpand_aliases \toff\njoblog         \toff\nnullglob       \ton\nshopt -s nullglob\n\
+             expand_aliases \toff\njoblog         \toff\nset +o errexit\n"
+        );
+        assert_eq!(shell.run("shopt -q nullglob dotglob"), Ok(0));
+        assert_eq!(shell.run("shopt -q nullglob expand_aliases"), Ok(1));
+        let captured = shell.capture("shopt -s nope; shopt -su dotglob").unwrap();
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: shopt: nope: invalid shell option name\n\
+             crussh: shopt: cannot set and unset shell options simultaneously\n"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! `source file [arg...]` and `. file [arg...]`.
+
+use std::io::Write;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use crate::builtin::{Builtin, Io};
+use crate::{Error, Shell};
+
+pub(crate) struct Source {
+    /// Whether this is `.` rather than `source`.
+    pub(crate) dot: bool,
+}
+
+impl Builtin for Source {
+    fn name(&self) -> &str {
+        if self.dot { "." } else { "source" }
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let args = match args.first() {
+            Some(arg) if arg == "--" => &args[1..],
+            _ => args,
+        };
+        let (file, args) = match args.split_first() {
+            Some(split) => split,
+            None => {
+                io.error(self.name(), "filename argument required");
+                let _ = writeln!(io.stderr, "{}: usage: {} filename [arguments]", self.name(), self.name());
+                return 2;
+            }
+        };
+
+        let path = find(shell, file).unwrap_or_else(|| PathBuf::from(file));
+        // The arguments replace the positional parameters while the file
+        // runs; without any it sees the caller's.
+        let saved = if args.is_empty() { None } else { Some(mem::replace(&mut shell.positional, args.to_vec())) };
+        let result = shell.run_script(&path);
+        if let Some(positional) = saved {
+            shell.positional = positional;
+        }
+
+        match result {
+            Ok(status) => status,
+            Err(e) => {
+                let _ = writeln!(io.stderr, "crussh: {}", e);
+                match e {
+                    // As in bash, a file that cannot be read is a plain
+                    // failure rather than a missing command.
+                    Error::Exec(_) => 1,
+                    e => e.status(),
+                }
+            }
+        }
+    }
+}
+
+/// A file named without a `/` is looked for in `$PATH`, where it need not
+/// be executable, before the working directory.
+fn find(shell: &Shell, file: &str) -> Option<PathBuf> {
+    if file.contains('/') {
+        return None;
+    }
+    shell.var("PATH")?.split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| Path::new(dir).join(file))
+        .find(|path| shell.cwd().join(path).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::Shell;
+
+    #[test]
+    fn runs_files_in_the_shell() {
+        let dir = env::temp_dir().join(format!("crussh-source-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("lib.sh"), "alias say=echo\nx=$1\nsay \"$x\" $#\nf() {\n  echo in f\n}\n").unwrap();
+        fs::write(dir.join("bad.sh"), "echo before\nnope\necho after\nif true\n").unwrap();
+
+        let mut shell = Shell::new();
+        shell.set_cwd(&dir).unwrap();
+        shell.set_args(vec!["outer".to_string()]);
+        let captured = shell.capture("source ./lib.sh one two; f; echo $1 $x").unwrap();
+        assert_eq!(captured.stdout_lossy(), "one 2\nin f\nouter one\n");
+
+        shell.set_var("PATH", dir.to_string_lossy());
+        shell.set_cwd("/").unwrap();
+        let captured = shell.capture(". bad.sh").unwrap();
+        assert_eq!(captured.stdout_lossy(), "before\nafter\n");
+        let bad = dir.join("bad.sh");
+        // The error for line 2 is printed with `eprintln!`, which the test
+        // harness captures itself.
+        assert_eq!(
+            captured.stderr_lossy(),
+            format!("crussh: {}: line 4: syntax error: unexpected end of input while looking for `then'\n", bad.display())
+        );
+        assert_eq!(captured.status, 2);
+
+        let captured = shell.capture("source /nonexistent; echo $?").unwrap();
+        assert_eq!(captured.stdout_lossy(), "1\n");
+        assert_eq!(captured.stderr_lossy(), "crussh: /nonexistent: No such file or directory\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
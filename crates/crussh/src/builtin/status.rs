@@ -0,0 +1,20 @@
+//! `true`, `false` and `:`.
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+/// A builtin that does nothing but exit with `status`.
+pub(crate) struct Status {
+    pub(crate) name: &'static str,
+    pub(crate) status: i32,
+}
+
+impl Builtin for Status {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn run(&self, _shell: &mut Shell, _args: &[String], _io: &mut Io) -> i32 {
+        self.status
+    }
+}
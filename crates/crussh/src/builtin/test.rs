@@ -0,0 +1,303 @@
+//! `test expr` and `[ expr ]`.
+
+use std::fs::{self, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::PathBuf;
+
+use nix::unistd::{AccessFlags, access, getegid, geteuid, isatty};
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+pub(crate) struct Test {
+    /// Whether this is `[`, which needs a closing `]`.
+    pub(crate) bracket: bool,
+}
+
+impl Builtin for Test {
+    fn name(&self) -> &str {
+        if self.bracket { "[" } else { "test" }
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let args = if self.bracket {
+            match args.split_last() {
+                Some((last, args)) if last == "]" => args,
+                _ => {
+                    io.error("[", "missing `]'");
+                    return 2;
+                }
+            }
+        } else {
+            args
+        };
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match (Evaluator { shell, args: &args }).test() {
+            Ok(true) => 0,
+            Ok(false) => 1,
+            Err(e) => {
+                io.error(self.name(), e);
+                2
+            }
+        }
+    }
+}
+
+struct Evaluator<'a> {
+    shell: &'a Shell,
+    args: &'a [&'a str],
+}
+
+impl Evaluator<'_> {
+
+    /// Evaluates the arguments by the POSIX rules for up to four of them,
+    /// which settle what `-a`, `!` and parentheses mean by position, and
+    /// as a full expression beyond that.
+    fn test(&self) -> Result<bool, String> {
+        let args = self.args;
+        match args.len() {
+            0 => Ok(false),
+            1 => Ok(!args[0].is_empty()),
+            2 if args[0] == "!" => Ok(args[1].is_empty()),
+            2 if is_unary(args[0]) => self.unary(args[0], args[1]),
+            2 => Err(format!("{}: unary operator expected", args[0])),
+            3 if is_binary(args[1]) => self.binary(args[0], args[1], args[2]),
+            3 if args[1] == "-a" => Ok(!args[0].is_empty() && !args[2].is_empty()),
+            3 if args[1] == "-o" => Ok(!args[0].is_empty() || !args[2].is_empty()),
+            3 if args[0] == "!" => self.sub(&args[1..]).map(|result| !result),
+            3 if args[0] == "(" && args[2] == ")" => Ok(!args[1].is_empty()),
+            3 => Err(format!("{}: binary operator expected", args[1])),
+            4 if args[0] == "!" => self.sub(&args[1..]).map(|result| !result),
+            4 if args[0] == "(" && args[3] == ")" => self.sub(&args[1..3]),
+            _ => {
+                let mut pos = 0;
+                let result = self.or(&mut pos)?;
+                match args.get(pos) {
+                    None => Ok(result),
+                    Some(arg) => Err(format!("{}: unexpected argument", arg)),
+                }
+            }
+        }
+    }
+
+    fn sub(&self, args: &[&str]) -> Result<bool, String> {
+        Evaluator { shell: self.shell, args }.test()
+    }
+
+    fn or(&self, pos: &mut usize) -> Result<bool, String> {
+        let mut result = self.and(pos)?;
+        while self.args.get(*pos) == Some(&"-o") {
+            *pos += 1;
+            result |= self.and(pos)?;
+        }
+        Ok(result)
+    }
+
+    fn and(&self, pos: &mut usize) -> Result<bool, String> {
+        let mut result = self.not(pos)?;
+        while self.args.get(*pos) == Some(&"-a") {
+            *pos += 1;
+            result &= self.not(pos)?;
+        }
+        Ok(result)
+    }
+
+    fn not(&self, pos: &mut usize) -> Result<bool, String> {
+        if self.args.get(*pos) == Some(&"!") {
+            *pos += 1;
+            return self.not(pos).map(|result| !result);
+        }
+        self.primary(pos)
+    }
+
+    fn primary(&self, pos: &mut usize) -> Result<bool, String> {
+        let args = self.args;
+        let arg = match args.get(*pos) {
+            Some(&arg) => arg,
+            None => return Err("argument expected".to_string()),
+        };
+
+        if arg == "(" {
+            *pos += 1;
+            let result = self.or(pos)?;
+            if args.get(*pos) != Some(&")") {
+                return Err("`)' expected".to_string());
+            }
+            *pos += 1;
+            return Ok(result);
+        }
+        if let Some(&op) = args.get(*pos + 1).filter(|&&op| is_binary(op)) {
+            if let Some(&right) = args.get(*pos + 2) {
+                *pos += 3;
+                return self.binary(arg, op, right);
+            }
+        }
+        if is_unary(arg) {
+            if let Some(&operand) = args.get(*pos + 1) {
+                *pos += 2;
+                return self.unary(arg, operand);
+            }
+        }
+        *pos += 1;
+        Ok(!arg.is_empty())
+    }
+
+    fn unary(&self, op: &str, operand: &str) -> Result<bool, String> {
+        match op {
+            "-n" => return Ok(!operand.is_empty()),
+            "-z" => return Ok(operand.is_empty()),
+            "-v" => return Ok(self.shell.var(operand).is_some()),
+            "-t" => {
+                let fd = integer(operand)?;
+                return Ok(isatty(fd as i32).unwrap_or(false));
+            },
+            _ => {},
+        }
+
+        let path = match self.path(operand) {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+        let metadata = match if op == "-h" || op == "-L" { fs::symlink_metadata(&path) } else { fs::metadata(&path) } {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(false),
+        };
+        let file_type = metadata.file_type();
+        let mode = metadata.mode();
+
+        Ok(match op {
+            "-e" => true,
+            "-f" => file_type.is_file(),
+            "-d" => file_type.is_dir(),
+            "-b" => file_type.is_block_device(),
+            "-c" => file_type.is_char_device(),
+            "-p" => file_type.is_fifo(),
+            "-S" => file_type.is_socket(),
+            "-h" | "-L" => file_type.is_symlink(),
+            "-s" => metadata.len() > 0,
+            "-g" => mode & 0o2000 != 0,
+            "-u" => mode & 0o4000 != 0,
+            "-k" => mode & 0o1000 != 0,
+            "-r" => access(&path, AccessFlags::R_OK).is_ok(),
+            "-w" => access(&path, AccessFlags::W_OK).is_ok(),
+            "-x" => access(&path, AccessFlags::X_OK).is_ok(),
+            "-O" => metadata.uid() == geteuid().as_raw(),
+            "-G" => metadata.gid() == getegid().as_raw(),
+            "-N" => metadata.mtime() > metadata.atime(),
+            _ => unreachable!("unknown unary operator {}", op),
+        })
+    }
+
+    fn binary(&self, left: &str, op: &str, right: &str) -> Result<bool, String> {
+        Ok(match op {
+            "=" | "==" => left == right,
+            "!=" => left != right,
+            "<" => left < right,
+            ">" => left > right,
+            "-eq" => integer(left)? == integer(right)?,
+            "-ne" => integer(left)? != integer(right)?,
+            "-lt" => integer(left)? < integer(right)?,
+            "-le" => integer(left)? <= integer(right)?,
+            "-gt" => integer(left)? > integer(right)?,
+            "-ge" => integer(left)? >= integer(right)?,
+            "-nt" | "-ot" => {
+                let (newer, older) = if op == "-nt" { (left, right) } else { (right, left) };
+                match (self.metadata(newer), self.metadata(older)) {
+                    (Some(newer), Some(older)) => modified(&newer) > modified(&older),
+                    (newer, _) => newer.is_some(),
+                }
+            },
+            "-ef" => match (self.metadata(left), self.metadata(right)) {
+                (Some(left), Some(right)) => left.dev() == right.dev() && left.ino() == right.ino(),
+                _ => false,
+            },
+            _ => unreachable!("unknown binary operator {}", op),
+        })
+    }
+
+    /// `operand` relative to the shell's working directory; an empty one
+    /// names no file.
+    fn path(&self, operand: &str) -> Option<PathBuf> {
+        if operand.is_empty() { None } else { Some(self.shell.cwd().join(operand)) }
+    }
+
+    fn metadata(&self, operand: &str) -> Option<Metadata> {
+        fs::metadata(self.path(operand)?).ok()
+    }
+}
+
+fn is_unary(op: &str) -> bool {
+    matches!(
+        op,
+        "-b" | "-c" | "-d" | "-e" | "-f" | "-g" | "-h" | "-k" | "-L" | "-n" | "-N" | "-O" | "-G" | "-p"
+            | "-r" | "-s" | "-S" | "-t" | "-u" | "-v" | "-w" | "-x" | "-z"
+    )
+}
+
+fn is_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "=" | "==" | "!=" | "<" | ">" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot" | "-ef"
+    )
+}
+
+fn integer(s: &str) -> Result<i64, String> {
+    s.trim().parse().map_err(|_| format!("{}: integer expression expected", s))
+}
+
+fn modified(metadata: &Metadata) -> (i64, i64) {
+    (metadata.mtime(), metadata.mtime_nsec())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    fn status(expr: &str) -> i32 {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_cwd("/").unwrap();
+        shell.run(expr).unwrap()
+    }
+
+    #[test]
+    fn tests_strings() {
+        assert_eq!(status("test"), 1);
+        assert_eq!(status("test ''"), 1);
+        assert_eq!(status("test -n x && test -z ''"), 0);
+        assert_eq!(status("[ abc = abc ] && [ a != b ] && [ a '<' b ]"), 0);
+        assert_eq!(status("[ abc == abd ]"), 1);
+        assert_eq!(status("[ -n ]"), 0);
+        assert_eq!(status("[ ! -z ]"), 1);
+    }
+
+    #[test]
+    fn tests_integers() {
+        assert_eq!(status("[ 3 -lt 10 ] && [ -2 -le -2 ] && [ 7 -ne 8 ] && [ 9 -ge 1 ]"), 0);
+        assert_eq!(status("[ 10 -gt 3 -a 2 -eq 3 ]"), 1);
+        assert_eq!(status("[ 1 -eq one ]"), 2);
+    }
+
+    #[test]
+    fn tests_files() {
+        assert_eq!(status("[ -d tmp ] && [ -e /bin/sh ] && [ -x /bin/sh ] && [ ! -f / ]"), 0);
+        assert_eq!(status("[ -e '' ]"), 1);
+        assert_eq!(status("[ -f /no/such/file ]"), 1);
+        assert_eq!(status("[ / -ef /tmp/.. ]"), 0);
+    }
+
+    #[test]
+    fn combines_expressions() {
+        assert_eq!(status("[ '(' a = b ')' -o ! '(' -z '' -a x ')' ]"), 1);
+        assert_eq!(status("[ '(' a = b ')' -o '(' c = c ')' ]"), 0);
+        assert_eq!(status("test ! a = b"), 0);
+        assert_eq!(status("[ a = a -a '(' b ]"), 2);
+    }
+
+    #[test]
+    fn needs_a_closing_bracket() {
+        assert_eq!(status("[ a = a"), 2);
+        assert_eq!(status("test a = a ]"), 2);
+    }
+}
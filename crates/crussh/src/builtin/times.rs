@@ -0,0 +1,25 @@
+//! `times`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+use crate::time;
+
+pub(crate) struct Times;
+
+impl Builtin for Times {
+    fn name(&self) -> &str {
+        "times"
+    }
+
+    /// Prints the user and system time the shell has used, then those of
+    /// the processes it has waited for.
+    fn run(&self, shell: &mut Shell, _args: &[String], io: &mut Io) -> i32 {
+        let own = shell.executor.usage();
+        for usage in [own, shell.children_usage] {
+            let _ = writeln!(io.stdout, "{} {}", time::seconds(usage.user, 3, true), time::seconds(usage.system, 3, true));
+        }
+        0
+    }
+}
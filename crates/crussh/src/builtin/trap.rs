@@ -0,0 +1,142 @@
+//! `trap [-lp] [[action] condition...]`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::alias::quote;
+use crate::builtin::{Builtin, Io};
+use crate::trap::{self, Condition};
+
+pub(crate) struct Trap;
+
+impl Builtin for Trap {
+    fn name(&self) -> &str {
+        "trap"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let mut print = false;
+        while let Some((arg, rest)) = args.split_first() {
+            match arg.as_str() {
+                "-l" => {
+                    let _ = trap::list_signals(&mut io.stdout);
+                    return 0;
+                },
+                "-p" => print = true,
+                "--" => {
+                    args = rest;
+                    break;
+                },
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    io.error("trap", format!("{}: invalid option", arg));
+                    let _ = writeln!(io.stderr, "trap: usage: trap [-lp] [[action] condition ...]");
+                    return 2;
+                },
+                _ => break,
+            }
+            args = rest;
+        }
+
+        if print || args.is_empty() {
+            let traps: Vec<(Condition, String)> = if args.is_empty() {
+                shell.traps.iter().map(|(&condition, action)| (condition, action.clone())).collect()
+            } else {
+                args.iter()
+                    .filter_map(|arg| arg.parse().ok())
+                    .filter_map(|condition| shell.traps.get(&condition).map(|action| (condition, action.clone())))
+                    .collect()
+            };
+            for (condition, action) in traps {
+                let _ = writeln!(io.stdout, "trap -- {} {}", quote(&action), condition);
+            }
+            return 0;
+        }
+
+        // A lone condition, or a first one given by number, is reset.
+        let (action, conditions) = match args {
+            [_] => (None, args),
+            [first, ..] if first.parse::<u32>().is_ok() => (None, args),
+            [first, rest @ ..] if first == "-" => (None, rest),
+            [first, rest @ ..] => (Some(first.clone()), rest),
+            [] => unreachable!(),
+        };
+        let mut status = 0;
+        for arg in conditions {
+            match arg.parse() {
+                Ok(condition) => shell.set_trap(condition, action.clone()),
+                Err(()) => {
+                    io.error("trap", format!("{}: invalid signal specification", arg));
+                    status = 1;
+                },
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::sys::signal::Signal;
+
+    use crate::Shell;
+    use crate::exec::{Disposition, Fd, Recorder};
+
+    #[test]
+    fn sets_lists_and_resets() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run(r#"trap 'echo "it'\''s over"' EXIT; trap '' int; trap 'echo hup' 1 TERM"#).unwrap();
+        assert_eq!(recorder.disposition(Signal::SIGINT), Disposition::Ignore);
+        assert_eq!(recorder.disposition(Signal::SIGHUP), Disposition::Catch);
+
+        shell.run("trap; trap -p EXIT").unwrap();
+        let listed = "trap -- 'echo \"it'\\''s over\"' EXIT\ntrap -- 'echo hup' SIGHUP\ntrap -- '' SIGINT\n\
+                      trap -- 'echo hup' SIGTERM\ntrap -- 'echo \"it'\\''s over\"' EXIT\n";
+        assert_eq!(String::from_utf8_lossy(&recorder.written(&Fd::Inherited(1))), listed);
+
+        let captured = shell.capture("trap x NOPE").unwrap();
+        assert_eq!(captured.stderr_lossy(), "crussh: trap: NOPE: invalid signal specification\n");
+        assert_eq!(captured.status, 1);
+        shell.run("trap - HUP; trap 15; trap int").unwrap();
+        assert_eq!(recorder.disposition(Signal::SIGHUP), Disposition::Default);
+        assert_eq!(recorder.disposition(Signal::SIGINT), Disposition::Default);
+
+        shell.run_exit_trap();
+        shell.run_exit_trap();
+        shell.run("trap").unwrap();
+        assert_eq!(String::from_utf8_lossy(&recorder.written(&Fd::Inherited(1))), format!("{}it's over\n", listed));
+
+        // An interactive shell goes back to ignoring the terminal's signals.
+        shell.options_mut().interactive = true;
+        shell.run("trap 'echo int' INT QUIT; trap - INT").unwrap();
+        assert_eq!(recorder.disposition(Signal::SIGINT), Disposition::IgnoreInShell);
+        assert_eq!(recorder.disposition(Signal::SIGQUIT), Disposition::Catch);
+        assert_eq!(recorder.disposition(Signal::SIGTSTP), Disposition::IgnoreInShell);
+    }
+
+    #[test]
+    fn runs_signal_traps_between_commands() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("trap 'echo caught $?' USR1").unwrap();
+        recorder.raise(Signal::SIGUSR1);
+        recorder.raise(Signal::SIGUSR2);
+        shell.run("false; echo after $?").unwrap();
+        assert_eq!(recorder.written(&Fd::Inherited(1)), b"caught 0\nafter 1\n");
+    }
+
+    #[test]
+    fn runs_err_and_debug_traps() {
+        let recorder = Recorder::new().with_status("fail", 3);
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("trap 'echo err $?' ERR").unwrap();
+        shell.run("fail; { fail; }; fail && a; a && fail; ! a; if fail; then :; fi; (fail); f() { fail; a; }; f").unwrap();
+        assert_eq!(recorder.written(&Fd::Inherited(1)), b"err 3\nerr 3\nerr 3\nerr 3\n");
+
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("trap 'echo next' DEBUG; a; b | c; trap - DEBUG; d").unwrap();
+        assert_eq!(recorder.written(&Fd::Inherited(1)), b"next\nnext\nnext\nnext\n");
+    }
+}
@@ -0,0 +1,180 @@
+//! `ulimit [-SHa] [-cdefilmnqrstuvxR] [limit]`.
+
+use std::io::Write;
+
+use nix::sys::resource::Resource;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+const USAGE: &str = "ulimit: usage: ulimit [-SHa] [-cdefilmnqrstuvxR] [limit]";
+
+/// A limit `ulimit` knows: the option that selects it, how it is
+/// described, the unit it is given in and how many of the kernel's units
+/// make one.
+struct Kind {
+    option: char,
+    description: &'static str,
+    unit: Option<&'static str>,
+    resource: Resource,
+    scale: u64,
+}
+
+const KINDS: &[Kind] = &[
+    #[cfg(target_os = "linux")]
+    Kind { option: 'R', description: "real-time non-blocking time", unit: Some("microseconds"), resource: Resource::RLIMIT_RTTIME, scale: 1 },
+    Kind { option: 'c', description: "core file size", unit: Some("blocks"), resource: Resource::RLIMIT_CORE, scale: 1024 },
+    Kind { option: 'd', description: "data seg size", unit: Some("kbytes"), resource: Resource::RLIMIT_DATA, scale: 1024 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'e', description: "scheduling priority", unit: None, resource: Resource::RLIMIT_NICE, scale: 1 },
+    Kind { option: 'f', description: "file size", unit: Some("blocks"), resource: Resource::RLIMIT_FSIZE, scale: 1024 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'i', description: "pending signals", unit: None, resource: Resource::RLIMIT_SIGPENDING, scale: 1 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'l', description: "max locked memory", unit: Some("kbytes"), resource: Resource::RLIMIT_MEMLOCK, scale: 1024 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'm', description: "max memory size", unit: Some("kbytes"), resource: Resource::RLIMIT_RSS, scale: 1024 },
+    Kind { option: 'n', description: "open files", unit: None, resource: Resource::RLIMIT_NOFILE, scale: 1 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'q', description: "POSIX message queues", unit: Some("bytes"), resource: Resource::RLIMIT_MSGQUEUE, scale: 1 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'r', description: "real-time priority", unit: None, resource: Resource::RLIMIT_RTPRIO, scale: 1 },
+    Kind { option: 's', description: "stack size", unit: Some("kbytes"), resource: Resource::RLIMIT_STACK, scale: 1024 },
+    Kind { option: 't', description: "cpu time", unit: Some("seconds"), resource: Resource::RLIMIT_CPU, scale: 1 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'u', description: "max user processes", unit: None, resource: Resource::RLIMIT_NPROC, scale: 1 },
+    #[cfg(not(any(target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+    Kind { option: 'v', description: "virtual memory", unit: Some("kbytes"), resource: Resource::RLIMIT_AS, scale: 1024 },
+    #[cfg(any(target_os = "android", target_os = "linux"))]
+    Kind { option: 'x', description: "file locks", unit: None, resource: Resource::RLIMIT_LOCKS, scale: 1 },
+];
+
+pub(crate) struct Ulimit;
+
+impl Builtin for Ulimit {
+    fn name(&self) -> &str {
+        "ulimit"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (mut soft, mut hard, mut all) = (false, false, false);
+        let mut kinds = Vec::new();
+        let mut args = args;
+        while let Some((arg, rest)) = args.split_first() {
+            if arg == "--" {
+                args = rest;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            for c in arg[1..].chars() {
+                match c {
+                    'S' => soft = true,
+                    'H' => hard = true,
+                    'a' => all = true,
+                    c => match KINDS.iter().find(|kind| kind.option == c) {
+                        Some(kind) => kinds.push(kind),
+                        None => {
+                            io.error("ulimit", format!("-{}: invalid option", c));
+                            let _ = writeln!(io.stderr, "{}", USAGE);
+                            return 2;
+                        },
+                    },
+                }
+            }
+            args = rest;
+        }
+        let value = match args {
+            [] => None,
+            [value] => Some(value.as_str()),
+            _ => {
+                io.error("ulimit", "too many arguments");
+                return 2;
+            },
+        };
+        if all {
+            kinds = KINDS.iter().collect();
+        } else if kinds.is_empty() {
+            kinds.extend(KINDS.iter().find(|kind| kind.option == 'f'));
+        }
+        // Without `-S` or `-H`, both limits are set and the soft one shown.
+        let (soft, hard) = if soft || hard { (soft, hard) } else { (true, value.is_some()) };
+
+        let value = match value {
+            Some(value) if !all => value,
+            _ => {
+                for kind in &kinds {
+                    let limit = shell.executor.limit(kind.resource);
+                    let shown = if soft { limit.soft } else { limit.hard };
+                    let shown = shown.map_or_else(|| "unlimited".to_string(), |limit| (limit / kind.scale).to_string());
+                    let _ = if kinds.len() > 1 {
+                        let unit = match kind.unit {
+                            Some(unit) => format!("({}, -{}) ", unit, kind.option),
+                            None => format!("(-{}) ", kind.option),
+                        };
+                        writeln!(io.stdout, "{:<20} {:>20}{}", kind.description, unit, shown)
+                    } else {
+                        writeln!(io.stdout, "{}", shown)
+                    };
+                }
+                return 0;
+            },
+        };
+
+        let mut status = 0;
+        for kind in kinds {
+            let mut limit = shell.executor.limit(kind.resource);
+            let new = match value {
+                "unlimited" => None,
+                "soft" => limit.soft,
+                "hard" => limit.hard,
+                number => match number.parse::<u64>().ok().and_then(|number| number.checked_mul(kind.scale)) {
+                    Some(limit) => Some(limit),
+                    None => {
+                        io.error("ulimit", format!("{}: invalid number", number));
+                        return 1;
+                    },
+                },
+            };
+            if soft {
+                limit.soft = new;
+            }
+            if hard {
+                limit.hard = new;
+            }
+            if let Err(e) = shell.executor.set_limit(kind.resource, limit) {
+                io.error("ulimit", format!("{}: {}", kind.description, e));
+                status = 1;
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn shows_and_sets_limits() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let captured = shell.capture("ulimit -n 256; ulimit -n; ulimit -c 100; ulimit -Sc 50; ulimit -c; ulimit -Hc; ulimit -n -c").unwrap();
+        assert_eq!(
+            captured.stdout_lossy(),
+            "256\n50\n100\n\
+             open files                          (-n) 256\n\
+             core file size              (blocks, -c) 50\n"
+        );
+
+        let captured = shell.capture("ulimit -c 200; ulimit -c hard; ulimit -c; ulimit -n lots").unwrap();
+        assert_eq!(captured.stdout_lossy(), "100\n");
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: ulimit: core file size: cannot modify limit: Operation not permitted\n\
+             crussh: ulimit: lots: invalid number\n"
+        );
+        assert_eq!(shell.capture("ulimit -j").unwrap().status, 2);
+    }
+}
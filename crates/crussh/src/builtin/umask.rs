@@ -0,0 +1,156 @@
+//! `umask [-pS] [mode]`.
+
+use std::io::Write;
+
+use crate::Shell;
+use crate::builtin::{Builtin, Io};
+
+pub(crate) struct Umask;
+
+impl Builtin for Umask {
+    fn name(&self) -> &str {
+        "umask"
+    }
+
+    fn run(&self, shell: &mut Shell, args: &[String], io: &mut Io) -> i32 {
+        let (mut reusable, mut symbolic) = (false, false);
+        let mut args = args;
+        while let Some((arg, rest)) = args.split_first() {
+            if arg == "--" {
+                args = rest;
+                break;
+            }
+            if !arg.starts_with('-') || arg.len() == 1 {
+                break;
+            }
+            for c in arg[1..].chars() {
+                match c {
+                    'p' => reusable = true,
+                    'S' => symbolic = true,
+                    _ => {
+                        io.error("umask", format!("-{}: invalid option", c));
+                        let _ = writeln!(io.stderr, "umask: usage: umask [-p] [-S] [mode]");
+                        return 2;
+                    },
+                }
+            }
+            args = rest;
+        }
+
+        if let Some(mode) = args.first() {
+            let mask = if mode.starts_with(|c: char| c.is_ascii_digit()) {
+                match u32::from_str_radix(mode, 8) {
+                    Ok(mask) if mask <= 0o777 => mask,
+                    _ => {
+                        io.error("umask", format!("{}: octal number out of range", mode));
+                        return 1;
+                    },
+                }
+            } else {
+                match apply(mode, shell.executor.umask()) {
+                    Ok(mask) => mask,
+                    Err(e) => {
+                        io.error("umask", e);
+                        return 1;
+                    },
+                }
+            };
+            shell.executor.set_umask(mask);
+            // With a mode, only `-S` prints, and the new mask.
+            if !symbolic {
+                return 0;
+            }
+        }
+
+        let mask = shell.executor.umask();
+        let shown = if symbolic { describe(mask) } else { format!("{:04o}", mask) };
+        let _ = match (reusable, symbolic) {
+            (true, true) => writeln!(io.stdout, "umask -S {}", shown),
+            (true, false) => writeln!(io.stdout, "umask {}", shown),
+            (false, _) => writeln!(io.stdout, "{}", shown),
+        };
+        0
+    }
+}
+
+/// Applies a symbolic mode such as `u=rwx,g=rx,o=` or `go-w` to `mask`.
+/// The mode names the permissions files are created with, which the mask
+/// takes away.
+fn apply(mode: &str, mask: u32) -> Result<u32, String> {
+    let mut permissions = !mask & 0o777;
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who = 0;
+        while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+            who |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                _ => 0o777,
+            };
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+        if chars.peek().is_none() {
+            return Err(format!("`{}': missing symbolic mode operator", clause));
+        }
+        while let Some(op) = chars.next() {
+            if !"+-=".contains(op) {
+                return Err(format!("`{}': invalid symbolic mode operator", op));
+            }
+            let mut bits = 0;
+            while let Some(c) = chars.next_if(|c| !"+-=".contains(*c)) {
+                bits |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    c => return Err(format!("`{}': invalid symbolic mode character", c)),
+                };
+            }
+            let bits = bits & who;
+            match op {
+                '+' => permissions |= bits,
+                '-' => permissions &= !bits,
+                _ => permissions = permissions & !who | bits,
+            }
+        }
+    }
+    Ok(!permissions & 0o777)
+}
+
+/// `mask` the way `umask -S` shows it, as the permissions it leaves.
+fn describe(mask: u32) -> String {
+    let clauses: Vec<String> = [('u', 6), ('g', 3), ('o', 0)]
+        .iter()
+        .map(|&(who, shift)| {
+            let permissions = !mask >> shift;
+            let letters = [(0o4, 'r'), (0o2, 'w'), (0o1, 'x')].iter().filter(|(bit, _)| permissions & bit != 0).map(|(_, letter)| letter);
+            format!("{}={}", who, letters.collect::<String>())
+        })
+        .collect();
+    clauses.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn sets_octal_and_symbolic_masks() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let captured = shell.capture("umask; umask 027; umask -S; umask g+w,o=r; umask -p; umask -S u=rwx,g=rx,o=; umask a-x; umask").unwrap();
+        assert_eq!(captured.stdout_lossy(), "0022\nu=rwx,g=rx,o=\numask 0003\nu=rwx,g=rx,o=\n0137\n");
+
+        let captured = shell.capture("umask 1000; umask u~r; umask u=rz; umask g").unwrap();
+        assert_eq!(
+            captured.stderr_lossy(),
+            "crussh: umask: 1000: octal number out of range\n\
+             crussh: umask: `~': invalid symbolic mode operator\n\
+             crussh: umask: `z': invalid symbolic mode character\n\
+             crussh: umask: `g': missing symbolic mode operator\n"
+        );
+        assert_eq!(captured.status, 1);
+    }
+}
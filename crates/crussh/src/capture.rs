@@ -0,0 +1,167 @@
+//! Running a command line with its output collected instead of shown.
+
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use crate::ast::List;
+use crate::exec::FdPlan;
+use crate::{Error, ExecError, Shell};
+
+/// What a command line run by [`Shell::capture`] wrote, and how it ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Captured {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// The exit status, or 124 if the command line timed out.
+    pub status: i32,
+    /// Whether the command line was killed for running past its timeout.
+    pub timed_out: bool,
+}
+
+impl Captured {
+    /// Standard output as text, with invalid UTF-8 replaced.
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    /// Standard error as text, with invalid UTF-8 replaced.
+    pub fn stderr_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+}
+
+/// The status of a command line that was killed for taking too long, as
+/// `timeout(1)` reports it.
+pub(crate) const TIMED_OUT: i32 = 124;
+
+impl Shell {
+
+    /// Runs a command line with empty standard input and returns what it
+    /// wrote to standard output and standard error.
+    ///
+    /// The command line runs in a child of the shell, like a command
+    /// substitution, so variables it sets and directories it changes to
+    /// are not kept. Errors that keep a command from running end up in
+    /// [`Captured::stderr`]; only a parse error, or a failure to set up the
+    /// pipes, is returned as an error.
+    ///
+    /// ```no_run
+    /// let mut shell = crussh::Shell::new();
+    /// let captured = shell.capture("git status --porcelain | wc -l").unwrap();
+    /// println!("{} changed files", captured.stdout_lossy().trim());
+    /// ```
+    pub fn capture(&mut self, input: &str) -> Result<Captured, Error> {
+        self.capture_with(input, &[], None)
+    }
+
+    /// Runs a command line as [`capture`](Shell::capture) does, with
+    /// `stdin` as its standard input, killing it if it is still running
+    /// after `timeout`.
+    pub fn capture_with(&mut self, input: &str, stdin: &[u8], timeout: Option<Duration>) -> Result<Captured, Error> {
+        let parse = self.parse(input).map_err(|e| {
+            self.last_status = 2;
+            Error::Parse(e)
+        })?;
+
+        let captured = self.capture_list(parse.ast(), stdin, timeout);
+        self.last_status = match &captured {
+            Ok(captured) => captured.status,
+            Err(e) => e.status(),
+        };
+        captured.map_err(Error::Exec)
+    }
+
+    fn capture_list(&mut self, list: &List, stdin: &[u8], timeout: Option<Duration>) -> Result<Captured, ExecError> {
+        let mut fds = [0; 3];
+        let mut plan = FdPlan::default();
+        for target in 0..fds.len() {
+            let (read, write) = match self.executor.pipe() {
+                Ok(pipe) => pipe,
+                Err(e) => {
+                    plan.release(&mut *self.executor);
+                    fds[..target].iter().for_each(|&fd| self.executor.close(fd));
+                    return Err(e);
+                }
+            };
+            // The child reads standard input and writes the other two.
+            let (theirs, ours) = if target == 0 { (read, write) } else { (write, read) };
+            plan.redirect_owned(target as RawFd, theirs);
+            plan.exclude(ours);
+            fds[target] = ours;
+        }
+
+        let forked = self.fork(&plan, |shell| {
+            shell.executor.set_process_group(0);
+            shell.exec_list(list)
+        });
+        plan.release(&mut *self.executor);
+
+        let pid = match forked {
+            Ok(pid) => pid,
+            Err(e) => {
+                fds.iter().for_each(|&fd| self.executor.close(fd));
+                return Err(e);
+            }
+        };
+        self.executor.set_process_group(pid);
+        self.executor.communicate(pid, fds, stdin, timeout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::Shell;
+    use crate::exec::{Fd, Recorder};
+
+    #[test]
+    fn collects_output_and_status() {
+        let recorder = Recorder::new().with_output("ls", b"a\nb\n").with_status("wc", 3);
+        let mut shell = Shell::with_executor(recorder.clone());
+
+        let captured = shell.capture("ls; wc").unwrap();
+        assert_eq!(captured.stdout, b"a\nb\n");
+        assert_eq!(captured.status, 3);
+        assert_eq!(shell.last_status(), 3);
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[1].fds[&0], Fd::PipeRead(0));
+        assert_eq!(spawned[1].fds[&1], Fd::PipeWrite(1));
+        assert_eq!(spawned[1].fds[&2], Fd::PipeWrite(2));
+    }
+
+    #[test]
+    fn pipes_through_pipelines() {
+        let recorder = Recorder::new().with_output("ls", b"a\n").with_output("wc", b"1\n");
+        let mut shell = Shell::with_executor(recorder);
+        assert_eq!(shell.capture("ls | wc").unwrap().stdout, b"1\n");
+    }
+
+    #[test]
+    fn runs_programs() {
+        let mut shell = Shell::new();
+        let captured = shell.capture_with("tr a-z A-Z; echo oops >&2; sh -c 'exit 4'", b"hello\n", None).unwrap();
+        assert_eq!(captured.stdout_lossy(), "HELLO\n");
+        assert_eq!(captured.stderr_lossy(), "oops\n");
+        assert_eq!(captured.status, 4);
+        assert!(!captured.timed_out);
+    }
+
+    #[test]
+    fn streams_more_than_a_pipe_holds() {
+        let input = "line\n".repeat(100_000);
+        let mut shell = Shell::new();
+        let captured = shell.capture_with("cat; cat /dev/null", input.as_bytes(), None).unwrap();
+        assert_eq!(captured.stdout, input.as_bytes());
+    }
+
+    #[test]
+    fn kills_on_timeout() {
+        let mut shell = Shell::new();
+        let captured = shell.capture_with("echo started; sleep 5 | cat", &[], Some(Duration::from_millis(200))).unwrap();
+        assert_eq!(captured.stdout_lossy(), "started\n");
+        assert_eq!(captured.status, 124);
+        assert!(captured.timed_out);
+    }
+}
@@ -0,0 +1,216 @@
+use std::{error, fmt};
+
+use nix::errno::Errno;
+
+/// Reasons a command line can fail to parse.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// A specific token was required but something else was found.
+    Expected(String),
+    /// A token appeared where the grammar does not allow it.
+    Unexpected(String),
+    /// The input ended in the middle of a construct, e.g. an open quote or
+    /// an `if` without `fi`.
+    Incomplete(String),
+}
+
+impl ParseError {
+    /// Whether more input could complete the command, as with a
+    /// continuation line.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, Self::Incomplete(_))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expected(what) => write!(f, "syntax error: expected {}", what),
+            Self::Unexpected(token) => write!(f, "syntax error near unexpected token `{}'", token),
+            Self::Incomplete(what) => write!(f, "syntax error: unexpected end of input while looking for {}", what),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Reasons a parsed command can fail to run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecError {
+    /// The program was not found on `PATH`.
+    CommandNotFound(String),
+    /// A path given with a `/` does not exist.
+    NoSuchFile(String),
+    /// The program exists but is not executable.
+    PermissionDenied(String),
+    /// The program is a directory.
+    IsADirectory(String),
+    /// A redirection target could not be opened.
+    Redirection { path: String, errno: Errno },
+    /// A `<&` or `>&` target is not a file descriptor or `-`.
+    BadRedirect(String),
+    /// A file descriptor named by `<&` or `>&` could not be duplicated.
+    BadFd { fd: i32, errno: Errno },
+    /// `>` would overwrite an existing file under `set -C`.
+    Clobber(String),
+    /// A parameter expanded under `set -u` is unset.
+    Unbound(String),
+    /// Creating a pipe between two commands failed.
+    Pipe(Errno),
+    /// `fork(2)` failed.
+    Fork(Errno),
+    /// The working directory could not be entered to start a program.
+    WorkingDirectory { path: String, errno: Errno },
+    /// Waiting for a child failed.
+    Wait(Errno),
+    /// A resource limit could not be changed.
+    Limit(Errno),
+    /// A signal could not be sent to a process.
+    Signal { pid: u32, errno: Errno },
+}
+
+impl ExecError {
+    /// The exit status a shell reports for this failure.
+    pub fn status(&self) -> i32 {
+        match self {
+            Self::CommandNotFound(_) | Self::NoSuchFile(_) => 127,
+            Self::PermissionDenied(_) | Self::IsADirectory(_) => 126,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandNotFound(name) => write!(f, "{}: command not found", name),
+            Self::NoSuchFile(name) => write!(f, "{}: No such file or directory", name),
+            Self::PermissionDenied(name) => write!(f, "{}: Permission denied", name),
+            Self::IsADirectory(name) => write!(f, "{}: Is a directory", name),
+            Self::Redirection { path, errno } => write!(f, "{}: {}", path, errno.desc()),
+            Self::BadRedirect(word) => write!(f, "{}: ambiguous redirect", word),
+            Self::BadFd { fd, errno } => write!(f, "{}: {}", fd, errno.desc()),
+            Self::Clobber(path) => write!(f, "{}: cannot overwrite existing file", path),
+            Self::Unbound(name) => write!(f, "{}: unbound variable", name),
+            Self::Pipe(errno) => write!(f, "pipe error: {}", errno.desc()),
+            Self::Fork(errno) => write!(f, "fork: {}", errno.desc()),
+            Self::WorkingDirectory { path, errno } => write!(f, "cannot change directory to {}: {}", path, errno.desc()),
+            Self::Wait(errno) => write!(f, "wait: {}", errno.desc()),
+            Self::Limit(errno) => write!(f, "cannot modify limit: {}", errno.desc()),
+            Self::Signal { pid, errno } => write!(f, "({}) - {}", pid, errno.desc()),
+        }
+    }
+}
+
+impl error::Error for ExecError {}
+
+/// Reasons `!` history expansion can fail.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryError {
+    /// No entry matches an event such as `!foo`.
+    EventNotFound(String),
+    /// A word designator such as `:5` names words the entry lacks.
+    BadWordSpecifier(String),
+    UnknownModifier(char),
+    /// `^old^new` or `:s/old/new/` found no `old`.
+    SubstitutionFailed,
+}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EventNotFound(event) => write!(f, "{}: event not found", event),
+            Self::BadWordSpecifier(spec) => write!(f, "{}: bad word specifier", spec),
+            Self::UnknownModifier(modifier) => write!(f, ":{}: unrecognized history modifier", modifier),
+            Self::SubstitutionFailed => write!(f, "substitution failed"),
+        }
+    }
+}
+
+impl error::Error for HistoryError {}
+
+/// Why running a command line through a [`Shell`](crate::Shell) failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Parse(ParseError),
+    Exec(ExecError),
+    /// An error in a script, at a line counted from 1.
+    Script { path: String, line: usize, error: Box<Error> },
+}
+
+impl Error {
+    /// The exit status a shell reports for this failure: 2 for syntax
+    /// errors, as in bash.
+    pub fn status(&self) -> i32 {
+        match self {
+            Self::Parse(_) => 2,
+            Self::Exec(e) => e.status(),
+            Self::Script { error, .. } => error.status(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse(e) => e.fmt(f),
+            Self::Exec(e) => e.fmt(f),
+            Self::Script { path, line, error } => write!(f, "{}: line {}: {}", path, line, error),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+impl From<ExecError> for Error {
+    fn from(e: ExecError) -> Self {
+        Self::Exec(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::errno::Errno;
+
+    use super::{Error, ExecError, ParseError};
+
+    #[test]
+    fn maps_failures_to_statuses_and_messages() {
+        let cases = [
+            (ExecError::CommandNotFound("nope".to_string()), 127, "nope: command not found".to_string()),
+            (ExecError::NoSuchFile("./nope".to_string()), 127, "./nope: No such file or directory".to_string()),
+            (ExecError::PermissionDenied("./x".to_string()), 126, "./x: Permission denied".to_string()),
+            (ExecError::IsADirectory("/tmp".to_string()), 126, "/tmp: Is a directory".to_string()),
+            (ExecError::Redirection { path: "out".to_string(), errno: Errno::EACCES }, 1, format!("out: {}", Errno::EACCES.desc())),
+            (ExecError::BadRedirect("x".to_string()), 1, "x: ambiguous redirect".to_string()),
+            (ExecError::BadFd { fd: 5, errno: Errno::EBADF }, 1, format!("5: {}", Errno::EBADF.desc())),
+            (ExecError::Clobber("out".to_string()), 1, "out: cannot overwrite existing file".to_string()),
+            (ExecError::Unbound("x".to_string()), 1, "x: unbound variable".to_string()),
+            (ExecError::Pipe(Errno::EMFILE), 1, format!("pipe error: {}", Errno::EMFILE.desc())),
+            (ExecError::Fork(Errno::EAGAIN), 1, format!("fork: {}", Errno::EAGAIN.desc())),
+            (
+                ExecError::WorkingDirectory { path: "/gone".to_string(), errno: Errno::ENOENT },
+                1,
+                format!("cannot change directory to /gone: {}", Errno::ENOENT.desc()),
+            ),
+            (ExecError::Wait(Errno::ECHILD), 1, format!("wait: {}", Errno::ECHILD.desc())),
+            (ExecError::Limit(Errno::EPERM), 1, format!("cannot modify limit: {}", Errno::EPERM.desc())),
+            (ExecError::Signal { pid: 42, errno: Errno::ESRCH }, 1, format!("(42) - {}", Errno::ESRCH.desc())),
+        ];
+        for (error, status, message) in cases {
+            assert_eq!((error.status(), error.to_string()), (status, message));
+        }
+
+        let parse = Error::Parse(ParseError::Unexpected(")".to_string()));
+        assert_eq!(parse.status(), 2);
+        let script = Error::Script { path: "x.sh".to_string(), line: 3, error: Box::new(Error::Exec(ExecError::CommandNotFound("nope".to_string()))) };
+        assert_eq!(script.status(), 127);
+        assert_eq!(script.to_string(), "x.sh: line 3: nope: command not found");
+    }
+}
@@ -0,0 +1,799 @@
+//! Runs a parse tree.
+
+pub(crate) mod arith;
+mod expand;
+mod glob;
+mod pattern;
+
+use std::os::unix::io::RawFd;
+use std::path::Path;
+
+use crate::{ExecError, utils};
+use crate::ast::{
+    AndOr, Command, CompoundCommand, Connector, FunctionDef, List, Pipeline, Redirect, RedirectOp,
+    SimpleCommand,
+};
+use crate::exec::{FdPlan, Forked, Stdio};
+use crate::shell::{Shell, Variable};
+
+impl Shell {
+
+    /// Records an error as the cause of the current status.
+    fn fail(&mut self, e: ExecError) -> i32 {
+        self.flush_error();
+        let status = e.status();
+        // An unset parameter under `set -u` ends the command line of an
+        // interactive shell, and any other shell.
+        if let ExecError::Unbound(_) = e {
+            if self.options.interactive {
+                self.aborted = true;
+            } else {
+                self.exit_status = Some(status);
+            }
+        }
+        self.pending_error = Some(e);
+        status
+    }
+
+    fn flush_error(&mut self) {
+        if let Some(e) = self.pending_error.take() {
+            eprintln!("crussh: {}", e);
+        }
+    }
+
+    pub(crate) fn exec_list(&mut self, list: &List) -> i32 {
+        for item in &list.items {
+            if self.stopped() || (self.options.noexec && !self.options.interactive) {
+                break;
+            }
+            self.last_status = if item.background {
+                self.spawn_background(&item.and_or)
+            } else {
+                self.exec_and_or(&item.and_or)
+            };
+            self.run_pending_traps();
+        }
+        self.last_status
+    }
+
+    fn exec_and_or(&mut self, and_or: &AndOr) -> i32 {
+        let mut status = self.exec_checked(&and_or.first, and_or.rest.is_empty());
+
+        for (i, (connector, pipeline)) in and_or.rest.iter().enumerate() {
+            if self.stopped() {
+                break;
+            }
+            let run = match connector {
+                Connector::And => status == 0,
+                Connector::Or => status != 0,
+            };
+            if run {
+                status = self.exec_checked(pipeline, i + 1 == and_or.rest.len());
+            }
+        }
+
+        status
+    }
+
+    /// Runs a pipeline of an and-or list. Only a failure of the `last` one,
+    /// when it is not negated, counts as the command failing; the others
+    /// are tested like the condition of an `if`.
+    fn exec_checked(&mut self, pipeline: &Pipeline, last: bool) -> i32 {
+        if !last || pipeline.negated {
+            return self.exec_condition(|ctx| ctx.exec_pipeline(pipeline));
+        }
+        let status = self.exec_pipeline(pipeline);
+        if status != 0 && reports_failure(pipeline) {
+            self.command_failed();
+        }
+        status
+    }
+
+    /// Runs a command whose status is tested.
+    fn exec_condition(&mut self, f: impl FnOnce(&mut Self) -> i32) -> i32 {
+        self.conditions += 1;
+        let status = f(self);
+        self.conditions -= 1;
+        status
+    }
+
+    fn exec_pipeline(&mut self, pipeline: &Pipeline) -> i32 {
+        self.flush_error();
+
+        let run = |ctx: &mut Self| match pipeline.commands.as_slice() {
+            [command] => ctx.exec_command(command),
+            commands => ctx.exec_piped(commands),
+        };
+        let status = match pipeline.time {
+            Some(timing) if pipeline.commands.is_empty() => self.time_shell(timing),
+            Some(timing) => self.timed(timing, run),
+            None => run(self),
+        };
+        let status = if pipeline.negated { (status == 0) as i32 } else { status };
+
+        self.last_status = status;
+        status
+    }
+
+    /// Runs every stage of a pipeline in its own process and waits for all
+    /// of them; the status is that of the last stage.
+    fn exec_piped(&mut self, commands: &[Command]) -> i32 {
+        let mut pids = Vec::new();
+        let mut status = 0;
+        let mut stdin = None;
+
+        for (i, command) in commands.iter().enumerate() {
+            self.flush_error();
+            let last = i + 1 == commands.len();
+
+            let (next_stdin, stdout) = if last {
+                (None, None)
+            } else {
+                match self.executor.pipe() {
+                    Ok((read, write)) => (Some(read), Some(write)),
+                    Err(e) => {
+                        self.close_fd(stdin);
+                        status = self.fail(e);
+                        break;
+                    }
+                }
+            };
+
+            let stdio = Stdio { stdin, stdout, unused: next_stdin };
+            // A simple command's words are expanded once, here, however
+            // it goes on to run.
+            let expanded = match command {
+                Command::Simple(simple) => {
+                    self.before_command();
+                    self.expand_words(&simple.words).map(|argv| Some((simple, argv)))
+                },
+                _ => Ok(None),
+            };
+            let spawned = match expanded {
+                Ok(Some((simple, argv))) if last && self.is_builtin(&argv) => {
+                    // Run in the shell, like a builtin on its own, so that
+                    // `... | read x` sets `x`.
+                    status = self.with_stdio(&stdio, |ctx| ctx.run_simple(simple, argv));
+                    self.close_fd(stdin);
+                    break;
+                },
+                Ok(Some((simple, argv))) => self.spawn_stage(simple, argv, &stdio),
+                Ok(None) => self.fork(&FdPlan::new(&stdio), |ctx| ctx.exec_command(command)),
+                Err(e) => Err(e),
+            };
+            self.close_fd(stdin);
+            self.close_fd(stdout);
+            stdin = next_stdin;
+
+            match spawned {
+                Ok(pid) => pids.push((pid, last)),
+                Err(e) => status = self.fail(e),
+            }
+        }
+
+        for (pid, last) in pids {
+            match self.wait_child(pid) {
+                Ok(code) if last => status = code,
+                Ok(_) => {},
+                Err(e) => status = self.fail(e),
+            }
+        }
+
+        status
+    }
+
+    /// Runs a command in the shell process and returns its status.
+    fn exec_command(&mut self, command: &Command) -> i32 {
+        match command {
+            Command::Simple(simple) => self.exec_simple(simple),
+            Command::Compound { command, redirects } => {
+                self.with_redirects(redirects, |ctx| ctx.exec_compound(command))
+            },
+            Command::FunctionDef(function) => {
+                self.functions.insert(function.name.clone(), function.clone());
+                0
+            }
+        }
+    }
+
+    /// Starts a simple command whose words have been expanded to `argv`
+    /// with the given pipe ends in a child process: a program directly, and
+    /// a builtin or function in a copy of the shell.
+    fn spawn_stage(&mut self, simple: &SimpleCommand, argv: Vec<String>, stdio: &Stdio) -> Result<u32, ExecError> {
+        let (stripped, skip_functions) = self.strip_command(&argv);
+        if !stripped.is_empty()
+            && (skip_functions || !self.functions.contains_key(&stripped[0]))
+            && !self.builtins.contains(&stripped[0])
+        {
+            self.trace(simple, stripped);
+            return self.spawn_simple(simple, stripped, stdio);
+        }
+        self.fork(&FdPlan::new(stdio), |ctx| ctx.run_simple(simple, argv))
+    }
+
+    fn exec_simple(&mut self, simple: &SimpleCommand) -> i32 {
+        self.before_command();
+        match self.expand_words(&simple.words) {
+            Ok(argv) => self.run_simple(simple, argv),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Runs a simple command whose words have been expanded to `argv`.
+    fn run_simple(&mut self, simple: &SimpleCommand, argv: Vec<String>) -> i32 {
+        self.trace(simple, &argv);
+
+        if argv.is_empty() {
+            for assignment in &simple.assignments {
+                match self.expand_word(&assignment.value) {
+                    Ok(value) => self.set_var(&assignment.name, value),
+                    Err(e) => return self.fail(e),
+                }
+            }
+            return self.with_redirects(&simple.redirects, |_| 0);
+        }
+
+        let (argv, skip_functions) = self.strip_command(&argv);
+        let argv = argv.to_vec();
+
+        if let Some(function) = self.functions.get(&argv[0]).filter(|_| !skip_functions).cloned() {
+            return self.with_redirects(&simple.redirects, |ctx| {
+                ctx.call_function(&function, simple, &argv[1..])
+            });
+        }
+
+        if let Some(builtin) = self.builtins.get(&argv[0]) {
+            return self.with_redirects(&simple.redirects, |ctx| {
+                ctx.with_assignments(simple, |ctx| {
+                    let mut io = ctx.executor.stdio();
+                    let status = builtin.run(ctx, &argv[1..], &mut io);
+                    let _ = io.stdout.flush();
+                    status
+                })
+            });
+        }
+
+        match self.spawn_simple(simple, &argv, &Stdio::default()) {
+            Ok(pid) => self.wait_child(pid).unwrap_or_else(|e| self.fail(e)),
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Prints a command that is about to run, after `$PS4`, under `set -x`.
+    /// Its assignments come first, one per line.
+    fn trace(&mut self, simple: &SimpleCommand, argv: &[String]) {
+        if !self.options.xtrace {
+            return;
+        }
+        let ps4 = self.var("PS4").unwrap_or("+ ").to_string();
+        let mut trace = String::new();
+        for assignment in &simple.assignments {
+            // An error here is reported when the value is expanded for use.
+            let value = self.expand_word(&assignment.value).unwrap_or_default();
+            trace.push_str(&format!("{}{}={}\n", ps4, assignment.name, utils::quote(&value)));
+        }
+        if !argv.is_empty() {
+            let words: Vec<String> = argv.iter().map(|arg| utils::quote(arg)).collect();
+            trace.push_str(&format!("{}{}\n", ps4, words.join(" ")));
+        }
+        let mut io = self.executor.stdio();
+        let _ = io.stderr.write_all(trace.as_bytes());
+        let _ = io.stderr.flush();
+    }
+
+    /// Forks and executes the program named by `argv[0]`.
+    fn spawn_simple(&mut self, simple: &SimpleCommand, argv: &[String], stdio: &Stdio) -> Result<u32, ExecError> {
+        let path = self.find_program(&argv[0])?;
+
+        // The assignments come first, so that nothing is open yet if one
+        // fails.
+        let assignments = simple.assignments.iter()
+            .map(|a| Ok((a.name.clone(), self.expand_word(&a.value)?)))
+            .collect::<Result<Vec<_>, ExecError>>()?;
+        let env = self.child_env(&assignments);
+
+        let mut plan = FdPlan::new(stdio);
+        self.plan_redirects(&simple.redirects, &mut plan)?;
+
+        let pid = self.executor.spawn(&path, argv, &env, &self.cwd, &plan);
+        plan.release(&mut *self.executor);
+        pid
+    }
+
+    fn call_function(&mut self, function: &FunctionDef, simple: &SimpleCommand, args: &[String]) -> i32 {
+        self.with_assignments(simple, |ctx| {
+            let saved_positional = std::mem::replace(&mut ctx.positional, args.to_vec());
+            ctx.function_depth += 1;
+            let status = ctx.with_redirects(&function.redirects, |ctx| ctx.exec_compound(&function.body));
+            ctx.function_depth -= 1;
+            ctx.positional = saved_positional;
+            status
+        })
+    }
+
+    /// Runs `f` with the assignments of `simple` exported, then puts the
+    /// variables back as they were.
+    fn with_assignments(&mut self, simple: &SimpleCommand, f: impl FnOnce(&mut Self) -> i32) -> i32 {
+        let values = simple.assignments.iter()
+            .map(|a| self.expand_word(&a.value))
+            .collect::<Result<Vec<_>, ExecError>>();
+        let values = match values {
+            Ok(values) => values,
+            Err(e) => return self.fail(e),
+        };
+        let saved_vars: Vec<(String, Option<Variable>)> = simple.assignments.iter().zip(values)
+            .map(|(a, value)| {
+                (a.name.clone(), self.vars.insert(a.name.clone(), Variable { value, exported: true }))
+            })
+            .collect();
+
+        let status = f(self);
+
+        for (name, value) in saved_vars {
+            match value {
+                Some(value) => self.vars.insert(name, value),
+                None => self.vars.remove(&name),
+            };
+        }
+        status
+    }
+
+    /// Takes `command` off the front of `command name args...`, returning
+    /// the rest and whether it did; `name` is then looked up without
+    /// functions. `command -v` and `-V` are left to the builtin.
+    fn strip_command<'a>(&self, argv: &'a [String]) -> (&'a [String], bool) {
+        let mut rest = argv;
+        let mut stripped = false;
+        while rest.first().is_some_and(|name| name == "command")
+            && !self.functions.contains_key("command")
+            && self.builtins.contains("command")
+        {
+            let args = match rest.get(1) {
+                Some(arg) if arg == "--" => &rest[2..],
+                _ => &rest[1..],
+            };
+            match args.first() {
+                Some(name) if !name.starts_with('-') => {
+                    rest = args;
+                    stripped = true;
+                },
+                _ => break,
+            }
+        }
+        (rest, stripped)
+    }
+
+    /// Whether the expanded words `argv` name a builtin.
+    fn is_builtin(&self, argv: &[String]) -> bool {
+        let (argv, skip_functions) = self.strip_command(argv);
+        argv.first().is_some_and(|name| {
+            (skip_functions || !self.functions.contains_key(name)) && self.builtins.contains(name)
+        })
+    }
+
+    fn exec_compound(&mut self, command: &CompoundCommand) -> i32 {
+        match command {
+            CompoundCommand::BraceGroup(list) => self.exec_list(list),
+            CompoundCommand::Subshell(list) => {
+                match self.fork(&FdPlan::default(), |ctx| ctx.exec_list(list)) {
+                    Ok(pid) => self.wait_child(pid).unwrap_or_else(|e| self.fail(e)),
+                    Err(e) => self.fail(e),
+                }
+            },
+            CompoundCommand::If(clause) => {
+                for branch in &clause.branches {
+                    if self.exec_condition(|ctx| ctx.exec_list(&branch.condition)) == 0 {
+                        return self.exec_list(&branch.body);
+                    }
+                }
+                match &clause.else_body {
+                    Some(else_body) => self.exec_list(else_body),
+                    None => 0,
+                }
+            },
+            CompoundCommand::While(clause) | CompoundCommand::Until(clause) => {
+                let until = matches!(command, CompoundCommand::Until(_));
+                let mut status = 0;
+                while !self.stopped() && (self.exec_condition(|ctx| ctx.exec_list(&clause.condition)) == 0) != until {
+                    status = self.exec_list(&clause.body);
+                }
+                status
+            },
+            CompoundCommand::For(clause) => {
+                let values = match &clause.words {
+                    Some(words) => match self.expand_words(words) {
+                        Ok(values) => values,
+                        Err(e) => return self.fail(e),
+                    },
+                    None => self.positional.clone(),
+                };
+                let mut status = 0;
+                for value in values {
+                    if self.stopped() {
+                        break;
+                    }
+                    self.set_var(&clause.name, value);
+                    status = self.exec_list(&clause.body);
+                }
+                status
+            },
+            CompoundCommand::Case(clause) => {
+                let subject = match self.expand_word(&clause.word) {
+                    Ok(subject) => subject,
+                    Err(e) => return self.fail(e),
+                };
+                for item in &clause.items {
+                    for pattern in &item.patterns {
+                        match self.expand_pattern(pattern) {
+                            Ok(pattern) if pattern::matches(&pattern, &subject) => return self.exec_list(&item.body),
+                            Ok(_) => {},
+                            Err(e) => return self.fail(e),
+                        }
+                    }
+                }
+                0
+            }
+        }
+    }
+
+    /// Starts an and-or list without waiting for it.
+    fn spawn_background(&mut self, and_or: &AndOr) -> i32 {
+        self.flush_error();
+
+        let mut plan = FdPlan::default();
+        if let Some(log) = self.job_log(and_or) {
+            // Output stays where it was if the log cannot be opened.
+            match self.executor.open(&self.cwd, &log, RedirectOp::Output) {
+                Ok(fd) => {
+                    plan.redirect_owned(1, fd);
+                    plan.redirect(2, 1);
+                },
+                Err(e) => eprintln!("crussh: {}", e),
+            }
+        }
+
+        // Each job has a process group of its own, which `kill %n` signals
+        // as a whole.
+        let spawned = self.fork(&plan, |ctx| {
+            ctx.executor.set_process_group(0);
+            ctx.exec_and_or(and_or)
+        });
+        plan.release(&mut *self.executor);
+
+        match spawned {
+            Ok(pid) => {
+                self.executor.set_process_group(pid);
+                self.add_job(pid, and_or.to_string());
+                0
+            },
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// The file a background job writes to under `shopt -s joblog`:
+    /// `<job id>-<command>.log` in `$CRUSSH_JOBLOG_DIR`, after the name of
+    /// the job's first command. Without the directory there is none, which
+    /// the shell says.
+    fn job_log(&mut self, and_or: &AndOr) -> Option<String> {
+        if !self.options.joblog {
+            return None;
+        }
+        let dir = match self.var("CRUSSH_JOBLOG_DIR").filter(|dir| !dir.is_empty()) {
+            Some(dir) => dir.to_string(),
+            None => {
+                let mut io = self.executor.stdio();
+                let _ = writeln!(io.stderr, "crussh: joblog: CRUSSH_JOBLOG_DIR not set; job output is not logged");
+                let _ = io.stderr.flush();
+                return None;
+            },
+        };
+        let name = match and_or.first.commands.first() {
+            Some(Command::Simple(simple)) => simple.words.first().and_then(|word| self.expand_word(word).ok()),
+            _ => None,
+        };
+        let name = name.as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .map_or_else(|| "job".to_string(), |name| name.to_string_lossy().into_owned());
+        Some(format!("{}/{}-{}.log", dir.trim_end_matches('/'), self.next_job_id(), name))
+    }
+
+    /// Runs `f` in a child process with `plan` applied.
+    pub(crate) fn fork(&mut self, plan: &FdPlan, f: impl FnOnce(&mut Self) -> i32) -> Result<u32, ExecError> {
+        match self.executor.fork(plan)? {
+            Forked::Parent(pid) => Ok(pid),
+            Forked::Child => {
+                // Like a subshell in bash, the child keeps only the traps
+                // that ignore signals. They are put back for executors that
+                // run children in place.
+                let traps = self.traps.clone();
+                self.traps.retain(|_, action| action.is_empty());
+                let subshell = std::mem::replace(&mut self.subshell, true);
+                let status = f(self);
+                self.flush_error();
+                self.traps = traps;
+                self.subshell = subshell;
+                self.exit_status = None;
+                self.aborted = false;
+                Ok(self.executor.exit(status))
+            }
+        }
+    }
+
+    fn close_fd(&mut self, fd: Option<RawFd>) {
+        if let Some(fd) = fd {
+            self.executor.close(fd);
+        }
+    }
+
+    /// Runs `f` with the pipe ends in `stdio` as the shell's own standard
+    /// input and output.
+    fn with_stdio(&mut self, stdio: &Stdio, f: impl FnOnce(&mut Self) -> i32) -> i32 {
+        let saved = match self.executor.redirect(&FdPlan::new(stdio)) {
+            Ok(saved) => saved,
+            Err(e) => return self.fail(e),
+        };
+        let status = f(self);
+        self.executor.restore(saved);
+        status
+    }
+
+    /// Runs `f` with `redirects` applied to the shell itself.
+    fn with_redirects(&mut self, redirects: &[Redirect], f: impl FnOnce(&mut Self) -> i32) -> i32 {
+        if redirects.is_empty() {
+            let status = f(self);
+            self.keep_redirects = false;
+            return status;
+        }
+
+        let mut plan = FdPlan::default();
+        if let Err(e) = self.plan_redirects(redirects, &mut plan) {
+            return self.fail(e);
+        }
+        let saved = match self.executor.redirect(&plan) {
+            Ok(saved) => saved,
+            Err(e) => {
+                plan.release(&mut *self.executor);
+                return self.fail(e);
+            }
+        };
+
+        let status = f(self);
+        if std::mem::take(&mut self.keep_redirects) {
+            for copy in saved.0.into_iter().filter_map(|(_, copy)| copy) {
+                self.executor.close(copy);
+            }
+        } else {
+            self.executor.restore(saved);
+        }
+        plan.release(&mut *self.executor);
+        status
+    }
+
+    /// Opens the files named by `redirects` and adds them to `plan`.
+    fn plan_redirects(&mut self, redirects: &[Redirect], plan: &mut FdPlan) -> Result<(), ExecError> {
+        for redirect in redirects {
+            if let Err(e) = self.plan_redirect(redirect, plan) {
+                plan.release(&mut *self.executor);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn plan_redirect(&mut self, redirect: &Redirect, plan: &mut FdPlan) -> Result<(), ExecError> {
+        let target = self.expand_word(&redirect.target)?;
+        let fd = redirect.target_fd();
+
+        match redirect.op {
+            RedirectOp::DupInput | RedirectOp::DupOutput => {
+                if target == "-" {
+                    plan.close(fd);
+                } else {
+                    let source = target.parse().map_err(|_| ExecError::BadRedirect(target.clone()))?;
+                    plan.redirect(fd, source);
+                }
+            },
+            // `>|` is how to overwrite a file under `set -C`.
+            RedirectOp::Output if self.options.noclobber && self.cwd.join(&target).is_file() => {
+                return Err(ExecError::Clobber(target));
+            },
+            op => {
+                let opened = self.executor.open(&self.cwd, &target, op)?;
+                plan.redirect_owned(fd, opened);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether a failure of `pipeline` is its own rather than that of a
+/// command inside it, which has already been reported.
+fn reports_failure(pipeline: &Pipeline) -> bool {
+    match pipeline.commands.as_slice() {
+        [Command::Compound { command, .. }] => matches!(command, CompoundCommand::Subshell(_)),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::ast::RedirectOp;
+    use crate::exec::{Fd, Recorder};
+    use crate::{Error, ExecError, Shell};
+
+    fn run(recorder: &Recorder, s: &str) -> Result<i32, ExecError> {
+        crate::parse(s).unwrap().execute_with(recorder.clone())
+    }
+
+    fn file(path: &str, op: RedirectOp) -> Fd {
+        Fd::File { path: path.to_string(), op }
+    }
+
+    #[test]
+    fn spawns_with_argv_and_env() {
+        let recorder = Recorder::new();
+        assert_eq!(run(&recorder, "X=1 ls -l 'a b'"), Ok(0));
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned.len(), 1);
+        assert_eq!(spawned[0].argv, ["ls", "-l", "a b"]);
+        assert_eq!(spawned[0].var("X"), Some("1"));
+        assert!(spawned[0].fds.is_empty());
+    }
+
+    #[test]
+    fn connects_pipeline_stages() {
+        let recorder = Recorder::new().with_status("wc", 3);
+        assert_eq!(run(&recorder, "ls | grep x | wc"), Ok(3));
+
+        let fds: Vec<_> = recorder.spawned().into_iter().map(|s| s.fds).collect();
+        assert_eq!(fds[0].get(&1), Some(&Fd::PipeWrite(0)));
+        assert_eq!(fds[1].get(&0), Some(&Fd::PipeRead(0)));
+        assert_eq!(fds[1].get(&1), Some(&Fd::PipeWrite(1)));
+        assert_eq!(fds[2].get(&0), Some(&Fd::PipeRead(1)));
+        assert_eq!(fds[2].get(&1), None);
+    }
+
+    #[test]
+    fn applies_redirections_in_order() {
+        let recorder = Recorder::new();
+        run(&recorder, "cmd < in > out 2>&1 3>> log 4<&-").unwrap();
+
+        let fds = &recorder.spawned()[0].fds;
+        assert_eq!(fds[&0], file("in", RedirectOp::Input));
+        assert_eq!(fds[&1], file("out", RedirectOp::Output));
+        assert_eq!(fds[&2], file("out", RedirectOp::Output));
+        assert_eq!(fds[&3], file("log", RedirectOp::Append));
+        assert_eq!(fds[&4], Fd::Closed);
+
+        // Nothing is left open when an assignment fails.
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        let _ = shell.run("set -u; x=$nope cmd > out | cat");
+        assert!(recorder.spawned().iter().all(|spawned| spawned.argv != ["cmd"]));
+        assert_eq!(recorder.open_fds(), []);
+    }
+
+    #[test]
+    fn redirects_compound_commands() {
+        let recorder = Recorder::new();
+        run(&recorder, "{ a; b 2>&1; } > out | c; d").unwrap();
+
+        let spawned = recorder.spawned();
+        assert_eq!(recorder.argvs(), [["a"], ["b"], ["c"], ["d"]]);
+        assert_eq!(spawned[0].fds[&1], file("out", RedirectOp::Output));
+        assert_eq!(spawned[1].fds[&2], file("out", RedirectOp::Output));
+        assert_eq!(spawned[2].fds[&0], Fd::PipeRead(0));
+        assert!(spawned[3].fds.is_empty());
+    }
+
+    #[test]
+    fn follows_statuses() {
+        let recorder = Recorder::new().with_status("fail", 1);
+        assert_eq!(run(&recorder, "fail && a || b; ! fail"), Ok(0));
+        assert_eq!(recorder.argvs(), [["fail"], ["b"], ["fail"]]);
+
+        let recorder = Recorder::new().with_status("check", 1);
+        run(&recorder, "if check; then a; elif b; then c; else d; fi").unwrap();
+        assert_eq!(recorder.argvs(), [["check"], ["b"], ["c"]]);
+    }
+
+    #[test]
+    fn expands_words() {
+        let recorder = Recorder::new();
+        run(&recorder, "f() { print \"$1\" $2; }; for x in 1 2; do f \"$x y\" z; done").unwrap();
+        assert_eq!(recorder.argvs(), [["print", "1 y", "z"], ["print", "2 y", "z"]]);
+
+        let recorder = Recorder::new();
+        run(&recorder, "case abc in x*) a;; a?c) b;; esac").unwrap();
+        assert_eq!(recorder.argvs(), [["b"]]);
+    }
+
+    #[test]
+    fn reports_missing_programs() {
+        let recorder = Recorder::new().without_program("nope");
+        assert_eq!(run(&recorder, "nope"), Err(ExecError::CommandNotFound("nope".to_string())));
+        assert_eq!(run(&recorder, "nope || a"), Ok(0));
+        assert_eq!(recorder.argvs(), [["a"]]);
+    }
+
+    #[test]
+    fn stops_on_failures_under_errexit_and_nounset() {
+        let recorder = Recorder::new().with_status("fail", 2);
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("set -e; fail || a; if fail; then :; fi; ! a; f() { fail; b; }; f && c; fail; d").unwrap();
+        assert_eq!(recorder.argvs(), [["fail"], ["a"], ["fail"], ["a"], ["fail"], ["b"], ["c"], ["fail"]]);
+        assert_eq!(shell.exit_status(), Some(2));
+        shell.run("e").unwrap();
+        assert_eq!(recorder.argvs().len(), 8);
+
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.options_mut().interactive = true;
+        shell.run("set -u").unwrap();
+        assert_eq!(shell.run("a \"$@\"; b $nope; c"), Err(Error::Exec(ExecError::Unbound("nope".to_string()))));
+        assert_eq!(shell.exit_status(), None);
+        shell.options_mut().interactive = false;
+        assert!(shell.run("for x in $1; do d; done; e").is_err());
+        assert_eq!(shell.exit_status(), Some(1));
+        assert_eq!(recorder.argvs(), [["a"]]);
+    }
+
+    #[test]
+    fn traces_and_skips_commands() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("set -x; x='a b'; echo $x \"$x\" | wc; PS4='> '; y=1 ls; set +x; set -n; z").unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&recorder.written(&Fd::Inherited(2))),
+            "+ x='a b'\n+ echo a b 'a b'\n+ wc\n+ PS4='> '\n> y=1\n> ls\n> set +x\n"
+        );
+        assert_eq!(recorder.argvs(), [["wc"], ["ls"]]);
+    }
+
+    #[test]
+    fn expands_and_protects_files() {
+        let dir = env::temp_dir().join(format!("crussh-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["b.rs", "a.rs", ".c.rs", "out"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.set_cwd(&dir).unwrap();
+        shell.run("ls *.rs '*'.rs; p='?.rs'; ls $p \"$p\" *.c; shopt -s nullglob dotglob; ls *.c *.rs; set -f; ls *.rs").unwrap();
+        assert_eq!(recorder.argvs(), [
+            vec!["ls", "a.rs", "b.rs", "*.rs"],
+            vec!["ls", "a.rs", "b.rs", "?.rs", "*.c"],
+            vec!["ls", ".c.rs", "a.rs", "b.rs"],
+            vec!["ls", "*.rs"],
+        ]);
+
+        assert_eq!(shell.run("set -C; a > out"), Err(Error::Exec(ExecError::Clobber("out".to_string()))));
+        shell.run("a >| out; b > new").unwrap();
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[4].fds[&1], file("out", RedirectOp::Clobber));
+        assert_eq!(spawned[5].fds[&1], file("new", RedirectOp::Output));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn logs_background_jobs_on_request() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.unset_var("CRUSSH_JOBLOG_DIR");
+        shell.run("sleep 1 &").unwrap();
+        assert!(recorder.written(&Fd::Inherited(2)).is_empty());
+        shell.run("shopt -s joblog; sleep 2 &").unwrap();
+        assert_eq!(recorder.written(&Fd::Inherited(2)), b"crussh: joblog: CRUSSH_JOBLOG_DIR not set; job output is not logged\n");
+        shell.run("CRUSSH_JOBLOG_DIR=/tmp/logs/; /bin/sleep 3 && b &").unwrap();
+
+        let spawned = recorder.spawned();
+        assert!(spawned[0].fds.is_empty());
+        assert!(spawned[1].fds.is_empty());
+        assert_eq!(spawned[2].fds[&1], file("/tmp/logs/1-sleep.log", RedirectOp::Output));
+        assert_eq!(spawned[2].fds[&2], file("/tmp/logs/1-sleep.log", RedirectOp::Output));
+    }
+}
@@ -0,0 +1,353 @@
+//! Arithmetic, as `let` evaluates it: C's integer operators on 64-bit
+//! numbers, where names stand for shell variables.
+
+use std::convert::TryFrom;
+
+use crate::Shell;
+
+/// Operators, longest first so that `<<=` is not read as `<<` and `=`.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=", "+=",
+    "-=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "&", "^", "|", "!", "~", "?", ":", "=", ",",
+    "(", ")",
+];
+
+/// The binary operators from the loosest binding to the tightest, below
+/// `**` and the unary operators.
+const LEVELS: [&[&str]; 10] = [
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+const ASSIGNMENTS: &[&str] = &["=", "*=", "/=", "%=", "+=", "-=", "<<=", ">>=", "&=", "^=", "|="];
+
+/// How deeply variables whose values are expressions may refer to others.
+const MAX_DEPTH: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+/// Evaluates `expr`, assigning to the shell's variables as it asks to.
+pub(crate) fn evaluate(shell: &mut Shell, expr: &str) -> Result<i64, String> {
+    evaluate_at(shell, expr, 0)
+}
+
+fn evaluate_at(shell: &mut Shell, expr: &str, depth: usize) -> Result<i64, String> {
+    if depth > MAX_DEPTH {
+        return Err("expression recursion level exceeded".to_string());
+    }
+    let mut parser = Parser { tokens: tokenize(expr)?, pos: 0, shell, depth };
+    if parser.tokens.is_empty() {
+        return Ok(0);
+    }
+    let value = parser.comma(true)?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(_) => Err("syntax error in expression".to_string()),
+    }
+}
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut s = expr.trim_start();
+    while !s.is_empty() {
+        let c = s.chars().next().unwrap_or_default();
+        let len = if c.is_ascii_digit() {
+            let len = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '#' || c == '@' || c == '_')).unwrap_or(s.len());
+            tokens.push(Token::Number(number(&s[..len])?));
+            len
+        } else if c == '_' || c.is_ascii_alphabetic() {
+            let len = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(s.len());
+            tokens.push(Token::Name(s[..len].to_string()));
+            len
+        } else {
+            match OPERATORS.iter().find(|op| s.starts_with(**op)) {
+                Some(op) => {
+                    tokens.push(Token::Operator(op));
+                    op.len()
+                },
+                None => return Err(format!("syntax error: invalid arithmetic operator (error token is \"{}\")", s)),
+            }
+        };
+        s = s[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Reads a number: decimal, octal with a leading `0`, hexadecimal with
+/// `0x`, or in any base from 2 to 64 as `base#digits`.
+fn number(s: &str) -> Result<i64, String> {
+    let (base, digits) = match s.split_once('#') {
+        Some((base, digits)) => match base.parse::<u32>() {
+            Ok(base) if (2..=64).contains(&base) => (base, digits),
+            _ => return Err(format!("{}: invalid arithmetic base", s)),
+        },
+        None => match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(digits) => (16, digits),
+            None if s.len() > 1 && s.starts_with('0') => (8, &s[1..]),
+            None => (10, s),
+        },
+    };
+    if digits.is_empty() {
+        return Err(format!("{}: invalid number", s));
+    }
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => u32::MAX,
+        };
+        if digit >= base {
+            return Err(format!("{}: value too great for base", s));
+        }
+        value = value.wrapping_mul(i64::from(base)).wrapping_add(i64::from(digit));
+    }
+    Ok(value)
+}
+
+/// Parses and evaluates at once. Where a value is not used, as on the
+/// right of a `&&` whose left is false, `live` is unset: the operands are
+/// still parsed, but nothing is assigned and nothing fails.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    shell: &'a mut Shell,
+    depth: usize,
+}
+
+impl Parser<'_> {
+
+    fn peek_operator(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Operator(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.peek_operator() == Some(op) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("syntax error: `{}' expected", op))
+        }
+    }
+
+    fn comma(&mut self, live: bool) -> Result<i64, String> {
+        let mut value = self.assignment(live)?;
+        while self.peek_operator() == Some(",") {
+            self.pos += 1;
+            value = self.assignment(live)?;
+        }
+        Ok(value)
+    }
+
+    fn assignment(&mut self, live: bool) -> Result<i64, String> {
+        let op = match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            (Some(Token::Name(_)), Some(Token::Operator(op))) if ASSIGNMENTS.contains(op) => *op,
+            (_, Some(Token::Operator(op))) if ASSIGNMENTS.contains(op) && self.peek_operator().is_none() => {
+                return Err("attempted assignment to non-variable".to_string());
+            },
+            _ => return self.conditional(live),
+        };
+        let name = match &self.tokens[self.pos] {
+            Token::Name(name) => name.clone(),
+            _ => unreachable!(),
+        };
+        self.pos += 2;
+        let value = self.assignment(live)?;
+        if !live {
+            return Ok(0);
+        }
+        let value = match op {
+            "=" => value,
+            op => apply(&op[..op.len() - 1], self.variable(&name)?, value)?,
+        };
+        self.shell.set_var(&name, value.to_string());
+        Ok(value)
+    }
+
+    fn conditional(&mut self, live: bool) -> Result<i64, String> {
+        let condition = self.binary(0, live)?;
+        if self.peek_operator() != Some("?") {
+            return Ok(condition);
+        }
+        self.pos += 1;
+        let then = self.comma(live && condition != 0)?;
+        self.expect(":")?;
+        let otherwise = self.conditional(live && condition == 0)?;
+        Ok(if condition != 0 { then } else { otherwise })
+    }
+
+    fn binary(&mut self, level: usize, live: bool) -> Result<i64, String> {
+        if level == LEVELS.len() {
+            return self.power(live);
+        }
+        let mut value = self.binary(level + 1, live)?;
+        while let Some(op) = self.peek_operator().filter(|op| LEVELS[level].contains(op)) {
+            self.pos += 1;
+            let right_live = match op {
+                "&&" => live && value != 0,
+                "||" => live && value == 0,
+                _ => live,
+            };
+            let right = self.binary(level + 1, right_live)?;
+            value = if live { apply(op, value, right)? } else { 0 };
+        }
+        Ok(value)
+    }
+
+    /// `**`, which groups to the right.
+    fn power(&mut self, live: bool) -> Result<i64, String> {
+        let base = self.unary(live)?;
+        if self.peek_operator() != Some("**") {
+            return Ok(base);
+        }
+        self.pos += 1;
+        let exponent = self.power(live)?;
+        if live { apply("**", base, exponent) } else { Ok(0) }
+    }
+
+    fn unary(&mut self, live: bool) -> Result<i64, String> {
+        let op = match self.peek_operator() {
+            Some(op @ ("-" | "+" | "!" | "~" | "++" | "--")) => op,
+            _ => return self.postfix(live),
+        };
+        self.pos += 1;
+        if op == "++" || op == "--" {
+            let name = match self.tokens.get(self.pos) {
+                Some(Token::Name(name)) => name.clone(),
+                _ => return Err("syntax error: operand expected".to_string()),
+            };
+            self.pos += 1;
+            if !live {
+                return Ok(0);
+            }
+            let value = self.variable(&name)?.wrapping_add(if op == "++" { 1 } else { -1 });
+            self.shell.set_var(&name, value.to_string());
+            return Ok(value);
+        }
+        let value = self.unary(live)?;
+        Ok(match op {
+            "-" => value.wrapping_neg(),
+            "!" => (value == 0) as i64,
+            "~" => !value,
+            _ => value,
+        })
+    }
+
+    fn postfix(&mut self, live: bool) -> Result<i64, String> {
+        let token = match self.tokens.get(self.pos) {
+            Some(token) => token.clone(),
+            None => return Err("syntax error: operand expected".to_string()),
+        };
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(value),
+            Token::Name(name) => {
+                let value = if live { self.variable(&name)? } else { 0 };
+                if let Some(op @ ("++" | "--")) = self.peek_operator() {
+                    self.pos += 1;
+                    if live {
+                        let changed = value.wrapping_add(if op == "++" { 1 } else { -1 });
+                        self.shell.set_var(&name, changed.to_string());
+                    }
+                }
+                Ok(value)
+            },
+            Token::Operator("(") => {
+                let value = self.comma(live)?;
+                self.expect(")")?;
+                Ok(value)
+            },
+            Token::Operator(_) => Err("syntax error: operand expected".to_string()),
+        }
+    }
+
+    /// The value of a variable, which may itself be an expression. Unset
+    /// and empty variables are 0.
+    fn variable(&mut self, name: &str) -> Result<i64, String> {
+        let value = self.shell.var(name).unwrap_or_default().to_string();
+        match value.trim().parse() {
+            Ok(value) => Ok(value),
+            Err(_) => evaluate_at(self.shell, &value, self.depth + 1),
+        }
+    }
+}
+
+fn apply(op: &str, left: i64, right: i64) -> Result<i64, String> {
+    Ok(match op {
+        "||" => (left != 0 || right != 0) as i64,
+        "&&" => (left != 0 && right != 0) as i64,
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "==" => (left == right) as i64,
+        "!=" => (left != right) as i64,
+        "<=" => (left <= right) as i64,
+        ">=" => (left >= right) as i64,
+        "<" => (left < right) as i64,
+        ">" => (left > right) as i64,
+        "<<" => left.wrapping_shl(right as u32),
+        ">>" => left.wrapping_shr(right as u32),
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => return Err("division by 0".to_string()),
+        "/" => left.wrapping_div(right),
+        "%" => left.wrapping_rem(right),
+        "**" if right < 0 => return Err("exponent less than 0".to_string()),
+        "**" => left.wrapping_pow(u32::try_from(right).unwrap_or(u32::MAX)),
+        _ => unreachable!("not a binary operator: {}", op),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn evaluates_c_expressions() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        let mut eval = |expr| evaluate(&mut shell, expr);
+        assert_eq!(eval("1 + 2 * 3 - -4"), Ok(11));
+        assert_eq!(eval("(1 + 2) * 3 % 5 << 2"), Ok(16));
+        assert_eq!(eval("-2 ** 2 + 2 ** 3 ** 2"), Ok(516));
+        assert_eq!(eval("010 + 0x10 + 2#101 + 64#_"), Ok(8 + 16 + 5 + 63));
+        assert_eq!(eval("!0 + ~0 + (3 > 2) + (2 == 3) + (5 & 3 | 8 ^ 1)"), Ok(10));
+        assert_eq!(eval("x = 5, y = x++ + ++x, x *= 2, z = x > 10 ? y : 0"), Ok(12));
+        assert_eq!(eval("0 && (w = 1), 1 || (w = 2), 1 ? 3 : (w = 3)"), Ok(3));
+        assert_eq!(eval("e = 'x'"), Err("syntax error: invalid arithmetic operator (error token is \"'x'\")".to_string()));
+        assert_eq!(eval("5 / (x - 14)"), Err("division by 0".to_string()));
+        assert_eq!(eval("1 +"), Err("syntax error: operand expected".to_string()));
+        assert_eq!(eval("2 = 3"), Err("attempted assignment to non-variable".to_string()));
+        assert_eq!(eval("09"), Err("09: value too great for base".to_string()));
+        assert_eq!(eval("1 2"), Err("syntax error in expression".to_string()));
+
+        assert_eq!(shell.var("x"), Some("14"));
+        assert_eq!(shell.var("y"), Some("12"));
+        assert_eq!(shell.var("z"), Some("12"));
+        assert_eq!(shell.var("w"), None);
+        shell.set_var("expr", "y - 2");
+        assert_eq!(evaluate(&mut shell, "expr * 2 + unset"), Ok(20));
+    }
+}
@@ -0,0 +1,254 @@
+//! Word expansion: tilde and parameter expansion, field splitting,
+//! pathname expansion and quote removal.
+
+use nix::unistd::User;
+
+use super::glob;
+use crate::ExecError;
+use crate::ast::{Word, WordPart};
+use crate::shell::Shell;
+
+const DEFAULT_IFS: &str = " \t\n";
+
+/// Fields being built from one word.
+#[derive(Default)]
+struct Fields {
+    /// Each field, with its pattern if it has unquoted `*`, `?` or `[`.
+    fields: Vec<(String, Option<String>)>,
+    current: String,
+    /// `current` as a pattern, with what was quoted escaped.
+    pattern: String,
+    /// Whether `current` has pattern characters that were not quoted.
+    glob: bool,
+    /// Whether `current` exists even if empty, e.g. after `""`.
+    started: bool,
+}
+
+impl Fields {
+
+    fn push_str(&mut self, s: &str) {
+        self.current.push_str(s);
+        self.pattern.push_str(&escape_pattern(s));
+        self.started = true;
+    }
+
+    /// Appends unquoted text, whose `*`, `?` and `[` make a pattern.
+    fn push_unquoted(&mut self, s: &str) {
+        self.current.push_str(s);
+        self.pattern.push_str(s);
+        self.glob |= glob::has_wildcards(s);
+        self.started = true;
+    }
+
+    /// Appends an unquoted expansion, splitting it on `ifs`.
+    fn push_split(&mut self, s: &str, ifs: &str) {
+        for c in s.chars() {
+            if ifs.contains(c) {
+                self.end_field();
+            } else {
+                self.push_unquoted(c.encode_utf8(&mut [0; 4]));
+            }
+        }
+    }
+
+    fn end_field(&mut self) {
+        if self.started {
+            let pattern = std::mem::take(&mut self.pattern);
+            let glob = std::mem::take(&mut self.glob);
+            self.fields.push((std::mem::take(&mut self.current), Some(pattern).filter(|_| glob)));
+            self.started = false;
+        }
+    }
+
+    fn finish(mut self) -> Vec<(String, Option<String>)> {
+        self.end_field();
+        self.fields
+    }
+}
+
+impl Shell {
+
+    /// Expands command words into the fields that make up `argv`.
+    pub(crate) fn expand_words(&self, words: &[Word]) -> Result<Vec<String>, ExecError> {
+        let mut fields = Vec::new();
+        for word in words {
+            fields.extend(self.expand_fields(word)?);
+        }
+        Ok(fields)
+    }
+
+    fn expand_fields(&self, word: &Word) -> Result<Vec<String>, ExecError> {
+        let ifs = self.var("IFS").unwrap_or(DEFAULT_IFS).to_string();
+        let mut fields = Fields::default();
+
+        for (i, part) in word.parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(text) if i == 0 => match self.split_tilde(text) {
+                    Some((home, rest)) => {
+                        fields.push_str(&home);
+                        fields.push_unquoted(rest);
+                    },
+                    None => fields.push_unquoted(text),
+                },
+                WordPart::Literal(text) => fields.push_unquoted(text),
+                WordPart::Quoted(text) => fields.push_str(text),
+                WordPart::Parameter { name, quoted: true } if name == "@" => {
+                    for (n, param) in self.positional.iter().enumerate() {
+                        if n > 0 {
+                            fields.started = true;
+                            fields.end_field();
+                        }
+                        fields.push_str(param);
+                    }
+                },
+                WordPart::Parameter { name, quoted: true } => fields.push_str(&self.expand_parameter(name)?),
+                WordPart::Parameter { name, quoted: false } if name == "@" || name == "*" => {
+                    for param in &self.positional {
+                        fields.push_split(param, &ifs);
+                        fields.end_field();
+                    }
+                },
+                WordPart::Parameter { name, quoted: false } => {
+                    fields.push_split(&self.expand_parameter(name)?, &ifs)
+                },
+            }
+        }
+
+        let mut expanded = Vec::new();
+        for (field, pattern) in fields.finish() {
+            match pattern.filter(|_| !self.options.noglob) {
+                Some(pattern) => {
+                    let paths = glob::expand(&pattern, &self.cwd, self.options.dotglob);
+                    if paths.is_empty() && !self.options.nullglob {
+                        expanded.push(field);
+                    }
+                    expanded.extend(paths);
+                },
+                None => expanded.push(field),
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// Expands a word into a single string, without field splitting, as for
+    /// assignment values and redirection targets.
+    pub(crate) fn expand_word(&self, word: &Word) -> Result<String, ExecError> {
+        let mut expanded = String::new();
+        for (i, part) in word.parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(text) if i == 0 => expanded.push_str(&self.tilde(text)),
+                WordPart::Literal(text) | WordPart::Quoted(text) => expanded.push_str(text),
+                WordPart::Parameter { name, .. } => expanded.push_str(&self.expand_parameter(name)?),
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// Expands a word into a pattern, escaping whatever was quoted so that
+    /// it only matches itself.
+    pub(crate) fn expand_pattern(&self, word: &Word) -> Result<String, ExecError> {
+        let mut expanded = String::new();
+        for (i, part) in word.parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(text) if i == 0 => expanded.push_str(&self.tilde(text)),
+                WordPart::Literal(text) => expanded.push_str(text),
+                WordPart::Quoted(text) => expanded.push_str(&escape_pattern(text)),
+                WordPart::Parameter { name, quoted: false } => expanded.push_str(&self.expand_parameter(name)?),
+                WordPart::Parameter { name, quoted: true } => {
+                    expanded.push_str(&escape_pattern(&self.expand_parameter(name)?))
+                },
+            }
+        }
+        Ok(expanded)
+    }
+
+    /// The value a parameter expands to, which is empty if it is unset,
+    /// or an error under `set -u`.
+    fn expand_parameter(&self, name: &str) -> Result<String, ExecError> {
+        match self.parameter(name) {
+            Some(value) => Ok(value),
+            None if self.options.nounset => Err(ExecError::Unbound(name.to_string())),
+            None => Ok(String::new()),
+        }
+    }
+
+    /// The value of a variable, positional or special parameter.
+    pub(crate) fn parameter(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(self.shell_pid.to_string()),
+            "!" => self.last_background.map(|pid| pid.to_string()),
+            "#" => Some(self.positional.len().to_string()),
+            "0" => Some("crussh".to_string()),
+            "-" => Some(self.options.flags()),
+            "@" | "*" => {
+                let separator = match self.var("IFS") {
+                    Some(ifs) => ifs.chars().next().map(String::from).unwrap_or_default(),
+                    None => " ".to_string(),
+                };
+                Some(self.positional.join(&separator))
+            },
+            name if name.chars().all(|c| c.is_ascii_digit()) => {
+                let n: usize = name.parse().ok()?;
+                self.positional.get(n.checked_sub(1)?).cloned()
+            },
+            name => self.var(name).map(str::to_string),
+        }
+    }
+
+    /// Expands a leading `~`, `~user`, `~+`, `~-` or `~N` in the first
+    /// segment of a word.
+    fn tilde(&self, text: &str) -> String {
+        match self.split_tilde(text) {
+            Some((home, rest)) => home + rest,
+            None => text.to_string(),
+        }
+    }
+
+    /// The directory a leading tilde prefix stands for, and the text after
+    /// the prefix.
+    fn split_tilde<'a>(&self, text: &'a str) -> Option<(String, &'a str)> {
+        let rest = text.strip_prefix('~')?;
+        let (user, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+
+        let home = match user {
+            "" => self.var("HOME").map(str::to_string),
+            "+" => Some(self.cwd.to_string_lossy().into_owned()),
+            "-" => self.var("OLDPWD").map(str::to_string),
+            _ if stack_index(user).is_some() => self.stack_entry(user),
+            _ => User::from_name(user).ok().flatten().map(|u| u.dir.to_string_lossy().into_owned()),
+        };
+        home.map(|home| (home, path))
+    }
+
+    /// Entry `N`, `+N` or `-N` of the directory stack as `dirs` lists it:
+    /// counting from the working directory, or from the end with `-`.
+    fn stack_entry(&self, index: &str) -> Option<String> {
+        let (from_end, n) = stack_index(index)?;
+        let n = if from_end { (self.dir_stack.len() + 1).checked_sub(n + 1)? } else { n };
+        match n {
+            0 => Some(self.cwd.to_string_lossy().into_owned()),
+            n => self.dir_stack.get(n - 1).map(|dir| dir.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/// Parses `N`, `+N` or `-N`, returning whether it counts from the end.
+fn stack_index(s: &str) -> Option<(bool, usize)> {
+    let digits = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((s.starts_with('-'), digits.parse().ok()?))
+}
+
+fn escape_pattern(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "*?[]\\".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
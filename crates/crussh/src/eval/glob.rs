@@ -0,0 +1,116 @@
+//! Pathname expansion: the files a pattern such as `src/*.rs` names.
+
+use std::fs;
+use std::path::Path;
+
+use super::pattern;
+
+/// The paths matching `pattern`, which are relative to `dir` unless the
+/// pattern is absolute, sorted within each directory. A name starting with
+/// `.` is only matched by a segment that starts with one too, unless
+/// `dotglob` is set.
+pub(crate) fn expand(pattern: &str, dir: &Path, dotglob: bool) -> Vec<String> {
+    let mut paths = vec![if pattern.starts_with('/') { "/".to_string() } else { String::new() }];
+    let mut segments = pattern.split('/').filter(|segment| !segment.is_empty()).peekable();
+
+    while let Some(segment) = segments.next() {
+        // Every segment but the last, or a last one followed by `/`, has
+        // to name a directory.
+        let directory = segments.peek().is_some() || pattern.ends_with('/');
+        let mut matched = Vec::new();
+        for prefix in &paths {
+            let base = dir.join(prefix);
+            let names = if has_wildcards(segment) {
+                matching(&base, segment, dotglob)
+            } else {
+                let name = unescape(segment);
+                if fs::symlink_metadata(base.join(&name)).is_ok() { vec![name] } else { Vec::new() }
+            };
+            for name in names {
+                let path = format!("{}{}", prefix, name);
+                if !directory {
+                    matched.push(path);
+                } else if dir.join(&path).is_dir() {
+                    matched.push(path + "/");
+                }
+            }
+        }
+        paths = matched;
+    }
+    paths
+}
+
+/// Whether `word` has an unescaped `*`, `?` or `[`, and so is a pattern.
+pub(crate) fn has_wildcards(word: &str) -> bool {
+    let mut chars = word.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            },
+            '*' | '?' | '[' => return true,
+            _ => {},
+        }
+    }
+    false
+}
+
+/// The names in `dir` that `segment` matches, sorted.
+fn matching(dir: &Path, segment: &str, dotglob: bool) -> Vec<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let explicit_dot = segment.starts_with('.') || segment.starts_with("\\.");
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.') || dotglob || explicit_dot)
+        .filter(|name| pattern::matches(segment, name))
+        .collect();
+    names.sort();
+    names
+}
+
+fn unescape(segment: &str) -> String {
+    let mut unescaped = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{expand, has_wildcards};
+
+    #[test]
+    fn matches_files_segment_by_segment() {
+        let dir = std::env::temp_dir().join(format!("crussh-glob-{}", std::process::id()));
+        for file in ["src/main.rs", "src/lib.rs", "src/.hidden.rs", "tests/a.rs", "README"] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(expand("src/*.rs", &dir, false), ["src/lib.rs", "src/main.rs"]);
+        assert_eq!(expand("src/*.rs", &dir, true), ["src/.hidden.rs", "src/lib.rs", "src/main.rs"]);
+        assert_eq!(expand("src/.*", &dir, false), ["src/.hidden.rs"]);
+        assert_eq!(expand("*/a.rs", &dir, false), ["tests/a.rs"]);
+        assert_eq!(expand("*/", &dir, false), ["src/", "tests/"]);
+        assert_eq!(expand("R??DME", &dir, false), ["README"]);
+        assert!(expand("*.c", &dir, false).is_empty());
+        let absolute = format!("{}/s[r]c/m*", dir.display());
+        assert_eq!(expand(&absolute, &dir, false), [format!("{}/src/main.rs", dir.display())]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(has_wildcards("a[bc]"));
+        assert!(!has_wildcards("a\\*"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Shell pattern matching (`*`, `?`, `[...]`), as used by `case`.
+
+/// Whether `text` matches the whole of `pattern`. A backslash makes the
+/// next pattern character match itself.
+pub(crate) fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the most recent `*`: pattern index and the
+    // text index the star currently extends to.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() || p < pattern.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    star = Some((p + 1, t));
+                    p += 1;
+                    continue;
+                },
+                '?' if t < text.len() => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                },
+                '[' if t < text.len() => {
+                    if let Some((matched, next)) = bracket(&pattern, p, text[t]) {
+                        if matched {
+                            p = next;
+                            t += 1;
+                            continue;
+                        }
+                    } else if text[t] == '[' {
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                },
+                '\\' if p + 1 < pattern.len() && t < text.len() && pattern[p + 1] == text[t] => {
+                    p += 2;
+                    t += 1;
+                    continue;
+                },
+                '\\' if p + 1 < pattern.len() => {},
+                c if t < text.len() && c == text[t] && c != '?' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                },
+                _ => {}
+            }
+        }
+
+        match star {
+            Some((star_p, star_t)) if star_t < text.len() => {
+                star = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            },
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+/// Matches `c` against the bracket expression starting at `pattern[start]`,
+/// returning whether it matched and the index after the closing `]`, or
+/// `None` if the bracket is not closed.
+fn bracket(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let current = *pattern.get(i)?;
+        if current == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+
+        if current == '[' && pattern.get(i + 1) == Some(&':') {
+            let rest: String = pattern[i + 2..].iter().collect();
+            if let Some(end) = rest.find(":]") {
+                matched |= class_matches(&rest[..end], c);
+                i += 2 + rest[..end].chars().count() + 2;
+                continue;
+            }
+        }
+
+        let (low, next) = match current {
+            '\\' => (*pattern.get(i + 1)?, i + 2),
+            current => (current, i + 1),
+        };
+        if pattern.get(next) == Some(&'-') && pattern.get(next + 1).is_some_and(|&h| h != ']') {
+            let high = pattern[next + 1];
+            matched |= low <= c && c <= high;
+            i = next + 2;
+        } else {
+            matched |= low == c;
+            i = next;
+        }
+    }
+}
+
+fn class_matches(class: &str, c: char) -> bool {
+    match class {
+        "alpha" => c.is_alphabetic(),
+        "digit" => c.is_ascii_digit(),
+        "alnum" => c.is_alphanumeric(),
+        "upper" => c.is_uppercase(),
+        "lower" => c.is_lowercase(),
+        "space" => c.is_whitespace(),
+        "blank" => c == ' ' || c == '\t',
+        "punct" => c.is_ascii_punctuation(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        "cntrl" => c.is_control(),
+        "print" => !c.is_control(),
+        "graph" => !c.is_control() && !c.is_whitespace(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("*.rs", "main.rc"));
+        assert!(matches("?", "x"));
+        assert!(!matches("?", ""));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn brackets() {
+        assert!(matches("[abc]x", "bx"));
+        assert!(matches("[!abc]x", "dx"));
+        assert!(matches("[a-z][[:digit:]]", "q7"));
+        assert!(!matches("[a-z]", "Q"));
+        assert!(matches("[", "["));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+    }
+}
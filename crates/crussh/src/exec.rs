@@ -0,0 +1,314 @@
+//! The operating-system side of running commands.
+//!
+//! The evaluator never forks, executes programs or moves descriptors
+//! itself; it asks an [`Executor`] to. [`Native`] does the real thing with
+//! `fork`, `execve` and `dup2`. [`Recorder`] starts nothing and instead
+//! records each program that would have run, with its arguments,
+//! environment and descriptors, which makes the evaluator testable without
+//! spawning processes.
+//!
+//! ```
+//! use crussh::exec::{Fd, Recorder};
+//!
+//! let recorder = Recorder::new();
+//! crussh::parse("ls -l | wc > out").unwrap().execute_with(recorder.clone()).unwrap();
+//!
+//! let spawned = recorder.spawned();
+//! assert_eq!(spawned[0].argv, ["ls", "-l"]);
+//! assert_eq!(spawned[0].fds[&1], Fd::PipeWrite(0));
+//! assert_eq!(spawned[1].fds[&0], Fd::PipeRead(0));
+//! assert!(matches!(&spawned[1].fds[&1], Fd::File { path, .. } if path == "out"));
+//! ```
+
+mod native;
+mod recorder;
+
+pub use native::Native;
+pub use recorder::{Fd, Recorder, Spawned};
+
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::time::Duration;
+
+use nix::sys::resource::Resource;
+use nix::sys::signal::Signal;
+
+use crate::{Captured, ExecError, Io, ast::RedirectOp};
+
+/// The side of a [`Executor::fork`] the caller continues on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Forked {
+    /// In the new process, which must end with [`Executor::exit`].
+    Child,
+    /// In the shell, with the id of the new process.
+    Parent(u32),
+}
+
+/// What the shell does when it receives a signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// The signal's default action, usually ending the process.
+    Default,
+    /// Nothing, in the shell and the programs it starts.
+    Ignore,
+    /// Nothing in the shell, while the programs it starts and its forked
+    /// copies get the default action, as an interactive shell does with
+    /// `SIGINT`.
+    IgnoreInShell,
+    /// The signal is queued, to be collected by
+    /// [`Executor::caught_signals`].
+    Catch,
+}
+
+/// CPU time and other resources used, as `getrusage(2)` reports them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub user: Duration,
+    pub system: Duration,
+    /// The largest resident set size reached, in kilobytes.
+    pub max_rss: u64,
+    /// Context switches made while waiting for something.
+    pub voluntary_switches: u64,
+    /// Context switches forced by the scheduler.
+    pub involuntary_switches: u64,
+}
+
+impl Usage {
+
+    /// Adds what another process used, keeping the larger resident set.
+    pub(crate) fn add(&mut self, other: &Usage) {
+        self.user += other.user;
+        self.system += other.system;
+        self.max_rss = self.max_rss.max(other.max_rss);
+        self.voluntary_switches += other.voluntary_switches;
+        self.involuntary_switches += other.involuntary_switches;
+    }
+
+    /// What was used since `earlier`, a reading of the same process. The
+    /// resident set is the largest so far.
+    pub(crate) fn since(&self, earlier: &Usage) -> Usage {
+        Usage {
+            user: self.user.saturating_sub(earlier.user),
+            system: self.system.saturating_sub(earlier.system),
+            max_rss: self.max_rss,
+            voluntary_switches: self.voluntary_switches.saturating_sub(earlier.voluntary_switches),
+            involuntary_switches: self.involuntary_switches.saturating_sub(earlier.involuntary_switches),
+        }
+    }
+}
+
+/// A resource limit of the shell and the programs it starts, where `None`
+/// is no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limit {
+    /// The limit the kernel enforces.
+    pub soft: Option<u64>,
+    /// How high the soft limit can be raised.
+    pub hard: Option<u64>,
+}
+
+/// The process and descriptor operations the evaluator needs.
+///
+/// Descriptors returned by [`open`](Executor::open) and
+/// [`pipe`](Executor::pipe) are the executor's own; the evaluator only
+/// passes them back in [`FdPlan`]s and to [`close`](Executor::close).
+pub trait Executor {
+    /// Finds the program `name` refers to, searching `path` (a `PATH`
+    /// value) when `name` has no slash. Relative names and `PATH` entries
+    /// are taken relative to `dir`, the shell's working directory.
+    fn find_program(&mut self, name: &str, path: &str, dir: &Path) -> Result<String, ExecError>;
+
+    /// Opens `path`, relative to `dir`, the way `op` needs for a
+    /// redirection.
+    fn open(&mut self, dir: &Path, path: &str, op: RedirectOp) -> Result<RawFd, ExecError>;
+
+    /// Creates a pipe, returning its read and write ends.
+    fn pipe(&mut self) -> Result<(RawFd, RawFd), ExecError>;
+
+    fn close(&mut self, fd: RawFd);
+
+    /// Starts `program` in `dir` with the descriptors set up by `fds` and
+    /// returns its process id.
+    fn spawn(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path, fds: &FdPlan) -> Result<u32, ExecError>;
+
+    /// Replaces the shell with `program`, run in `dir` with the shell's own
+    /// descriptors. A real process only comes back from this if the program
+    /// could not be started; an executor that starts nothing returns the
+    /// status the program would have ended the shell with.
+    fn exec(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path) -> Result<i32, ExecError>;
+
+    /// Starts a copy of the shell with the descriptors set up by `fds`.
+    fn fork(&mut self, fds: &FdPlan) -> Result<Forked, ExecError>;
+
+    /// Ends the child started by the last [`fork`](Executor::fork) with
+    /// `status`. A real process never returns from this; an executor that
+    /// runs children in place returns the id it gave the child.
+    fn exit(&mut self, status: i32) -> u32;
+
+    /// Waits for a process and returns its exit status, 128 plus the
+    /// signal number if it was killed.
+    fn wait(&mut self, pid: u32) -> Result<i32, ExecError>;
+
+    /// Waits for a process like [`wait`](Executor::wait), and also returns
+    /// the resources used by it and the children it waited for.
+    fn wait_with_usage(&mut self, pid: u32) -> Result<(i32, Usage), ExecError> {
+        self.wait(pid).map(|status| (status, Usage::default()))
+    }
+
+    /// The resources the shell itself has used so far.
+    fn usage(&mut self) -> Usage {
+        Usage::default()
+    }
+
+    /// Sends `signal` to process `pid`, or to the process group it leads
+    /// if `group` is set. With no signal it only checks that the process
+    /// exists.
+    fn kill(&mut self, pid: u32, signal: Option<Signal>, group: bool) -> Result<(), ExecError>;
+
+    /// Puts process `pid`, or the calling process if `pid` is 0, in a new
+    /// process group of its own.
+    fn set_process_group(&mut self, _pid: u32) {}
+
+    /// Writes `input` to the pipe `fds[0]` and collects what `pid` and the
+    /// processes it starts write to the pipes `fds[1]` and `fds[2]`, until
+    /// both are closed and `pid` has exited. If `timeout` passes first,
+    /// `pid`'s process group is killed. The three descriptors are closed.
+    fn communicate(
+        &mut self,
+        pid: u32,
+        fds: [RawFd; 3],
+        input: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<Captured, ExecError>;
+
+    /// Collects those of the background processes `pids` that have
+    /// finished, returning their ids and exit statuses. Other children of
+    /// the process, which may belong to another shell or to the program
+    /// running this one, are left alone.
+    fn reap(&mut self, _pids: &[u32]) -> Vec<(u32, i32)> {
+        Vec::new()
+    }
+
+    /// Sets what the shell does with `signal`. Forked copies of the shell
+    /// and the programs it starts go back to the default action for
+    /// signals it catches or ignores with
+    /// [`IgnoreInShell`](Disposition::IgnoreInShell).
+    fn set_disposition(&mut self, _signal: Signal, _disposition: Disposition) {}
+
+    /// Takes the caught signals that have arrived since the last call, each
+    /// once, in signal number order.
+    fn caught_signals(&mut self) -> Vec<Signal> {
+        Vec::new()
+    }
+
+    /// The shell's limit on `resource`, or no limit if it cannot be read.
+    fn limit(&mut self, _resource: Resource) -> Limit {
+        Limit::default()
+    }
+
+    /// Sets the shell's limit on `resource`, which the programs it starts
+    /// inherit.
+    fn set_limit(&mut self, _resource: Resource, _limit: Limit) -> Result<(), ExecError> {
+        Ok(())
+    }
+
+    /// The file mode bits that files the shell and its programs create
+    /// start without.
+    fn umask(&mut self) -> u32 {
+        0o022
+    }
+
+    fn set_umask(&mut self, _mask: u32) {}
+
+    /// The shell's own standard streams, for a builtin to use.
+    fn stdio(&mut self) -> Io;
+
+    /// Applies `fds` to the shell itself, until the returned value is
+    /// passed to [`restore`](Executor::restore). To keep the changes
+    /// instead, [`close`](Executor::close) the saved copies.
+    fn redirect(&mut self, fds: &FdPlan) -> Result<SavedFds, ExecError>;
+
+    fn restore(&mut self, saved: SavedFds);
+}
+
+/// Descriptor changes to make for a command: each target is either made a
+/// copy of a source descriptor or closed, in order.
+#[derive(Debug, Default)]
+pub struct FdPlan {
+    actions: Vec<(RawFd, Option<RawFd>)>,
+    /// Executor descriptors to close once the actions are applied.
+    close: Vec<RawFd>,
+    /// Descriptors opened for this plan, which the shell closes after use.
+    owned: Vec<RawFd>,
+}
+
+/// The pipe ends a pipeline stage reads from and writes to.
+#[derive(Debug, Default)]
+pub(crate) struct Stdio {
+    pub(crate) stdin: Option<RawFd>,
+    pub(crate) stdout: Option<RawFd>,
+    /// The other end of the stage's output pipe, which the stage must not hold.
+    pub(crate) unused: Option<RawFd>,
+}
+
+impl FdPlan {
+
+    pub(crate) fn new(stdio: &Stdio) -> Self {
+        let mut plan = Self::default();
+        if let Some(fd) = stdio.stdin {
+            plan.actions.push((0, Some(fd)));
+            plan.close.push(fd);
+        }
+        if let Some(fd) = stdio.stdout {
+            plan.actions.push((1, Some(fd)));
+            plan.close.push(fd);
+        }
+        plan.close.extend(stdio.unused);
+        plan
+    }
+
+    /// Makes `target` a copy of `source`, which the plan takes ownership of.
+    pub(crate) fn redirect_owned(&mut self, target: RawFd, source: RawFd) {
+        self.actions.push((target, Some(source)));
+        self.close.push(source);
+        self.owned.push(source);
+    }
+
+    pub(crate) fn redirect(&mut self, target: RawFd, source: RawFd) {
+        self.actions.push((target, Some(source)));
+    }
+
+    /// Keeps the new process from holding `fd`, one of the executor's
+    /// descriptors.
+    pub(crate) fn exclude(&mut self, fd: RawFd) {
+        self.close.push(fd);
+    }
+
+    pub(crate) fn close(&mut self, target: RawFd) {
+        self.actions.push((target, None));
+    }
+
+    /// Closes the descriptors opened for this plan.
+    pub(crate) fn release(&self, executor: &mut dyn Executor) {
+        for &fd in &self.owned {
+            executor.close(fd);
+        }
+    }
+
+    /// The changes to make, in order: `(target, Some(source))` makes
+    /// `target` a copy of `source`, `(target, None)` closes it.
+    pub fn actions(&self) -> &[(RawFd, Option<RawFd>)] {
+        &self.actions
+    }
+
+    /// Executor descriptors the new process must not keep open, closed
+    /// after the actions unless one of them is also a target.
+    pub fn closes(&self) -> &[RawFd] {
+        &self.close
+    }
+}
+
+/// What [`Executor::redirect`] replaced: each redirected descriptor with a
+/// saved copy of it, or `None` if it was not open.
+#[derive(Debug, Default)]
+pub struct SavedFds(pub Vec<(RawFd, Option<RawFd>)>);
@@ -0,0 +1,588 @@
+//! The fork/exec, pipe and descriptor plumbing of a real shell.
+//!
+//! Descriptors the shell opens for its own use (pipe ends, redirection
+//! targets, saved copies) are moved to 10 and above and marked close-on-exec,
+//! so they never leak into programs and never clash with descriptors a user
+//! redirects.
+
+use std::ffi::CString;
+use std::io::{self, Read, Write};
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::fcntl::{FcntlArg, FdFlag, OFlag, fcntl, open};
+use nix::libc;
+use nix::poll::{PollFd, PollFlags, poll};
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal, kill, killpg, sigaction};
+use nix::sys::resource::{self, Resource};
+use nix::sys::stat::{self, Mode};
+use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+use nix::unistd::{self, AccessFlags, ForkResult, Pid, access, chdir, close, dup2, execve, isatty, setpgid};
+
+use crate::{Captured, ExecError, Input, Io};
+use crate::ast::RedirectOp;
+use crate::capture::TIMED_OUT;
+use crate::exec::{Disposition, Executor, FdPlan, Forked, Limit, SavedFds, Usage};
+
+const FIRST_INTERNAL_FD: RawFd = 10;
+
+// Signal dispositions belong to the process, so they are kept here rather
+// than in `Native`; each is a set of signals with one bit per number.
+/// Signals with the shell's handler installed.
+static CATCHING: AtomicU64 = AtomicU64::new(0);
+/// Signals ignored with `Disposition::IgnoreInShell`.
+static SHIELDED: AtomicU64 = AtomicU64::new(0);
+/// Signals that have arrived and not been collected.
+static CAUGHT: AtomicU64 = AtomicU64::new(0);
+/// Signals ignored with `Disposition::Ignore`, which programs inherit.
+static IGNORING: AtomicU64 = AtomicU64::new(0);
+
+/// The executor that runs commands for real.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Native;
+
+impl Executor for Native {
+
+    fn find_program(&mut self, name: &str, path: &str, dir: &Path) -> Result<String, ExecError> {
+        if name.contains('/') {
+            let program = dir.join(name);
+            return check_executable(&program, name).map(|_| program.to_string_lossy().into_owned());
+        }
+
+        let mut denied = None;
+        for entry in path.split(':') {
+            let candidate = dir.join(entry).join(name);
+            if !candidate.is_file() {
+                continue;
+            }
+            match check_executable(&candidate, name) {
+                Ok(()) => return Ok(candidate.to_string_lossy().into_owned()),
+                Err(e) => denied = Some(e),
+            }
+        }
+
+        Err(denied.unwrap_or_else(|| ExecError::CommandNotFound(name.to_string())))
+    }
+
+    fn open(&mut self, dir: &Path, path: &str, op: RedirectOp) -> Result<RawFd, ExecError> {
+        let flags = match op {
+            RedirectOp::Input | RedirectOp::DupInput => OFlag::O_RDONLY,
+            RedirectOp::Output | RedirectOp::Clobber | RedirectOp::DupOutput => OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_TRUNC,
+            RedirectOp::Append => OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_APPEND,
+            RedirectOp::ReadWrite => OFlag::O_RDWR | OFlag::O_CREAT,
+        };
+        // The umask decides which of these the file ends up with.
+        let mode = Mode::S_IRUSR | Mode::S_IWUSR | Mode::S_IRGRP | Mode::S_IWGRP | Mode::S_IROTH | Mode::S_IWOTH;
+        open(&dir.join(path), flags, mode)
+            .and_then(internal_fd)
+            .map_err(|errno| ExecError::Redirection { path: path.to_string(), errno })
+    }
+
+    fn pipe(&mut self) -> Result<(RawFd, RawFd), ExecError> {
+        let (read, write) = unistd::pipe().map_err(ExecError::Pipe)?;
+        let read = internal_fd(read).map_err(ExecError::Pipe)?;
+        let write = internal_fd(write).map_err(ExecError::Pipe)?;
+        Ok((read, write))
+    }
+
+    fn close(&mut self, fd: RawFd) {
+        let _ = close(fd);
+    }
+
+    fn spawn(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path, fds: &FdPlan) -> Result<u32, ExecError> {
+        let program = to_cstring(program);
+        let argv: Vec<CString> = argv.iter().map(|arg| to_cstring(arg)).collect();
+        let env: Vec<CString> = env.iter()
+            .map(|(name, value)| to_cstring(&format!("{}={}", name, value)))
+            .collect();
+
+        match self.fork(fds)? {
+            Forked::Child => {
+                if let Err(errno) = chdir(dir) {
+                    eprintln!("crussh: {}: {}", dir.display(), errno.desc());
+                    exit(1);
+                }
+                let errno = execve(&program, &argv, &env).unwrap_err();
+                eprintln!("crussh: {}: {}", argv[0].to_string_lossy(), errno.desc());
+                exit(if errno == Errno::ENOENT { 127 } else { 126 })
+            },
+            Forked::Parent(pid) => Ok(pid),
+        }
+    }
+
+    fn exec(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path) -> Result<i32, ExecError> {
+        let program = to_cstring(program);
+        let argv: Vec<CString> = argv.iter().map(|arg| to_cstring(arg)).collect();
+        let env: Vec<CString> = env.iter()
+            .map(|(name, value)| to_cstring(&format!("{}={}", name, value)))
+            .collect();
+        let _ = io::stdout().flush();
+
+        // The shell keeps its working directory to itself, so the process
+        // only moves there for the program, and back if it does not start.
+        let previous = std::env::current_dir().ok();
+        if let Err(errno) = chdir(dir) {
+            return Err(ExecError::WorkingDirectory { path: dir.to_string_lossy().into_owned(), errno });
+        }
+        // Caught signals go back to the default action by themselves.
+        let ignored = SHIELDED.load(Ordering::SeqCst) | runtime_ignored();
+        set_handlers(ignored, SigHandler::SigDfl);
+        let errno = execve(&program, &argv, &env).unwrap_err();
+        set_handlers(ignored, SigHandler::SigIgn);
+        if let Some(previous) = previous {
+            let _ = chdir(&previous);
+        }
+        let name = argv[0].to_string_lossy().into_owned();
+        Err(if errno == Errno::ENOENT { ExecError::NoSuchFile(name) } else { ExecError::PermissionDenied(name) })
+    }
+
+    fn fork(&mut self, fds: &FdPlan) -> Result<Forked, ExecError> {
+        let _ = io::stdout().flush();
+
+        match unsafe { unistd::fork() } {
+            Ok(ForkResult::Child) => {
+                let reset = CATCHING.swap(0, Ordering::SeqCst) | SHIELDED.swap(0, Ordering::SeqCst) | runtime_ignored();
+                set_handlers(reset, SigHandler::SigDfl);
+                CAUGHT.store(0, Ordering::SeqCst);
+                if let Err(errno) = apply(fds) {
+                    eprintln!("crussh: {}", errno.desc());
+                    exit(1);
+                }
+                Ok(Forked::Child)
+            },
+            Ok(ForkResult::Parent { child }) => Ok(Forked::Parent(child.as_raw() as u32)),
+            Err(errno) => Err(ExecError::Fork(errno)),
+        }
+    }
+
+    fn exit(&mut self, status: i32) -> u32 {
+        exit(status)
+    }
+
+    fn wait(&mut self, pid: u32) -> Result<i32, ExecError> {
+        self.wait_with_usage(pid).map(|(status, _)| status)
+    }
+
+    fn wait_with_usage(&mut self, pid: u32) -> Result<(i32, Usage), ExecError> {
+        loop {
+            let mut status = 0;
+            // SAFETY: `rusage` is plain data that `wait4` fills in.
+            let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+            if unsafe { libc::wait4(pid as libc::pid_t, &mut status, 0, &mut rusage) } == -1 {
+                match Errno::last() {
+                    Errno::EINTR => continue,
+                    errno => return Err(ExecError::Wait(errno)),
+                }
+            }
+            if libc::WIFEXITED(status) {
+                return Ok((libc::WEXITSTATUS(status), usage(&rusage)));
+            }
+            if libc::WIFSIGNALED(status) {
+                return Ok((128 + libc::WTERMSIG(status), usage(&rusage)));
+            }
+        }
+    }
+
+    fn usage(&mut self) -> Usage {
+        // SAFETY: as for `wait4`.
+        let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+        unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut rusage) };
+        usage(&rusage)
+    }
+
+    fn reap(&mut self, pids: &[u32]) -> Vec<(u32, i32)> {
+        let mut finished = Vec::new();
+        for &pid in pids {
+            match waitpid(Pid::from_raw(pid as i32), Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::Exited(_, code)) => finished.push((pid, code)),
+                Ok(WaitStatus::Signaled(_, signal, _)) => finished.push((pid, 128 + signal as i32)),
+                _ => {},
+            }
+        }
+        finished
+    }
+
+    fn kill(&mut self, pid: u32, signal: Option<Signal>, group: bool) -> Result<(), ExecError> {
+        let target = Pid::from_raw(pid as i32);
+        let sent = if group { killpg(target, signal) } else { kill(target, signal) };
+        sent.map_err(|errno| ExecError::Signal { pid, errno })
+    }
+
+    fn set_process_group(&mut self, pid: u32) {
+        let pid = Pid::from_raw(pid as i32);
+        let _ = setpgid(pid, pid);
+    }
+
+    fn communicate(
+        &mut self,
+        pid: u32,
+        fds: [RawFd; 3],
+        input: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<Captured, ExecError> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut open = [true; 3];
+        let transferred = transfer(fds, &mut open, input, deadline);
+        for (&fd, _) in fds.iter().zip(open).filter(|&(_, open)| open) {
+            self.close(fd);
+        }
+        let (stdout, stderr) = transferred?;
+
+        let status = match deadline {
+            Some(deadline) => wait_until(Pid::from_raw(pid as i32), deadline)?,
+            None => Some(self.wait(pid)?),
+        };
+        Ok(Captured {
+            stdout,
+            stderr,
+            status: status.unwrap_or(TIMED_OUT),
+            timed_out: status.is_none(),
+        })
+    }
+
+    fn set_disposition(&mut self, signal: Signal, disposition: Disposition) {
+        let handler = match disposition {
+            Disposition::Default => SigHandler::SigDfl,
+            Disposition::Ignore | Disposition::IgnoreInShell => SigHandler::SigIgn,
+            Disposition::Catch => SigHandler::Handler(catch),
+        };
+        // `SIGKILL` and `SIGSTOP` cannot be changed, and are left alone.
+        let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+        if unsafe { sigaction(signal, &action) }.is_err() {
+            return;
+        }
+        include(&CATCHING, signal, disposition == Disposition::Catch);
+        include(&SHIELDED, signal, disposition == Disposition::IgnoreInShell);
+        include(&IGNORING, signal, disposition == Disposition::Ignore);
+    }
+
+    fn caught_signals(&mut self) -> Vec<Signal> {
+        let caught = CAUGHT.swap(0, Ordering::SeqCst);
+        Signal::iterator().filter(|&signal| caught & (1 << signal as i32) != 0).collect()
+    }
+
+    fn limit(&mut self, resource: Resource) -> Limit {
+        let finite = |limit: Option<libc::rlim_t>| limit.filter(|&limit| limit != libc::RLIM_INFINITY);
+        match resource::getrlimit(resource) {
+            Ok((soft, hard)) => Limit { soft: finite(soft), hard: finite(hard) },
+            Err(_) => Limit::default(),
+        }
+    }
+
+    fn set_limit(&mut self, resource: Resource, limit: Limit) -> Result<(), ExecError> {
+        resource::setrlimit(resource, limit.soft, limit.hard).map_err(ExecError::Limit)
+    }
+
+    fn umask(&mut self) -> u32 {
+        // Reading the mask means setting it, so it is put straight back.
+        let mask = stat::umask(Mode::empty());
+        stat::umask(mask);
+        mask.bits()
+    }
+
+    fn set_umask(&mut self, mask: u32) {
+        stat::umask(Mode::from_bits_truncate(mask));
+    }
+
+    fn stdio(&mut self) -> Io {
+        let _ = io::stdout().flush();
+        Io {
+            stdin: Box::new(Stream(0)),
+            stdout: Box::new(Stream(1)),
+            stderr: Box::new(Stream(2)),
+        }
+    }
+
+    fn redirect(&mut self, fds: &FdPlan) -> Result<SavedFds, ExecError> {
+        let mut saved = SavedFds::default();
+        for &(target, source) in fds.actions() {
+            if !saved.0.iter().any(|&(fd, _)| fd == target) {
+                let copy = fcntl(target, FcntlArg::F_DUPFD_CLOEXEC(FIRST_INTERNAL_FD)).ok();
+                saved.0.push((target, copy));
+            }
+            let applied = match source {
+                Some(source) if source == target => Ok(()),
+                Some(source) => dup2(source, target).map(|_| ()).map_err(|errno| ExecError::BadFd { fd: source, errno }),
+                None => {
+                    let _ = close(target);
+                    Ok(())
+                }
+            };
+            if let Err(e) = applied {
+                self.restore(saved);
+                return Err(e);
+            }
+        }
+        Ok(saved)
+    }
+
+    fn restore(&mut self, saved: SavedFds) {
+        let _ = io::stdout().flush();
+        for (target, copy) in saved.0.into_iter().rev() {
+            match copy {
+                Some(copy) => {
+                    let _ = dup2(copy, target);
+                    let _ = close(copy);
+                },
+                None => {
+                    let _ = close(target);
+                }
+            }
+        }
+    }
+}
+
+/// Feeds `input` to the first pipe and reads the other two until they are
+/// closed or `deadline` passes, closing each descriptor in `open` once it
+/// is done with.
+fn transfer(
+    fds: [RawFd; 3],
+    open: &mut [bool; 3],
+    input: &[u8],
+    deadline: Option<Instant>,
+) -> Result<(Vec<u8>, Vec<u8>), ExecError> {
+    for &fd in &fds {
+        fcntl(fd, FcntlArg::F_SETFL(OFlag::O_NONBLOCK)).map_err(ExecError::Pipe)?;
+    }
+
+    let mut written = 0;
+    let mut outputs = (Vec::new(), Vec::new());
+    let mut buf = [0; 8192];
+    if input.is_empty() {
+        let _ = close(fds[0]);
+        open[0] = false;
+    }
+
+    while open.iter().any(|&open| open) {
+        let remaining = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) => remaining.as_millis().min(i32::MAX as u128) as i32,
+                None => break,
+            },
+            None => -1,
+        };
+
+        let watched: Vec<usize> = (0..3).filter(|&i| open[i]).collect();
+        let mut poll_fds: Vec<PollFd> = watched.iter()
+            .map(|&i| PollFd::new(fds[i], if i == 0 { PollFlags::POLLOUT } else { PollFlags::POLLIN }))
+            .collect();
+        match poll(&mut poll_fds, remaining) {
+            Ok(_) | Err(Errno::EINTR) => {},
+            Err(errno) => return Err(ExecError::Pipe(errno)),
+        }
+
+        for (&i, poll_fd) in watched.iter().zip(&poll_fds) {
+            if poll_fd.revents().is_none_or(|events| events.is_empty()) {
+                continue;
+            }
+            let result = if i == 0 {
+                unistd::write(fds[0], &input[written..]).map(|n| {
+                    written += n;
+                    written < input.len()
+                })
+            } else {
+                unistd::read(fds[i], &mut buf).map(|n| {
+                    let output = if i == 1 { &mut outputs.0 } else { &mut outputs.1 };
+                    output.extend_from_slice(&buf[..n]);
+                    n > 0
+                })
+            };
+            match result {
+                Ok(more) => open[i] = more,
+                Err(Errno::EAGAIN) | Err(Errno::EINTR) => {},
+                // The reader went away without taking all the input.
+                Err(Errno::EPIPE) if i == 0 => open[i] = false,
+                Err(errno) => return Err(ExecError::Pipe(errno)),
+            }
+            if !open[i] {
+                let _ = close(fds[i]);
+            }
+        }
+    }
+    Ok(outputs)
+}
+
+/// Waits for `pid` until `deadline`, then kills its process group and
+/// returns `None`.
+fn wait_until(pid: Pid, deadline: Instant) -> Result<Option<i32>, ExecError> {
+    loop {
+        match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(_, code)) => return Ok(Some(code)),
+            Ok(WaitStatus::Signaled(_, signal, _)) => return Ok(Some(128 + signal as i32)),
+            Ok(_) | Err(Errno::EINTR) => {},
+            Err(errno) => return Err(ExecError::Wait(errno)),
+        }
+        if Instant::now() >= deadline {
+            if killpg(pid, Signal::SIGKILL).is_err() {
+                let _ = kill(pid, Signal::SIGKILL);
+            }
+            let _ = waitpid(pid, None);
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn usage(rusage: &libc::rusage) -> Usage {
+    let duration = |time: libc::timeval| Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000);
+    // Linux counts the resident set in kilobytes, macOS in bytes.
+    let max_rss = if cfg!(target_os = "macos") { rusage.ru_maxrss / 1024 } else { rusage.ru_maxrss };
+    Usage {
+        user: duration(rusage.ru_utime),
+        system: duration(rusage.ru_stime),
+        max_rss: max_rss as u64,
+        voluntary_switches: rusage.ru_nvcsw as u64,
+        involuntary_switches: rusage.ru_nivcsw as u64,
+    }
+}
+
+/// An unbuffered standard stream, so that a builtin reads no more input
+/// than it uses and its output is in place before the next command runs.
+struct Stream(RawFd);
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match unistd::read(self.0, buf) {
+                Err(Errno::EINTR) => continue,
+                read => return read.map_err(|errno| io::Error::from_raw_os_error(errno as i32)),
+            }
+        }
+    }
+}
+
+impl Input for Stream {
+    fn ready(&mut self, timeout: Duration) -> io::Result<bool> {
+        let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+        let mut fds = [PollFd::new(self.0, PollFlags::POLLIN)];
+        loop {
+            match poll(&mut fds, timeout) {
+                Err(Errno::EINTR) => continue,
+                polled => return polled.map(|n| n > 0).map_err(|errno| io::Error::from_raw_os_error(errno as i32)),
+            }
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        isatty(self.0).unwrap_or(false)
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        loop {
+            match unistd::write(self.0, buf) {
+                Err(Errno::EINTR) => continue,
+                written => return written.map_err(|errno| io::Error::from_raw_os_error(errno as i32)),
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Applies `plan` to the current process; used in children.
+fn apply(plan: &FdPlan) -> Result<(), Errno> {
+    for &(target, source) in plan.actions() {
+        match source {
+            Some(source) if source == target => {
+                fcntl(target, FcntlArg::F_SETFD(FdFlag::empty()))?;
+            },
+            Some(source) => {
+                dup2(source, target)?;
+            },
+            None => {
+                let _ = close(target);
+            }
+        }
+    }
+    for &fd in plan.closes() {
+        if !plan.actions().iter().any(|&(target, _)| target == fd) {
+            let _ = close(fd);
+        }
+    }
+    Ok(())
+}
+
+extern "C" fn catch(signal: libc::c_int) {
+    CAUGHT.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// Adds `signal` to or removes it from `set`.
+fn include(set: &AtomicU64, signal: Signal, member: bool) {
+    let bit = 1 << signal as i32;
+    if member {
+        set.fetch_or(bit, Ordering::SeqCst);
+    } else {
+        set.fetch_and(!bit, Ordering::SeqCst);
+    }
+}
+
+/// Installs `handler` for each signal in the set `signals`.
+fn set_handlers(signals: u64, handler: SigHandler) {
+    for signal in Signal::iterator().filter(|&signal| signals & (1 << signal as i32) != 0) {
+        let _ = unsafe { signal::signal(signal, handler) };
+    }
+}
+
+/// The signals the Rust runtime ignores before `main`, `SIGPIPE`, unless
+/// the shell ignores them itself. Children and programs get the default
+/// action back, so that a writer into a closed pipe dies quietly.
+fn runtime_ignored() -> u64 {
+    (1 << Signal::SIGPIPE as i32) & !IGNORING.load(Ordering::SeqCst)
+}
+
+fn exit(status: i32) -> ! {
+    let _ = io::stdout().flush();
+    unsafe { libc::_exit(status) }
+}
+
+/// Moves `fd` out of the range users redirect and marks it close-on-exec.
+fn internal_fd(fd: RawFd) -> Result<RawFd, Errno> {
+    let moved = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(FIRST_INTERNAL_FD));
+    let _ = close(fd);
+    moved
+}
+
+fn check_executable(path: &Path, name: &str) -> Result<(), ExecError> {
+    if !path.exists() {
+        return Err(ExecError::NoSuchFile(name.to_string()));
+    }
+    if path.is_dir() {
+        return Err(ExecError::IsADirectory(name.to_string()));
+    }
+    access(path, AccessFlags::X_OK)
+        .map_err(|_| ExecError::PermissionDenied(name.to_string()))
+}
+
+fn to_cstring(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::errno::Errno;
+
+    use crate::{Error, ExecError, Shell};
+
+    #[test]
+    fn names_a_closed_descriptor() {
+        let mut shell = Shell::new();
+        let e = shell.run("echo x >&5").unwrap_err();
+        assert_eq!(e, Error::Exec(ExecError::BadFd { fd: 5, errno: Errno::EBADF }));
+    }
+
+    #[test]
+    fn lets_writers_into_closed_pipes_die() {
+        let mut shell = Shell::new();
+        let captured = shell.capture("{ yes; echo $? >&2; } | head -n 1; { sleep 0.2; echo x; echo not reached >&2; } | true").unwrap();
+        assert_eq!(captured.stdout_lossy(), "y\n");
+        assert_eq!(captured.stderr_lossy(), "141\n");
+    }
+}
@@ -0,0 +1,456 @@
+//! An executor that records commands instead of running them.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{self, Read, Write};
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
+
+use nix::sys::resource::Resource;
+use nix::sys::signal::Signal;
+
+use crate::{Captured, ExecError, Input, Io};
+use crate::ast::RedirectOp;
+use crate::exec::{Disposition, Executor, FdPlan, Forked, Limit, SavedFds, Usage};
+
+/// What a descriptor of a recorded process refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fd {
+    /// The shell's own descriptor with this number.
+    Inherited(RawFd),
+    /// A file opened for a redirection.
+    File { path: String, op: RedirectOp },
+    /// The read end of the pipe with this index, counting from 0.
+    PipeRead(usize),
+    /// The write end of the pipe with this index.
+    PipeWrite(usize),
+    Closed,
+}
+
+/// A program the evaluator asked to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spawned {
+    pub pid: u32,
+    pub program: String,
+    pub argv: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub dir: PathBuf,
+    /// The descriptors below 10 that differ from the shell's.
+    pub fds: BTreeMap<RawFd, Fd>,
+}
+
+impl Spawned {
+    /// The value of `name` in the program's environment.
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.env.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str())
+    }
+}
+
+/// An [`Executor`] that starts no processes. Programs are recorded as
+/// [`Spawned`] and exit with 0 unless told otherwise; forked copies of the
+/// shell run in place, so changes they make to shell state are kept. A
+/// program the shell `exec`s is recorded the same way, and the shell
+/// carries on after it. Signals only arrive through
+/// [`raise`](Recorder::raise).
+///
+/// Clones share their records, so keep one to inspect after handing
+/// another to the shell.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder(Rc<RefCell<State>>);
+
+#[derive(Debug, Default)]
+struct State {
+    spawned: Vec<Spawned>,
+    statuses: HashMap<String, i32>,
+    missing: HashSet<String>,
+    outputs: HashMap<String, Vec<u8>>,
+    usages: HashMap<String, Usage>,
+    /// What has been written to each pipe and not yet read.
+    buffered: HashMap<usize, Vec<u8>>,
+    /// What builtins wrote to descriptors other than pipes.
+    written: Vec<(Fd, Vec<u8>)>,
+    /// Descriptors handed out by `open` and `pipe`.
+    open: HashMap<RawFd, Fd>,
+    next_fd: RawFd,
+    pipes: usize,
+    /// The descriptor table of the shell, then of each forked child that
+    /// is still running.
+    tables: Vec<BTreeMap<RawFd, Fd>>,
+    children: Vec<u32>,
+    next_pid: u32,
+    exited: HashMap<u32, i32>,
+    used: HashMap<u32, Usage>,
+    dispositions: HashMap<Signal, Disposition>,
+    caught: Vec<Signal>,
+    killed: Vec<(u32, Option<Signal>)>,
+    limits: HashMap<Resource, Limit>,
+    /// The umask, 022 until it is set.
+    umask: Option<u32>,
+}
+
+const FIRST_FD: RawFd = 10;
+const FIRST_PID: u32 = 1000;
+
+impl Recorder {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes the program named `name` exit with `status`.
+    pub fn with_status(self, name: &str, status: i32) -> Self {
+        self.0.borrow_mut().statuses.insert(name.to_string(), status);
+        self
+    }
+
+    /// Makes the program named `name` write `output` to its standard
+    /// output, which [`Shell::capture`](crate::Shell::capture) collects if
+    /// it is a pipe.
+    pub fn with_output(self, name: &str, output: &[u8]) -> Self {
+        self.0.borrow_mut().outputs.insert(name.to_string(), output.to_vec());
+        self
+    }
+
+    /// Makes the program named `name` report using `usage` when it is
+    /// waited for.
+    pub fn with_usage(self, name: &str, usage: Usage) -> Self {
+        self.0.borrow_mut().usages.insert(name.to_string(), usage);
+        self
+    }
+
+    /// Makes looking up `name` fail as if it were not installed.
+    pub fn without_program(self, name: &str) -> Self {
+        self.0.borrow_mut().missing.insert(name.to_string());
+        self
+    }
+
+    /// The programs started so far, in order.
+    pub fn spawned(&self) -> Vec<Spawned> {
+        self.0.borrow().spawned.clone()
+    }
+
+    /// What builtins wrote to `fd`, which is not a pipe; the terminal is
+    /// `Fd::Inherited(1)`.
+    pub fn written(&self, fd: &Fd) -> Vec<u8> {
+        self.0.borrow().written.iter()
+            .filter(|(written_to, _)| written_to == fd)
+            .flat_map(|(_, bytes)| bytes.iter().copied())
+            .collect()
+    }
+
+    /// The descriptors handed out by `open` and `pipe` that are still open.
+    pub fn open_fds(&self) -> Vec<Fd> {
+        self.0.borrow().open.values().cloned().collect()
+    }
+
+    /// What the shell last set for `signal`.
+    pub fn disposition(&self, signal: Signal) -> Disposition {
+        self.0.borrow().dispositions.get(&signal).copied().unwrap_or(Disposition::Default)
+    }
+
+    /// Sends `signal` to the shell, which only notices it if it catches
+    /// it.
+    pub fn raise(&self, signal: Signal) {
+        let mut state = self.0.borrow_mut();
+        if state.dispositions.get(&signal) == Some(&Disposition::Catch) && !state.caught.contains(&signal) {
+            state.caught.push(signal);
+        }
+    }
+
+    /// The signals sent to processes and process groups so far, by process
+    /// id or group leader.
+    pub fn killed(&self) -> Vec<(u32, Option<Signal>)> {
+        self.0.borrow().killed.clone()
+    }
+
+    /// The argument lists of the programs started so far.
+    pub fn argvs(&self) -> Vec<Vec<String>> {
+        self.0.borrow().spawned.iter().map(|s| s.argv.clone()).collect()
+    }
+}
+
+impl State {
+
+    fn table(&mut self) -> &mut BTreeMap<RawFd, Fd> {
+        if self.tables.is_empty() {
+            self.tables.push(BTreeMap::new());
+        }
+        self.tables.last_mut().unwrap()
+    }
+
+    /// The current table with `plan` applied.
+    fn planned(&mut self, plan: &FdPlan) -> BTreeMap<RawFd, Fd> {
+        let mut table = self.table().clone();
+        for &(target, source) in plan.actions() {
+            let description = match source {
+                Some(source) if source == target => continue,
+                Some(source) => match self.open.get(&source) {
+                    Some(description) => description.clone(),
+                    None => table.get(&source).cloned().unwrap_or(Fd::Inherited(source)),
+                },
+                None => Fd::Closed,
+            };
+            if description == Fd::Inherited(target) {
+                table.remove(&target);
+            } else {
+                table.insert(target, description);
+            }
+        }
+        table
+    }
+
+    fn allocate_fd(&mut self, description: Fd) -> RawFd {
+        let fd = FIRST_FD + self.next_fd;
+        self.next_fd += 1;
+        self.open.insert(fd, description);
+        fd
+    }
+
+    fn allocate_pid(&mut self) -> u32 {
+        self.next_pid += 1;
+        FIRST_PID + self.next_pid
+    }
+}
+
+impl Executor for Recorder {
+
+    fn find_program(&mut self, name: &str, _path: &str, _dir: &Path) -> Result<String, ExecError> {
+        if self.0.borrow().missing.contains(name) {
+            Err(ExecError::CommandNotFound(name.to_string()))
+        } else {
+            Ok(name.to_string())
+        }
+    }
+
+    fn open(&mut self, _dir: &Path, path: &str, op: RedirectOp) -> Result<RawFd, ExecError> {
+        Ok(self.0.borrow_mut().allocate_fd(Fd::File { path: path.to_string(), op }))
+    }
+
+    fn pipe(&mut self) -> Result<(RawFd, RawFd), ExecError> {
+        let mut state = self.0.borrow_mut();
+        let pipe = state.pipes;
+        state.pipes += 1;
+        Ok((state.allocate_fd(Fd::PipeRead(pipe)), state.allocate_fd(Fd::PipeWrite(pipe))))
+    }
+
+    fn close(&mut self, fd: RawFd) {
+        self.0.borrow_mut().open.remove(&fd);
+    }
+
+    fn spawn(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path, fds: &FdPlan) -> Result<u32, ExecError> {
+        let mut state = self.0.borrow_mut();
+        let pid = state.allocate_pid();
+        let status = state.statuses.get(&argv[0]).copied().unwrap_or(0);
+        state.exited.insert(pid, status);
+        if let Some(&usage) = state.usages.get(&argv[0]) {
+            state.used.insert(pid, usage);
+        }
+
+        let fds: BTreeMap<RawFd, Fd> = state.planned(fds).into_iter().filter(|&(fd, _)| fd < FIRST_FD).collect();
+        if let (Some(output), Some(&Fd::PipeWrite(pipe))) = (state.outputs.get(&argv[0]).cloned(), fds.get(&1)) {
+            state.buffered.entry(pipe).or_default().extend(output);
+        }
+        state.spawned.push(Spawned {
+            pid,
+            program: program.to_string(),
+            argv: argv.to_vec(),
+            env: env.to_vec(),
+            dir: dir.to_path_buf(),
+            fds,
+        });
+        Ok(pid)
+    }
+
+    fn exec(&mut self, program: &str, argv: &[String], env: &[(String, String)], dir: &Path) -> Result<i32, ExecError> {
+        let mut state = self.0.borrow_mut();
+        let pid = state.allocate_pid();
+        let fds: BTreeMap<RawFd, Fd> = state.table().iter()
+            .filter(|&(&fd, _)| fd < FIRST_FD)
+            .map(|(&fd, description)| (fd, description.clone()))
+            .collect();
+        state.spawned.push(Spawned {
+            pid,
+            program: program.to_string(),
+            argv: argv.to_vec(),
+            env: env.to_vec(),
+            dir: dir.to_path_buf(),
+            fds,
+        });
+        Ok(state.statuses.get(&argv[0]).copied().unwrap_or(0))
+    }
+
+    fn fork(&mut self, fds: &FdPlan) -> Result<Forked, ExecError> {
+        let mut state = self.0.borrow_mut();
+        let table = state.planned(fds);
+        let pid = state.allocate_pid();
+        state.tables.push(table);
+        state.children.push(pid);
+        Ok(Forked::Child)
+    }
+
+    fn exit(&mut self, status: i32) -> u32 {
+        let mut state = self.0.borrow_mut();
+        state.tables.pop();
+        let pid = state.children.pop().expect("exit outside a forked child");
+        state.exited.insert(pid, status);
+        pid
+    }
+
+    fn wait(&mut self, pid: u32) -> Result<i32, ExecError> {
+        self.wait_with_usage(pid).map(|(status, _)| status)
+    }
+
+    fn wait_with_usage(&mut self, pid: u32) -> Result<(i32, Usage), ExecError> {
+        let mut state = self.0.borrow_mut();
+        let usage = state.used.remove(&pid).unwrap_or_default();
+        Ok((state.exited.remove(&pid).unwrap_or(0), usage))
+    }
+
+    fn kill(&mut self, pid: u32, signal: Option<Signal>, _group: bool) -> Result<(), ExecError> {
+        self.0.borrow_mut().killed.push((pid, signal));
+        Ok(())
+    }
+
+    fn reap(&mut self, pids: &[u32]) -> Vec<(u32, i32)> {
+        let mut state = self.0.borrow_mut();
+        pids.iter().filter_map(|&pid| state.exited.remove(&pid).map(|status| (pid, status))).collect()
+    }
+
+    fn communicate(
+        &mut self,
+        pid: u32,
+        fds: [RawFd; 3],
+        _input: &[u8],
+        _timeout: Option<Duration>,
+    ) -> Result<Captured, ExecError> {
+        let mut state = self.0.borrow_mut();
+        state.open.remove(&fds[0]);
+        let mut read = |fd| match state.open.remove(&fd) {
+            Some(Fd::PipeRead(pipe)) => state.buffered.remove(&pipe).unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let (stdout, stderr) = (read(fds[1]), read(fds[2]));
+        let status = state.exited.remove(&pid).unwrap_or(0);
+        Ok(Captured { stdout, stderr, status, timed_out: false })
+    }
+
+    fn set_disposition(&mut self, signal: Signal, disposition: Disposition) {
+        self.0.borrow_mut().dispositions.insert(signal, disposition);
+    }
+
+    fn caught_signals(&mut self) -> Vec<Signal> {
+        let mut caught = std::mem::take(&mut self.0.borrow_mut().caught);
+        caught.sort();
+        caught
+    }
+
+    fn limit(&mut self, resource: Resource) -> Limit {
+        self.0.borrow().limits.get(&resource).copied().unwrap_or_default()
+    }
+
+    /// Fails like an unprivileged process would: raising the hard limit
+    /// is not permitted, and the soft limit cannot exceed it.
+    fn set_limit(&mut self, resource: Resource, limit: Limit) -> Result<(), ExecError> {
+        let mut state = self.0.borrow_mut();
+        let current = state.limits.get(&resource).copied().unwrap_or_default();
+        let above = |limit: Option<u64>, ceiling: Option<u64>| ceiling.is_some_and(|ceiling| limit.is_none_or(|limit| limit > ceiling));
+        if above(limit.hard, current.hard) {
+            return Err(ExecError::Limit(nix::errno::Errno::EPERM));
+        }
+        if above(limit.soft, limit.hard) {
+            return Err(ExecError::Limit(nix::errno::Errno::EINVAL));
+        }
+        state.limits.insert(resource, limit);
+        Ok(())
+    }
+
+    fn umask(&mut self) -> u32 {
+        self.0.borrow().umask.unwrap_or(0o022)
+    }
+
+    fn set_umask(&mut self, mask: u32) {
+        self.0.borrow_mut().umask = Some(mask);
+    }
+
+    fn stdio(&mut self) -> Io {
+        let mut state = self.0.borrow_mut();
+        let mut stream = |fd| Stream {
+            fd: state.table().get(&fd).cloned().unwrap_or(Fd::Inherited(fd)),
+            state: Rc::clone(&self.0),
+        };
+        Io {
+            stdin: Box::new(stream(0)),
+            stdout: Box::new(stream(1)),
+            stderr: Box::new(stream(2)),
+        }
+    }
+
+    fn redirect(&mut self, fds: &FdPlan) -> Result<SavedFds, ExecError> {
+        let mut state = self.0.borrow_mut();
+        let table = state.planned(fds);
+
+        // Each replaced descriptor is saved under a new one, as `dup` would.
+        let mut saved = SavedFds::default();
+        for &(target, _) in fds.actions() {
+            if !saved.0.iter().any(|&(fd, _)| fd == target) {
+                let previous = state.table().get(&target).cloned().unwrap_or(Fd::Inherited(target));
+                let copy = state.allocate_fd(previous);
+                saved.0.push((target, Some(copy)));
+            }
+        }
+        *state.table() = table;
+        Ok(saved)
+    }
+
+    fn restore(&mut self, saved: SavedFds) {
+        let mut state = self.0.borrow_mut();
+        for (target, copy) in saved.0.into_iter().rev() {
+            match copy.and_then(|copy| state.open.remove(&copy)) {
+                Some(Fd::Inherited(fd)) if fd == target => state.table().remove(&target),
+                Some(previous) => state.table().insert(target, previous),
+                None => state.table().remove(&target),
+            };
+        }
+    }
+}
+
+/// A builtin's view of one of its descriptors: pipes carry data from one
+/// stage to the next, anything else reads as empty.
+struct Stream {
+    fd: Fd,
+    state: Rc<RefCell<State>>,
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.borrow_mut();
+        let pending = match self.fd {
+            Fd::PipeRead(pipe) => state.buffered.entry(pipe).or_default(),
+            _ => return Ok(0),
+        };
+        let n = buf.len().min(pending.len());
+        buf[..n].copy_from_slice(&pending[..n]);
+        pending.drain(..n);
+        Ok(n)
+    }
+}
+
+impl Input for Stream {}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.borrow_mut();
+        match self.fd {
+            Fd::PipeWrite(pipe) => state.buffered.entry(pipe).or_default().extend_from_slice(buf),
+            Fd::Closed => return Err(io::Error::from_raw_os_error(nix::errno::Errno::EBADF as i32)),
+            _ => state.written.push((self.fd.clone(), buf.to_vec())),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
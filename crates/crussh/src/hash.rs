@@ -0,0 +1,106 @@
+//! The table of programs found on `PATH`, so that each is searched for
+//! only once, as bash's `hash` remembers them.
+
+use std::collections::BTreeMap;
+
+use crate::{ExecError, Shell};
+
+/// The programs found for one value of `PATH`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HashTable {
+    /// The `PATH` the programs were found on; when it changes the table is
+    /// emptied.
+    path: String,
+    programs: BTreeMap<String, Hashed>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Hashed {
+    pub(crate) path: String,
+    /// How many times the program has been run through the table.
+    pub(crate) hits: u32,
+}
+
+impl Shell {
+    /// Finds the program to run for `name`, from the table or by searching
+    /// `PATH` and remembering the result.
+    pub(crate) fn find_program(&mut self, name: &str) -> Result<String, ExecError> {
+        let path = self.var("PATH").unwrap_or_default().to_string();
+        if name.contains('/') {
+            return self.executor.find_program(name, &path, &self.cwd);
+        }
+
+        if let Some(hashed) = self.hashed_mut(name) {
+            hashed.hits += 1;
+            return Ok(hashed.path.clone());
+        }
+        let program = self.executor.find_program(name, &path, &self.cwd)?;
+        self.hash.programs.insert(name.to_string(), Hashed { path: program.clone(), hits: 1 });
+        Ok(program)
+    }
+
+    /// What [`find_program`](Shell::find_program) would run, without
+    /// adding it to the table.
+    pub(crate) fn lookup_program(&mut self, name: &str) -> Result<String, ExecError> {
+        if let Some(hashed) = self.hashed(name) {
+            return Ok(hashed.path.clone());
+        }
+        let path = self.var("PATH").unwrap_or_default().to_string();
+        self.executor.find_program(name, &path, &self.cwd)
+    }
+
+    /// Searches `PATH` for `name` and adds it to the table, as `hash name`
+    /// does.
+    pub(crate) fn rehash(&mut self, name: &str) -> Result<(), ExecError> {
+        self.check_hash();
+        let path = self.var("PATH").unwrap_or_default().to_string();
+        let program = self.executor.find_program(name, &path, &self.cwd)?;
+        self.hash.programs.insert(name.to_string(), Hashed { path: program, hits: 0 });
+        Ok(())
+    }
+
+    /// The table entry for `name`, if the table is still for the current
+    /// `PATH`.
+    pub(crate) fn hashed(&mut self, name: &str) -> Option<&Hashed> {
+        self.hashed_mut(name).map(|hashed| &*hashed)
+    }
+
+    /// Every program in the table, by name.
+    pub(crate) fn hashed_programs(&mut self) -> &mut BTreeMap<String, Hashed> {
+        self.check_hash();
+        &mut self.hash.programs
+    }
+
+    fn hashed_mut(&mut self, name: &str) -> Option<&mut Hashed> {
+        self.check_hash();
+        self.hash.programs.get_mut(name)
+    }
+
+    /// Empties the table if `PATH` has changed since it was filled.
+    fn check_hash(&mut self) {
+        let path = self.var("PATH").unwrap_or_default();
+        if self.hash.path != path {
+            self.hash.path = path.to_string();
+            self.hash.programs.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn remembers_programs_until_path_changes() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_var("PATH", "/bin");
+        shell.run("ls; ls; cat").unwrap();
+        assert_eq!(shell.hashed("ls").map(|hashed| hashed.hits), Some(2));
+        assert_eq!(shell.hashed("cat").map(|hashed| hashed.hits), Some(1));
+
+        shell.set_var("PATH", "/usr/bin:/bin");
+        assert_eq!(shell.hashed("ls"), None);
+        assert!(shell.hashed_programs().is_empty());
+    }
+}
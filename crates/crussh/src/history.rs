@@ -0,0 +1,686 @@
+//! Command history, the history file and bash's `!` history expansion.
+
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nix::fcntl::{flock, FlockArg};
+
+use crate::Shell;
+use crate::error::HistoryError;
+
+/// A command line in the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub line: String,
+    /// When it was entered, in seconds since the Unix epoch.
+    pub time: Option<u64>,
+    /// The exit status of its commands, once they have run.
+    pub status: Option<i32>,
+}
+
+/// The command lines entered so far, oldest first. Entries are numbered
+/// from 1, and keep their numbers when older ones are dropped to stay
+/// within `$HISTSIZE`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    /// How many entries have been dropped from the front.
+    base: usize,
+    /// How many of the first entries the history file already has; the
+    /// rest are appended to it.
+    saved: usize,
+}
+
+/// Which words of an event a designator such as `:2-$` picks.
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    Word(usize),
+    Last,
+    /// The word before the last one, where `2-` ends.
+    BeforeLast,
+}
+
+impl History {
+
+    /// Adds a command line entered now, without its trailing newline.
+    /// Blank lines are not kept.
+    pub fn add(&mut self, line: &str) {
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+        self.push(HistoryEntry { line: line.to_string(), time: Some(time), status: None });
+    }
+
+    /// Adds an entry, unless its line is blank.
+    pub fn push(&mut self, mut entry: HistoryEntry) {
+        let len = entry.line.trim_end_matches('\n').len();
+        entry.line.truncate(len);
+        if !entry.line.trim().is_empty() {
+            self.entries.push(entry);
+        }
+    }
+
+    /// The line of the entry numbered `number`.
+    pub fn get(&self, number: usize) -> Option<&str> {
+        self.entry(number).map(|entry| entry.line.as_str())
+    }
+
+    pub fn entry(&self, number: usize) -> Option<&HistoryEntry> {
+        self.index(number).map(|i| &self.entries[i])
+    }
+
+    /// The entries with their numbers, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &HistoryEntry)> {
+        let base = self.base;
+        self.entries.iter().enumerate().map(move |(i, entry)| (base + i + 1, entry))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.base = 0;
+        self.saved = 0;
+    }
+
+    /// Deletes the entry numbered `number`; the later ones move down.
+    pub fn remove(&mut self, number: usize) -> bool {
+        match self.index(number) {
+            Some(i) => {
+                self.entries.remove(i);
+                if i < self.saved {
+                    self.saved -= 1;
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The number of the last entry, or of the one before the first if
+    /// there are none.
+    pub fn last_number(&self) -> usize {
+        self.base + self.entries.len()
+    }
+
+    /// Records the exit status of the last entry, if it has not been saved
+    /// yet.
+    pub fn set_status(&mut self, status: i32) {
+        if self.entries.len() > self.saved {
+            if let Some(entry) = self.entries.last_mut() {
+                entry.status = Some(status);
+            }
+        }
+    }
+
+    /// Drops the oldest entries until at most `size` are left.
+    pub fn truncate(&mut self, size: usize) {
+        let dropped = self.entries.len().saturating_sub(size);
+        self.entries.drain(..dropped);
+        self.base += dropped;
+        self.saved = self.saved.saturating_sub(dropped);
+    }
+
+    fn index(&self, number: usize) -> Option<usize> {
+        number.checked_sub(self.base + 1).filter(|&i| i < self.entries.len())
+    }
+
+    /// Adds the entries of a history file, before any that are not in the
+    /// file yet.
+    pub fn read(&mut self, path: &Path) -> io::Result<()> {
+        let mut file = File::open(path)?;
+        lock(&file, FlockArg::LockShared)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let entries: Vec<HistoryEntry> = parse(&contents).into_iter().filter(|entry| !entry.line.trim().is_empty()).collect();
+        let count = entries.len();
+        self.entries.splice(self.saved..self.saved, entries);
+        self.saved += count;
+        Ok(())
+    }
+
+    /// Replaces the contents of a history file with the entries.
+    pub fn write(&mut self, path: &Path) -> io::Result<()> {
+        // Emptied only once locked, in case another shell is using it.
+        let mut file = OpenOptions::new().write(true).create(true).truncate(false).open(path)?;
+        lock(&file, FlockArg::LockExclusive)?;
+        file.set_len(0)?;
+        file.write_all(format(&self.entries).as_bytes())?;
+        self.saved = self.entries.len();
+        Ok(())
+    }
+
+    /// Appends the entries the history file does not have yet to it, and
+    /// then drops the oldest ones in the file beyond `size`. The file is
+    /// locked meanwhile, so shells that share it add to it in turn rather
+    /// than overwrite each other's entries.
+    pub fn append(&mut self, path: &Path, size: Option<usize>) -> io::Result<()> {
+        let new = &self.entries[self.saved..];
+        if new.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path)?;
+        lock(&file, FlockArg::LockExclusive)?;
+        file.write_all(format(new).as_bytes())?;
+        self.saved = self.entries.len();
+
+        if let Some(size) = size {
+            let mut contents = String::new();
+            file.seek(SeekFrom::Start(0))?;
+            file.read_to_string(&mut contents)?;
+            let entries = parse(&contents);
+            if entries.len() > size {
+                file.set_len(0)?;
+                file.write_all(format(&entries[entries.len() - size..]).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Expands the history references in `line`, as bash does before it
+    /// parses a line read from the terminal: `!!`, `!n`, `!-n`, `!prefix`
+    /// and `!?text?` stand for earlier lines, optionally followed by word
+    /// designators such as `:2`, `^`, `$` and `*` and by the modifiers
+    /// `:h`, `:t`, `:r`, `:e` and `:s/old/new/`. A line starting with
+    /// `^old^new` repeats the last one with `old` replaced. Nothing is
+    /// expanded in single quotes, after a backslash, or where `!` is
+    /// followed by a blank, `=` or `(`.
+    ///
+    /// Returns `None` if the line refers to no history, and the expanded
+    /// line otherwise, which is the one to run, show and remember.
+    pub fn expand(&self, line: &str) -> Result<Option<String>, HistoryError> {
+        let chars: Vec<char> = line.chars().collect();
+        let mut out = String::new();
+        let mut i = 0;
+        if chars.first() == Some(&'^') {
+            let (replaced, next) = substitute(self.previous("!!")?, &chars, 0, false)?;
+            out.push_str(&replaced);
+            i = next;
+        }
+
+        let (mut single, mut double) = (false, false);
+        let mut expanded = i > 0;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '\\' if !single => {
+                    out.push(c);
+                    out.extend(chars.get(i + 1));
+                    i += 2;
+                    continue;
+                },
+                '\'' if !double => single = !single,
+                '"' if !single => double = !double,
+                '!' if !single => {
+                    let next = chars.get(i + 1).copied();
+                    let literal = match next {
+                        None | Some(' ') | Some('\t') | Some('\n') | Some('=') | Some('(') => true,
+                        Some('"') => double,
+                        _ => false,
+                    };
+                    if !literal {
+                        let (text, next) = self.event(&chars, i + 1)?;
+                        out.push_str(&text);
+                        expanded = true;
+                        i = next;
+                        continue;
+                    }
+                },
+                _ => {},
+            }
+            out.push(c);
+            i += 1;
+        }
+        Ok(if expanded { Some(out) } else { None })
+    }
+
+    /// The text of the event starting at `chars[start]`, just after a `!`,
+    /// and the index after it.
+    fn event(&self, chars: &[char], start: usize) -> Result<(String, usize), HistoryError> {
+        let text_until = |from: usize, end: &dyn Fn(char) -> bool| {
+            let len = chars[from..].iter().position(|&c| end(c)).unwrap_or(chars.len() - from);
+            (chars[from..from + len].iter().collect::<String>(), from + len)
+        };
+
+        // Whether a word designator may follow without a `:`.
+        let mut bare_designator = true;
+        let (line, mut i) = match chars[start] {
+            '!' => (self.previous("!!")?, start + 1),
+            // `!$` and the like are short for `!!$`.
+            '$' | '^' | '*' | ':' => (self.previous("!")?, start),
+            c if c.is_ascii_digit() || c == '-' => {
+                let (number, next) = text_until(start + 1, &|c| !c.is_ascii_digit());
+                let number = format!("{}{}", c, number);
+                let event = || HistoryError::EventNotFound(format!("!{}", number));
+                let entry = match number.strip_prefix('-') {
+                    Some(back) => back.parse::<usize>().ok().and_then(|back| (self.last_number() + 1).checked_sub(back)),
+                    None => number.parse::<usize>().ok(),
+                };
+                (entry.and_then(|entry| self.get(entry)).ok_or_else(event)?, next)
+            },
+            '?' => {
+                let (text, next) = text_until(start + 1, &|c| c == '?' || c == '\n');
+                let next = if chars.get(next) == Some(&'?') { next + 1 } else { next };
+                let found = self.entries.iter().rev().map(|entry| entry.line.as_str()).find(|line| line.contains(&text));
+                (found.ok_or_else(|| HistoryError::EventNotFound(format!("!?{}", text)))?, next)
+            },
+            _ => {
+                bare_designator = false;
+                let (prefix, next) = text_until(start, &|c| c.is_whitespace() || ":;&|()<>\"'".contains(c));
+                let found = self.entries.iter().rev().map(|entry| entry.line.as_str()).find(|line| line.starts_with(&prefix));
+                (found.ok_or_else(|| HistoryError::EventNotFound(format!("!{}", prefix)))?, next)
+            },
+        };
+
+        // A word designator, after a `:` unless it starts with one of
+        // `^$*-`.
+        let designator_start = |c: Option<&char>| matches!(c, Some(c) if c.is_ascii_digit() || "^$*-".contains(*c));
+        let mut text = line.to_string();
+        let colon = chars.get(i) == Some(&':') && designator_start(chars.get(i + 1));
+        if colon || (bare_designator && matches!(chars.get(i), Some('^' | '$' | '*' | '-'))) {
+            let from = if colon { i + 1 } else { i };
+            let (range, next) = designator(chars, from);
+            text = select(line, range, &chars[i..next])?;
+            i = next;
+        }
+
+        while chars.get(i) == Some(&':') {
+            let (modified, next) = match chars.get(i + 1) {
+                Some('h') => (text.rfind('/').map_or(text.clone(), |slash| text[..slash].to_string()), i + 2),
+                Some('t') => (text.rfind('/').map_or(text.clone(), |slash| text[slash + 1..].to_string()), i + 2),
+                Some('r') => (split_suffix(&text).0.to_string(), i + 2),
+                Some('e') => (split_suffix(&text).1.to_string(), i + 2),
+                Some('s') => substitute(&text, chars, i + 2, false)?,
+                Some('g') if chars.get(i + 2) == Some(&'s') => substitute(&text, chars, i + 3, true)?,
+                Some(&c) if c.is_ascii_alphabetic() => return Err(HistoryError::UnknownModifier(c)),
+                _ => break,
+            };
+            text = modified;
+            i = next;
+        }
+        Ok((text, i))
+    }
+
+    /// The last entry, for an event that needs one.
+    fn previous(&self, event: &str) -> Result<&str, HistoryError> {
+        self.entries.last().map(|entry| entry.line.as_str()).ok_or_else(|| HistoryError::EventNotFound(event.to_string()))
+    }
+}
+
+/// Reads the word designator at `chars[i]`, returning the words it picks
+/// and the index after it.
+fn designator(chars: &[char], mut i: usize) -> ((Bound, Bound), usize) {
+    let number = |i: &mut usize| {
+        let len = chars[*i..].iter().take_while(|c| c.is_ascii_digit()).count();
+        let n = chars[*i..*i + len].iter().collect::<String>().parse().ok();
+        *i += len;
+        n
+    };
+    let first = match chars.get(i) {
+        Some('^') => {
+            i += 1;
+            Bound::Word(1)
+        },
+        Some('$') => return ((Bound::Last, Bound::Last), i + 1),
+        Some('*') => return ((Bound::Word(1), Bound::Last), i + 1),
+        Some('-') => Bound::Word(0),
+        _ => Bound::Word(number(&mut i).unwrap_or(0)),
+    };
+    let last = match chars.get(i) {
+        Some('*') => {
+            i += 1;
+            Bound::Last
+        },
+        Some('-') => {
+            i += 1;
+            match chars.get(i) {
+                Some('$') => {
+                    i += 1;
+                    Bound::Last
+                },
+                Some(c) if c.is_ascii_digit() => Bound::Word(number(&mut i).unwrap_or(0)),
+                _ => Bound::BeforeLast,
+            }
+        },
+        _ => first,
+    };
+    ((first, last), i)
+}
+
+/// The words of `line` from `range`, joined with spaces. `*` picks nothing
+/// from a line with a single word.
+fn select(line: &str, range: (Bound, Bound), spec: &[char]) -> Result<String, HistoryError> {
+    let words = words(line);
+    let last = words.len() - 1;
+    let index = |bound| match bound {
+        Bound::Word(n) => n,
+        Bound::Last => last,
+        Bound::BeforeLast => last.saturating_sub(1),
+    };
+    let (first, end) = (index(range.0), index(range.1));
+    match range {
+        (Bound::Word(1), Bound::Last) if last == 0 => Ok(String::new()),
+        _ if first > end || end > last => Err(HistoryError::BadWordSpecifier(spec.iter().collect())),
+        _ => Ok(words[first..=end].join(" ")),
+    }
+}
+
+/// Splits a line into words the way history expansion counts them:
+/// at blanks outside quotes, with operators such as `|` and `>` words of
+/// their own.
+fn words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => {
+                quote = None;
+                word.push(c);
+            },
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.push(c);
+            },
+            (None, '\\') => {
+                word.push(c);
+                word.extend(chars.next());
+            },
+            (None, c) if c.is_whitespace() => words.extend(Some(std::mem::take(&mut word)).filter(|word| !word.is_empty())),
+            (None, c) if "|&;<>()".contains(c) => {
+                words.extend(Some(std::mem::take(&mut word)).filter(|word| !word.is_empty()));
+                let mut operator = c.to_string();
+                while let Some(next) = chars.next_if(|&next| "|&;<>".contains(next) && next == c) {
+                    operator.push(next);
+                }
+                words.push(operator);
+            },
+            (None, c) => word.push(c),
+        }
+    }
+    words.extend(Some(word).filter(|word| !word.is_empty()));
+    if words.is_empty() {
+        words.push(String::new());
+    }
+    words
+}
+
+/// Splits off the suffix `.ext` of the last path component, if it has
+/// one.
+fn split_suffix(text: &str) -> (&str, &str) {
+    let name_start = text.rfind('/').map_or(0, |slash| slash + 1);
+    match text[name_start..].rfind('.') {
+        Some(dot) => text.split_at(name_start + dot),
+        None => (text, ""),
+    }
+}
+
+/// Applies `s/old/new/` to `text`, reading it from `chars[i]`, which is
+/// the delimiter. The last delimiter may be left out at the end of the
+/// line, and `&` in `new` stands for `old`. Returns the new text and the
+/// index after the substitution.
+fn substitute(text: &str, chars: &[char], i: usize, global: bool) -> Result<(String, usize), HistoryError> {
+    let delimiter = match chars.get(i) {
+        Some(&delimiter) => delimiter,
+        None => return Err(HistoryError::SubstitutionFailed),
+    };
+    let part = |mut i: usize| {
+        let mut part = String::new();
+        while let Some(&c) = chars.get(i) {
+            i += 1;
+            match c {
+                c if c == delimiter => break,
+                '\\' if chars.get(i) == Some(&delimiter) => {
+                    part.push(delimiter);
+                    i += 1;
+                },
+                c => part.push(c),
+            }
+        }
+        (part, i)
+    };
+    let (old, next) = part(i + 1);
+    let (new, next) = part(next);
+    if old.is_empty() || !text.contains(&old) {
+        return Err(HistoryError::SubstitutionFailed);
+    }
+    let new = new.replace('&', &old);
+    let replaced = if global { text.replace(&old, &new) } else { text.replacen(&old, &new, 1) };
+    Ok((replaced, next))
+}
+
+/// Reads the entries of a history file. As in bash's, an entry may follow
+/// a `#seconds` line with the time it was entered, after which this shell
+/// writes its exit status, and then takes all the lines up to the next
+/// such line. Files written by rustyline, which start with `#V2` and
+/// escape backslashes and newlines, are read too.
+fn parse(contents: &str) -> Vec<HistoryEntry> {
+    let mut lines = contents.lines().peekable();
+    if lines.next_if_eq(&"#V2").is_some() {
+        let unescape = |line: &str| line.replace("\\n", "\n").replace("\\\\", "\\");
+        return lines.map(|line| HistoryEntry { line: unescape(line), time: None, status: None }).collect();
+    }
+
+    let mut entries: Vec<HistoryEntry> = Vec::new();
+    let mut stamp = None;
+    // Whether the last entry had a timestamp, and so goes on until the
+    // next one.
+    let mut open = false;
+    for line in lines {
+        if let Some(next) = timestamp(line) {
+            stamp = Some(next);
+            open = false;
+        } else if let Some((time, status)) = stamp.take() {
+            entries.push(HistoryEntry { line: line.to_string(), time: Some(time), status });
+            open = true;
+        } else if let (true, Some(entry)) = (open, entries.last_mut()) {
+            entry.line.push('\n');
+            entry.line.push_str(line);
+        } else {
+            entries.push(HistoryEntry { line: line.to_string(), time: None, status: None });
+        }
+    }
+    entries
+}
+
+/// The time and status in a `#seconds [status]` line.
+fn timestamp(line: &str) -> Option<(u64, Option<i32>)> {
+    let line = line.strip_prefix('#')?;
+    let (time, status) = match line.split_once(' ') {
+        Some((time, status)) => (time, Some(status.parse().ok()?)),
+        None => (line, None),
+    };
+    if time.is_empty() || !time.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((time.parse().ok()?, status))
+}
+
+fn format(entries: &[HistoryEntry]) -> String {
+    let mut contents = String::new();
+    for entry in entries {
+        match (entry.time, entry.status) {
+            (Some(time), Some(status)) => contents.push_str(&format!("#{} {}\n", time, status)),
+            (Some(time), None) => contents.push_str(&format!("#{}\n", time)),
+            (None, _) => {},
+        }
+        contents.push_str(&entry.line);
+        contents.push('\n');
+    }
+    contents
+}
+
+fn lock(file: &File, arg: FlockArg) -> io::Result<()> {
+    flock(file.as_raw_fd(), arg).map_err(|e| io::Error::from_raw_os_error(e as i32))
+}
+
+impl Shell {
+
+    /// Adds a line read from the user to the history, unless
+    /// `$HISTCONTROL` leaves it out: `ignorespace` drops lines starting
+    /// with a space, `ignoredups` repeats of the last line, `ignoreboth`
+    /// both, and `erasedups` removes earlier copies of the line first.
+    /// Only the last `$HISTSIZE` entries are kept. Returns whether the
+    /// line was added.
+    pub fn add_history(&mut self, line: &str) -> bool {
+        let control: Vec<&str> = self.var("HISTCONTROL").unwrap_or_default().split(':').collect();
+        let has = |option: &str| control.contains(&option) || control.contains(&"ignoreboth") && option.starts_with("ignore");
+        let line = line.trim_end_matches('\n');
+        if line.trim().is_empty()
+            || has("ignorespace") && line.starts_with(' ')
+            || has("ignoredups") && self.history.entries.last().is_some_and(|last| last.line == line)
+        {
+            return false;
+        }
+        if has("erasedups") {
+            while let Some(i) = self.history.entries.iter().rposition(|entry| entry.line == line) {
+                self.history.remove(self.history.base + i + 1);
+            }
+        }
+        self.history.add(line);
+        if let Some(size) = self.history_limit("HISTSIZE") {
+            self.history.truncate(size);
+        }
+        true
+    }
+
+    /// Reads the history file into the history, keeping the last
+    /// `$HISTSIZE` entries. There is nothing to read if `$HISTFILE` is
+    /// unset or the file does not exist.
+    pub fn load_history(&mut self) -> io::Result<()> {
+        let path = match self.history_file() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        match self.history.read(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            result => result?,
+        }
+        if let Some(size) = self.history_limit("HISTSIZE") {
+            self.history.truncate(size);
+        }
+        Ok(())
+    }
+
+    /// Appends the entries added since the history file was last read or
+    /// written to it, keeping the last `$HISTFILESIZE` entries in the file.
+    pub fn save_history(&mut self) -> io::Result<()> {
+        match self.history_file() {
+            Some(path) => {
+                let size = self.history_limit("HISTFILESIZE");
+                self.history.append(&path, size)
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// `$HISTFILE`, or `None` if it is unset or empty, when the history is
+    /// not saved.
+    pub fn history_file(&self) -> Option<PathBuf> {
+        self.var("HISTFILE").filter(|file| !file.is_empty()).map(|file| self.cwd().join(file))
+    }
+
+    /// The number of entries `$HISTSIZE` or `$HISTFILESIZE` allows, or
+    /// `None` for no limit, which is also what a negative or non-numeric
+    /// value means.
+    fn history_limit(&self, name: &str) -> Option<usize> {
+        self.var(name).and_then(|value| value.parse::<i64>().ok()).and_then(|size| usize::try_from(size).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{History, HistoryEntry};
+    use crate::Shell;
+    use crate::error::HistoryError;
+    use crate::exec::Recorder;
+
+    fn history(lines: &[&str]) -> History {
+        let mut history = History::default();
+        for line in lines {
+            history.add(line);
+        }
+        history
+    }
+
+    #[test]
+    fn expands_events_and_words() {
+        let history = history(&["make build", "cat src/main.rs | grep 'fn main' > out.txt", "echo one two three"]);
+        let expand = |line| history.expand(line).map(|expanded| expanded.unwrap_or_else(|| "unchanged".to_string()));
+
+        assert_eq!(expand("!! four"), Ok("echo one two three four".to_string()));
+        assert_eq!(expand("sudo !1; !-2"), Ok("sudo make build; cat src/main.rs | grep 'fn main' > out.txt".to_string()));
+        assert_eq!(expand("!ma:0 clean"), Ok("make clean".to_string()));
+        assert_eq!(expand("!?grep?:2-3"), Ok("| grep".to_string()));
+        assert_eq!(expand("ls !$ !^ !*"), Ok("ls three one one two three".to_string()));
+        assert_eq!(expand("!cat:1:h !cat:1:t !cat:1:r !cat:$:e"), Ok("src main.rs src/main .txt".to_string()));
+        assert_eq!(expand("!cat:3*"), Ok("grep 'fn main' > out.txt".to_string()));
+        assert_eq!(expand("!!:1-"), Ok("one two".to_string()));
+        assert_eq!(expand("!!:s/one/1/ !!:gs/o/0"), Ok("echo 1 two three ech0 0ne tw0 three".to_string()));
+        assert_eq!(expand("^two^2^ again"), Ok("echo one 2 three again".to_string()));
+        assert_eq!(expand("!make:s/build/& test/"), Ok("make build test".to_string()));
+
+        assert_eq!(expand("echo '!!' \\!! \"a!\" x != y"), Ok("unchanged".to_string()));
+        assert_eq!(expand("!nope"), Err(HistoryError::EventNotFound("!nope".to_string())));
+        assert_eq!(expand("!9"), Err(HistoryError::EventNotFound("!9".to_string())));
+        assert_eq!(expand("!!:5"), Err(HistoryError::BadWordSpecifier(":5".to_string())));
+        assert_eq!(expand("!!:z"), Err(HistoryError::UnknownModifier('z')));
+        assert_eq!(expand("^four^4"), Err(HistoryError::SubstitutionFailed));
+        assert_eq!(History::default().expand("!!"), Err(HistoryError::EventNotFound("!!".to_string())));
+    }
+
+    #[test]
+    fn controls_what_is_kept() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.set_var("HISTCONTROL", "ignoreboth:erasedups");
+        shell.set_var("HISTSIZE", "3");
+        for line in ["ls", "ls", " secret", "make", "cd /", "ls", "pwd"] {
+            shell.add_history(line);
+        }
+        let entries: Vec<_> = shell.history().iter().map(|(number, entry)| (number, entry.line.as_str())).collect();
+        assert_eq!(entries, [(2, "cd /"), (3, "ls"), (4, "pwd")]);
+        assert_eq!(shell.history().get(1), None);
+        assert_eq!(shell.history().expand("!2").unwrap().as_deref(), Some("cd /"));
+        assert_eq!(shell.history().expand("!-1 !-3").unwrap().as_deref(), Some("pwd cd /"));
+        assert_eq!(shell.history().expand("!-4"), Err(HistoryError::EventNotFound("!-4".to_string())));
+    }
+
+    #[test]
+    fn appends_to_a_shared_file() {
+        let file = env::temp_dir().join(format!("crussh-histfile-{}", std::process::id()));
+        let entry = |line: &str, time, status| HistoryEntry { line: line.to_string(), time, status };
+        let mut first = History::default();
+        let mut second = History::default();
+        first.push(entry("make", Some(1000), Some(2)));
+        first.append(&file, None).unwrap();
+        second.push(entry("for x in a b\ndo echo $x\ndone", Some(1001), Some(0)));
+        second.append(&file, None).unwrap();
+        first.push(entry("ls", Some(1002), None));
+        first.set_status(1);
+        first.append(&file, Some(2)).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "#1001 0\nfor x in a b\ndo echo $x\ndone\n#1002 1\nls\n");
+
+        let mut read = History::default();
+        read.read(&file).unwrap();
+        assert_eq!(read.entry(1), Some(&entry("for x in a b\ndo echo $x\ndone", Some(1001), Some(0))));
+        assert_eq!(read.entry(2), Some(&entry("ls", Some(1002), Some(1))));
+
+        fs::write(&file, "#V2\necho a\\nb\nls\n").unwrap();
+        let mut read = History::default();
+        read.read(&file).unwrap();
+        assert_eq!(read.iter().map(|(_, entry)| entry.line.as_str()).collect::<Vec<_>>(), ["echo a\nb", "ls"]);
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! The table of background jobs, and the job specs such as `%1` and `%+`
+//! that builtins name them by.
+
+use nix::sys::signal::Signal;
+
+use crate::Shell;
+
+/// A background job that was still running when last checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// The job number, as in `%1`.
+    pub id: usize,
+    /// The process running the job, which leads its process group.
+    pub pid: u32,
+    /// The command line, as formatted source.
+    pub command: String,
+    /// Whether the job is left running when the shell hangs up its jobs,
+    /// as `disown -h` marks it.
+    pub nohup: bool,
+}
+
+/// What a builtin argument naming a process refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Target {
+    /// The job at this index of the table.
+    Job(usize),
+    Pid(u32),
+}
+
+impl Shell {
+
+    /// The number the next background job gets.
+    pub(crate) fn next_job_id(&self) -> usize {
+        self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1
+    }
+
+    /// Starts tracking a background process.
+    pub(crate) fn add_job(&mut self, pid: u32, command: String) {
+        let id = self.next_job_id();
+        if self.options.interactive {
+            eprintln!("[{}] {}", id, pid);
+        }
+        self.jobs.push(Job { id, pid, command, nohup: false });
+        self.last_background = Some(pid);
+    }
+
+    /// Forgets background jobs that have finished, keeping their statuses
+    /// for `wait` and reporting them if the shell is interactive.
+    pub(crate) fn reap_jobs(&mut self) {
+        let pids: Vec<u32> = self.jobs.iter().map(|job| job.pid).collect();
+        for (pid, status) in self.executor.reap(&pids) {
+            let index = match self.jobs.iter().position(|job| job.pid == pid) {
+                Some(index) => index,
+                None => continue,
+            };
+            let job = self.jobs.remove(index);
+            self.finished.insert(pid, status);
+            if self.options.interactive {
+                let state = if status == 0 { "Done".to_string() } else { format!("Exit {}", status) };
+                eprintln!("[{}]+  {:<24}{}", job.id, state, job.command);
+            }
+        }
+    }
+
+    /// The index in the table of the job `spec` names: `%n` by number, `%+`,
+    /// `%%` or `%` the current job, which is the latest, `%-` the one before
+    /// it, `%?text` the one whose command contains `text` and `%text` the
+    /// one whose command starts with it. The error is the message to show.
+    pub(crate) fn find_job(&self, spec: &str) -> Result<usize, String> {
+        let no_such_job = || format!("{}: no such job", spec);
+        let pattern = spec.strip_prefix('%').ok_or_else(no_such_job)?;
+        let index = match pattern {
+            "" | "+" | "%" => self.jobs.len().checked_sub(1),
+            "-" => self.jobs.len().checked_sub(if self.jobs.len() > 1 { 2 } else { 1 }),
+            _ if pattern.bytes().all(|b| b.is_ascii_digit()) => {
+                self.jobs.iter().position(|job| pattern.parse() == Ok(job.id))
+            },
+            _ => {
+                let matches: Vec<usize> = match pattern.strip_prefix('?') {
+                    Some(text) => self.matching_jobs(|command| command.contains(text)),
+                    None => self.matching_jobs(|command| command.starts_with(pattern)),
+                };
+                if matches.len() > 1 {
+                    return Err(format!("{}: ambiguous job spec", spec));
+                }
+                matches.first().copied()
+            },
+        };
+        index.ok_or_else(no_such_job)
+    }
+
+    fn matching_jobs(&self, matches: impl Fn(&str) -> bool) -> Vec<usize> {
+        (0..self.jobs.len()).filter(|&i| matches(&self.jobs[i].command)).collect()
+    }
+
+    /// What an argument such as `%2` or `1234` names.
+    pub(crate) fn target(&self, arg: &str) -> Result<Target, String> {
+        if arg.starts_with('%') {
+            return self.find_job(arg).map(Target::Job);
+        }
+        match arg.parse() {
+            Ok(pid) if pid > 0 => Ok(Target::Pid(pid)),
+            _ => Err(format!("{}: arguments must be process or job IDs", arg)),
+        }
+    }
+
+    /// Sends `SIGHUP` to every job not marked with `disown -h`, and
+    /// `SIGCONT` so that a stopped one sees it, as a shell does when its
+    /// terminal goes away. A subshell leaves them alone, since they are
+    /// the parent shell's.
+    pub fn hang_up_jobs(&mut self) {
+        if self.subshell {
+            return;
+        }
+        for job in self.jobs.iter().filter(|job| !job.nohup) {
+            for signal in [Signal::SIGHUP, Signal::SIGCONT] {
+                let _ = self.executor.kill(job.pid, Some(signal), true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::Shell;
+    use crate::exec::Recorder;
+
+    #[test]
+    fn finds_jobs_by_spec() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        shell.add_job(10, "sleep 10".to_string());
+        shell.add_job(11, "make all".to_string());
+        shell.add_job(12, "sleep 20 | cat".to_string());
+
+        assert_eq!(shell.find_job("%1"), Ok(0));
+        assert_eq!(shell.find_job("%+"), Ok(2));
+        assert_eq!(shell.find_job("%%"), Ok(2));
+        assert_eq!(shell.find_job("%-"), Ok(1));
+        assert_eq!(shell.find_job("%ma"), Ok(1));
+        assert_eq!(shell.find_job("%?cat"), Ok(2));
+        assert_eq!(shell.find_job("%sleep"), Err("%sleep: ambiguous job spec".to_string()));
+        assert_eq!(shell.find_job("%4"), Err("%4: no such job".to_string()));
+        assert_eq!(shell.target("x"), Err("x: arguments must be process or job IDs".to_string()));
+    }
+
+    #[test]
+    fn reaps_only_its_own_jobs() {
+        let mut other = Command::new("true").spawn().unwrap();
+        let mut shell = Shell::new();
+        assert_eq!(shell.run("true &"), Ok(0));
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(shell.run(":"), Ok(0));
+        assert!(shell.jobs().is_empty());
+        // The program's own child is still there to wait for.
+        assert!(other.wait().unwrap().success());
+    }
+}
@@ -1,31 +1,116 @@
-mod cmd;
-mod command;
+mod alias;
+pub mod ast;
+mod builtin;
+mod capture;
+mod error;
+mod eval;
+pub mod exec;
+mod hash;
+mod history;
+mod job;
+mod options;
+mod shell;
+mod time;
+mod trap;
 mod utils;
 
+pub use builtin::{Builtin, Builtins, Input, Io};
+pub use capture::Captured;
+pub use error::{Error, ExecError, HistoryError, ParseError};
+pub use history::{History, HistoryEntry};
+pub use job::Job;
+pub use options::Options;
+pub use shell::Shell;
+
+use std::fmt;
+
+use ast::{Command, List};
+
+/// A parsed command line.
 #[derive(Debug)]
-pub struct Parse(cmd::Cmd);
+pub struct Parse(List);
 
 impl Parse {
-    pub fn execute(&self) -> Result<(), String> {
-        self.0.execute().map_err(|e| e.to_string())
+    /// Runs the parsed command line in a new [`Shell`] and returns its
+    /// exit status, as [`Shell::run`] does.
+    pub fn execute(&self) -> Result<i32, ExecError> {
+        self.execute_with(exec::Native)
+    }
+
+    /// Runs the parsed command line with `executor` doing the process and
+    /// descriptor work, e.g. an [`exec::Recorder`] to see what would run.
+    pub fn execute_with(&self, executor: impl exec::Executor + 'static) -> Result<i32, ExecError> {
+        Shell::with_executor(executor).execute(self)
+    }
+
+    pub fn ast(&self) -> &List {
+        &self.0
+    }
+
+    pub fn into_ast(self) -> List {
+        self.0
+    }
+
+    /// The name of the command, if the line is a single simple command.
+    pub fn get_filename(&self) -> Option<String> {
+        self.simple_words().and_then(|words| words.first().cloned())
     }
 
-    pub fn get_filename(&self) -> &str {
-        self.0.get_filename()
+    /// The arguments of the command, if the line is a single simple command.
+    pub fn get_args(&self) -> Vec<String> {
+        self.simple_words().map(|words| words[1..].to_vec()).unwrap_or_default()
     }
 
-    pub fn get_args(&self) -> &Vec<String> {
-        self.0.get_args()
+    fn simple_words(&self) -> Option<Vec<String>> {
+        let simple = match self.0.items.as_slice() {
+            [item] if !item.background && item.and_or.rest.is_empty() => {
+                match item.and_or.first.commands.as_slice() {
+                    [Command::Simple(simple)] if !item.and_or.first.negated && item.and_or.first.time.is_none() => simple,
+                    _ => return None,
+                }
+            },
+            _ => return None,
+        };
+
+        let words: Vec<String> = simple.words.iter()
+            .map(|word| word.parts.iter().map(|part| match part {
+                ast::WordPart::Literal(text) | ast::WordPart::Quoted(text) => text.clone(),
+                ast::WordPart::Parameter { name, .. } => format!("${}", name),
+            }).collect())
+            .collect();
+
+        if words.is_empty() { None } else { Some(words) }
     }
 }
 
-pub fn parse(s: &str) -> Result<Parse, String> {
-    let (s, stmt) = cmd::Cmd::new(s)?;
+impl fmt::Display for Parse {
+    /// Prints the command line back as normalized source.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<List> for Parse {
+    /// Wraps a tree built by hand or deserialized, so it can be executed
+    /// like one that was parsed.
+    fn from(list: List) -> Self {
+        Self(list)
+    }
+}
+
+pub fn parse(s: &str) -> Result<Parse, ParseError> {
+    let (s, list) = List::new(s)?;
+    let (s, _) = utils::extract_linebreak(s);
 
     if s.is_empty() {
-        Ok(Parse(stmt))
+        Ok(Parse(list))
     } else {
-        dbg!(s);
-        Err("input was not fully consumed by parser".to_string())
+        Err(utils::unexpected(s))
     }
-}
\ No newline at end of file
+}
+
+/// The line, counted from 1, of the first comment in `s`. [`parse`] skips
+/// comments, so printing a [`Parse`] back drops them.
+pub fn comment_line(s: &str) -> Option<usize> {
+    utils::find_comment(s).map(|i| s[..i].matches('\n').count() + 1)
+}
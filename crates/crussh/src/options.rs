@@ -0,0 +1,105 @@
+//! The shell's options, as `set` and `shopt` change them and `$-` shows
+//! them.
+
+/// The options `set` takes, by letter and by the name `set -o` takes.
+pub(crate) const SET_OPTIONS: [(char, &str); 6] = [
+    ('e', "errexit"),
+    ('C', "noclobber"),
+    ('n', "noexec"),
+    ('f', "noglob"),
+    ('u', "nounset"),
+    ('x', "xtrace"),
+];
+
+/// The options only `shopt` knows.
+pub(crate) const SHOPT_OPTIONS: [&str; 4] = ["dotglob", "expand_aliases", "joblog", "nullglob"];
+
+/// Settings that change how the shell behaves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Whether the shell reads commands from a user, in which case it
+    /// reports background jobs as they start and finish.
+    pub interactive: bool,
+    /// `set -e`: exit as soon as a command fails, unless its status is
+    /// being tested.
+    pub errexit: bool,
+    /// `set -u`: treat expanding an unset parameter as an error.
+    pub nounset: bool,
+    /// `set -x`: print each simple command to standard error, after
+    /// `$PS4`, once it is expanded.
+    pub xtrace: bool,
+    /// `set -n`: read commands without running them. An interactive shell
+    /// ignores it.
+    pub noexec: bool,
+    /// `set -f`: leave `*`, `?` and `[` in words instead of matching them
+    /// against file names.
+    pub noglob: bool,
+    /// `set -C`: have `>` refuse to overwrite an existing file; `>|` still
+    /// does.
+    pub noclobber: bool,
+    /// Whether patterns match file names starting with `.`.
+    pub dotglob: bool,
+    /// Whether a pattern that matches no file expands to nothing rather
+    /// than to itself.
+    pub nullglob: bool,
+    /// Whether aliases are expanded.
+    pub expand_aliases: bool,
+    /// Whether background jobs write their output and errors to
+    /// `$CRUSSH_JOBLOG_DIR/<job id>-<command>.log` instead of the shell's.
+    /// There is no default directory: until the variable is set, jobs keep
+    /// the shell's output and each one started says so.
+    pub joblog: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            interactive: false,
+            errexit: false,
+            nounset: false,
+            xtrace: false,
+            noexec: false,
+            noglob: false,
+            noclobber: false,
+            dotglob: false,
+            nullglob: false,
+            expand_aliases: true,
+            joblog: false,
+        }
+    }
+}
+
+impl Options {
+
+    /// The option `set -o` or `shopt` knows by `name`.
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "errexit" => Some(&mut self.errexit),
+            "nounset" => Some(&mut self.nounset),
+            "xtrace" => Some(&mut self.xtrace),
+            "noexec" => Some(&mut self.noexec),
+            "noglob" => Some(&mut self.noglob),
+            "noclobber" => Some(&mut self.noclobber),
+            "dotglob" => Some(&mut self.dotglob),
+            "nullglob" => Some(&mut self.nullglob),
+            "expand_aliases" => Some(&mut self.expand_aliases),
+            "joblog" => Some(&mut self.joblog),
+            _ => None,
+        }
+    }
+
+    /// The letters of the options that are on, as in `$-`, with `i` for an
+    /// interactive shell.
+    pub(crate) fn flags(&self) -> String {
+        let flags = [
+            (self.errexit, 'e'),
+            (self.noglob, 'f'),
+            (self.interactive, 'i'),
+            (self.noexec, 'n'),
+            (self.nounset, 'u'),
+            (self.xtrace, 'x'),
+            (self.noclobber, 'C'),
+        ];
+        flags.iter().filter(|(on, _)| *on).map(|(_, flag)| flag).collect()
+    }
+}
@@ -0,0 +1,534 @@
+//! A shell session: the state that lasts from one command line to the next.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+
+use nix::errno::Errno;
+use nix::unistd::{AccessFlags, access, getpid};
+
+use crate::ast::{FunctionDef, List};
+use crate::builtin::Builtins;
+use crate::exec::{Executor, Native, Usage};
+use crate::hash::HashTable;
+use crate::job::Job;
+use crate::options::Options;
+use crate::trap::Condition;
+use crate::{Error, ExecError, History, Parse, ParseError, alias};
+
+/// An independent shell with its own variables, functions, builtins,
+/// aliases, traps, options, background jobs, working directory, directory
+/// stack and last status.
+///
+/// Several shells can live in one process: a shell never changes the
+/// process's working directory or environment, only those of the programs
+/// it starts.
+///
+/// ```
+/// let mut shell = crussh::Shell::new();
+/// shell.run("greeting=hello; f() { return_value=$1; }; f $greeting").unwrap();
+/// assert_eq!(shell.var("return_value"), Some("hello"));
+/// ```
+pub struct Shell {
+    pub(crate) executor: Box<dyn Executor>,
+    pub(crate) vars: HashMap<String, Variable>,
+    pub(crate) functions: HashMap<String, FunctionDef>,
+    pub(crate) builtins: Builtins,
+    pub(crate) aliases: BTreeMap<String, String>,
+    pub(crate) traps: BTreeMap<Condition, String>,
+    /// Whether a trap is running, during which no other runs.
+    pub(crate) in_trap: bool,
+    /// How many conditions of `if`, `while` and `&&` lists are running,
+    /// whose failures neither trigger `ERR` nor stop the shell under `set -e`.
+    pub(crate) conditions: usize,
+    pub(crate) function_depth: usize,
+    /// Whether the terminal's signals are ignored, as in an interactive
+    /// shell.
+    pub(crate) shielded: bool,
+    pub(crate) options: Options,
+    pub(crate) jobs: Vec<Job>,
+    /// The statuses of background jobs that finished before `wait` asked
+    /// for them, by process id.
+    pub(crate) finished: HashMap<u32, i32>,
+    pub(crate) cwd: PathBuf,
+    /// Directories saved by `pushd`, most recent first.
+    pub(crate) dir_stack: Vec<PathBuf>,
+    pub(crate) hash: HashTable,
+    pub(crate) positional: Vec<String>,
+    pub(crate) last_status: i32,
+    pub(crate) last_background: Option<u32>,
+    pub(crate) shell_pid: u32,
+    /// The error behind `last_status`, reported once another command runs
+    /// or handed to the caller if it ends up deciding the final status.
+    pub(crate) pending_error: Option<ExecError>,
+    /// Set by `exec` to keep the redirections of the command it ends.
+    pub(crate) keep_redirects: bool,
+    /// The status the shell exits with, once `set -e` or `set -u` has
+    /// stopped it. Nothing more runs after that.
+    pub(crate) exit_status: Option<i32>,
+    /// Whether the rest of the command line is skipped, as when an
+    /// interactive shell expands an unset parameter under `set -u`.
+    pub(crate) aborted: bool,
+    /// Whether this is a forked child running a subshell or a stage of a
+    /// pipeline, rather than the shell itself.
+    pub(crate) subshell: bool,
+    /// What the processes the shell has waited for used, as `times`
+    /// reports it.
+    pub(crate) children_usage: Usage,
+    /// When the shell started, for a bare `time`.
+    pub(crate) started: Instant,
+    /// Where `getopts` is within a group of options such as `-ab`: the
+    /// `$OPTIND` it was left at and the offset into that argument.
+    pub(crate) getopts_offset: Option<(usize, usize)>,
+    pub(crate) history: History,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Variable {
+    pub(crate) value: String,
+    /// Whether programs the shell starts see the variable.
+    pub(crate) exported: bool,
+}
+
+impl Shell {
+
+    /// A shell that runs commands for real, starting with the process's
+    /// environment and working directory.
+    pub fn new() -> Self {
+        Self::with_executor(Native)
+    }
+
+    /// A shell that has `executor` start its processes.
+    pub fn with_executor(executor: impl Executor + 'static) -> Self {
+        let vars = env::vars_os()
+            .map(|(name, value)| {
+                let value = value.to_string_lossy().into_owned();
+                (name.to_string_lossy().into_owned(), Variable { value, exported: true })
+            })
+            .collect();
+
+        let mut shell = Self {
+            executor: Box::new(executor),
+            vars,
+            functions: HashMap::new(),
+            builtins: Builtins::standard(),
+            aliases: BTreeMap::new(),
+            traps: BTreeMap::new(),
+            in_trap: false,
+            conditions: 0,
+            function_depth: 0,
+            shielded: false,
+            options: Options::default(),
+            jobs: Vec::new(),
+            finished: HashMap::new(),
+            cwd: initial_dir(),
+            dir_stack: Vec::new(),
+            hash: HashTable::default(),
+            positional: Vec::new(),
+            last_status: 0,
+            last_background: None,
+            shell_pid: getpid().as_raw() as u32,
+            pending_error: None,
+            keep_redirects: false,
+            exit_status: None,
+            aborted: false,
+            subshell: false,
+            children_usage: Usage::default(),
+            started: Instant::now(),
+            getopts_offset: None,
+            history: History::default(),
+        };
+        let pwd = shell.cwd.to_string_lossy().into_owned();
+        shell.set_var("PWD", pwd);
+        shell.export_var("PWD");
+        shell
+    }
+
+    /// Parses and runs a command line, returning its exit status.
+    ///
+    /// If the line does not parse, or its last command could not be run
+    /// at all, the error is returned instead; errors of earlier commands
+    /// are printed to standard error as they happen, the way a shell
+    /// reports them. Either way the status is kept for `$?`.
+    pub fn run(&mut self, input: &str) -> Result<i32, Error> {
+        match self.parse(input) {
+            Ok(parse) => self.execute(&parse).map_err(Error::Exec),
+            Err(e) => {
+                self.last_status = 2;
+                Err(Error::Parse(e))
+            }
+        }
+    }
+
+    /// Parses a command line after expanding its aliases.
+    pub(crate) fn parse(&self, input: &str) -> Result<Parse, ParseError> {
+        if !self.options.expand_aliases {
+            return crate::parse(input);
+        }
+        crate::parse(&alias::expand(input, &self.aliases))
+    }
+
+    /// Runs the commands in the file at `path`, relative to the shell's
+    /// working directory, in this shell.
+    ///
+    /// Commands are parsed and run one at a time, so aliases defined in the
+    /// file apply to the commands after them. A command that cannot be run
+    /// is reported with its line and the rest of the file still runs; a
+    /// syntax error stops the file. The error of the last command, or the
+    /// syntax error, is returned as an [`Error::Script`].
+    pub fn run_script(&mut self, path: impl AsRef<Path>) -> Result<i32, Error> {
+        let name = path.as_ref().to_string_lossy().into_owned();
+        let script = match fs::read_to_string(self.cwd.join(path)) {
+            Ok(script) => script,
+            Err(e) => {
+                let e = read_error(&name, &e);
+                self.last_status = e.status();
+                return Err(Error::Exec(e));
+            }
+        };
+        let located = |line, error| Error::Script { path: name.clone(), line, error: Box::new(error) };
+
+        self.last_status = 0;
+        let mut lines = script.split_inclusive('\n').enumerate().peekable();
+        let mut command = String::new();
+        while let Some((i, line)) = lines.next() {
+            command.push_str(line);
+            let last = lines.peek().is_none();
+            let parse = match self.parse(&command) {
+                Ok(parse) => parse,
+                Err(e) if e.is_incomplete() && !last => continue,
+                Err(e) => {
+                    self.last_status = 2;
+                    return Err(located(i + 1, Error::Parse(e)));
+                }
+            };
+            command.clear();
+
+            match self.execute(&parse) {
+                Ok(_) => {},
+                Err(e) if last => return Err(located(i + 1, Error::Exec(e))),
+                Err(e) => eprintln!("crussh: {}", located(i + 1, Error::Exec(e))),
+            }
+            if self.stopped() {
+                break;
+            }
+        }
+        Ok(self.last_status)
+    }
+
+    /// Runs an already parsed command line, as [`run`](Shell::run) does.
+    pub fn execute(&mut self, parse: &Parse) -> Result<i32, ExecError> {
+        self.execute_list(parse.ast())
+    }
+
+    pub(crate) fn execute_list(&mut self, list: &List) -> Result<i32, ExecError> {
+        self.aborted = false;
+        self.shield_signals();
+        self.reap_jobs();
+        self.run_pending_traps();
+        let status = self.exec_list(list);
+
+        match self.pending_error.take() {
+            Some(e) if e.status() == status => Err(e),
+            Some(e) => {
+                eprintln!("crussh: {}", e);
+                Ok(status)
+            },
+            None => Ok(status),
+        }
+    }
+
+    /// The exit status of the last command, as in `$?`.
+    pub fn last_status(&self) -> i32 {
+        self.last_status
+    }
+
+    /// The status to exit with, once the shell has stopped because a
+    /// command failed under `set -e`, or a shell that is not interactive
+    /// expanded an unset parameter under `set -u`. The shell runs nothing
+    /// more after that.
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
+    /// Stops the shell with `status`, as `exit` does in a subshell: nothing
+    /// more runs, and the child exits with it.
+    pub fn stop(&mut self, status: i32) {
+        self.exit_status = Some(status);
+    }
+
+    /// Whether this is a forked child running a subshell or a stage of a
+    /// pipeline, where `exit` ends only the child.
+    pub fn is_subshell(&self) -> bool {
+        self.subshell
+    }
+
+    /// Whether no more commands are to run on this command line.
+    pub(crate) fn stopped(&self) -> bool {
+        self.aborted || self.exit_status.is_some()
+    }
+
+    /// Waits for a child, adding what it used to `children_usage`.
+    pub(crate) fn wait_child(&mut self, pid: u32) -> Result<i32, ExecError> {
+        let (status, usage) = self.executor.wait_with_usage(pid)?;
+        self.children_usage.add(&usage);
+        Ok(status)
+    }
+
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|var| var.value.as_str())
+    }
+
+    /// Sets a variable, keeping it exported if it already was.
+    pub fn set_var(&mut self, name: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.vars.get_mut(name) {
+            Some(var) => var.value = value,
+            None => {
+                self.vars.insert(name.to_string(), Variable { value, exported: false });
+            }
+        }
+    }
+
+    /// Marks a variable, set or not, to be passed to programs.
+    pub fn export_var(&mut self, name: &str) {
+        self.vars.entry(name.to_string())
+            .or_insert_with(|| Variable { value: String::new(), exported: true })
+            .exported = true;
+    }
+
+    pub fn unset_var(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// Sets the positional parameters `$1`, `$2`, ...
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.positional = args;
+    }
+
+    pub fn function(&self, name: &str) -> Option<&FunctionDef> {
+        self.functions.get(name)
+    }
+
+    pub fn builtins(&self) -> &Builtins {
+        &self.builtins
+    }
+
+    pub fn builtins_mut(&mut self) -> &mut Builtins {
+        &mut self.builtins
+    }
+
+    pub fn alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn set_alias(&mut self, name: &str, value: impl Into<String>) {
+        self.aliases.insert(name.to_string(), value.into());
+    }
+
+    pub fn unset_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    /// The command lines read from the user, which `history` lists and
+    /// `!` refers to. The shell only adds to it through the `history`
+    /// builtin; whoever reads the lines adds them, with `add_history`.
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    pub fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut Options {
+        &mut self.options
+    }
+
+    /// The background jobs that had not finished when the last command
+    /// line started.
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// The working directory commands run in. It is a logical path, which
+    /// may go through symbolic links, as in `$PWD`.
+    pub fn cwd(&self) -> &Path {
+        &self.cwd
+    }
+
+    /// The directories saved by `pushd`, most recent first. The working
+    /// directory comes before them in `dirs` and `~N`, as entry 0.
+    pub fn dir_stack(&self) -> &[PathBuf] {
+        &self.dir_stack
+    }
+
+    pub fn dir_stack_mut(&mut self) -> &mut Vec<PathBuf> {
+        &mut self.dir_stack
+    }
+
+    /// Changes the working directory to `dir`, relative to the current one,
+    /// and updates `$PWD` and `$OLDPWD`.
+    ///
+    /// `.` and `..` are resolved in the path as written, the way `cd -L`
+    /// does it: `..` after a symbolic link leads back to where the link
+    /// is. Canonicalize `dir` first to follow links instead.
+    pub fn set_cwd(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        let dir = normalize(&self.cwd.join(dir));
+        if !fs::metadata(&dir)?.is_dir() {
+            return Err(io::Error::from_raw_os_error(Errno::ENOTDIR as i32));
+        }
+        access(&dir, AccessFlags::X_OK).map_err(|errno| io::Error::from_raw_os_error(errno as i32))?;
+
+        let old = std::mem::replace(&mut self.cwd, dir);
+        self.set_var("OLDPWD", old.to_string_lossy());
+        let pwd = self.cwd.to_string_lossy().into_owned();
+        self.set_var("PWD", pwd);
+        self.export_var("OLDPWD");
+        self.export_var("PWD");
+        Ok(())
+    }
+
+    /// The environment for a program: the exported variables, plus the
+    /// command's own assignments.
+    pub(crate) fn child_env(&self, assignments: &[(String, String)]) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self.vars.iter()
+            .filter(|(name, var)| var.exported && !assignments.iter().any(|(n, _)| n == *name))
+            .map(|(name, var)| (name.clone(), var.value.clone()))
+            .collect();
+        env.sort();
+        env.extend(assignments.iter().cloned());
+        env
+    }
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The process's working directory, by the name `$PWD` gives it if that
+/// is an equivalent path.
+fn initial_dir() -> PathBuf {
+    let physical = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    match env::var_os("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.is_absolute()
+            && normalize(&pwd) == pwd
+            && pwd.canonicalize().ok() == physical.canonicalize().ok() => pwd,
+        _ => physical,
+    }
+}
+
+/// `path` with `.` and `..` components resolved without looking at the
+/// file system.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The error for a script that cannot be read, worded like one for a
+/// program that cannot be run.
+fn read_error(path: &str, e: &io::Error) -> ExecError {
+    match e.kind() {
+        io::ErrorKind::NotFound => ExecError::NoSuchFile(path.to_string()),
+        io::ErrorKind::PermissionDenied => ExecError::PermissionDenied(path.to_string()),
+        _ if e.raw_os_error() == Some(Errno::EISDIR as i32) => ExecError::IsADirectory(path.to_string()),
+        _ => ExecError::Redirection {
+            path: path.to_string(),
+            errno: Errno::from_i32(e.raw_os_error().unwrap_or(0)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::Shell;
+    use crate::exec::Recorder;
+    use crate::{Error, ExecError};
+
+    #[test]
+    fn keeps_state_between_lines() {
+        let recorder = Recorder::new().with_status("fail", 1);
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("x=1; f() { print $x $1; }").unwrap();
+        assert_eq!(shell.run("fail"), Ok(1));
+        assert_eq!(shell.run("f $?"), Ok(0));
+
+        assert_eq!(recorder.argvs()[1], ["print", "1", "1"]);
+        assert_eq!(shell.var("x"), Some("1"));
+        assert!(shell.function("f").is_some());
+    }
+
+    #[test]
+    fn exports_variables() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.set_var("A", "1");
+        shell.set_var("B", "2");
+        shell.export_var("B");
+        shell.run("env").unwrap();
+
+        let spawned = recorder.spawned();
+        assert_eq!(spawned[0].var("A"), None);
+        assert_eq!(spawned[0].var("B"), Some("2"));
+    }
+
+    #[test]
+    fn runs_programs_in_its_own_directory() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.set_cwd("/").unwrap();
+        shell.run("ls").unwrap();
+
+        assert_eq!(shell.cwd(), Path::new("/"));
+        assert_eq!(recorder.spawned()[0].dir, Path::new("/"));
+        assert!(shell.set_cwd("/no/such/dir").is_err());
+        assert_eq!(shell.cwd(), Path::new("/"));
+    }
+
+    #[test]
+    fn tracks_background_jobs() {
+        let recorder = Recorder::new();
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("sleep 1 &").unwrap();
+
+        assert_eq!(shell.jobs().len(), 1);
+        assert_eq!(shell.jobs()[0].command, "sleep 1");
+        shell.run("true").unwrap();
+        assert!(shell.jobs().is_empty());
+
+        shell.run("sleep 2 & sleep 3 & ls").unwrap();
+        assert_eq!(shell.jobs().iter().map(|job| job.command.as_str()).collect::<Vec<_>>(), ["sleep 2", "sleep 3"]);
+        assert_eq!(recorder.argvs().last().unwrap(), &["ls"]);
+    }
+
+    #[test]
+    fn reports_errors_with_status() {
+        let mut shell = Shell::with_executor(Recorder::new());
+        assert!(matches!(shell.run("a |"), Err(Error::Parse(_))));
+        assert_eq!(shell.last_status(), 2);
+
+        let e = shell.run_script("/no/such/script").unwrap_err();
+        assert_eq!(e, Error::Exec(ExecError::NoSuchFile("/no/such/script".to_string())));
+        assert_eq!(shell.last_status(), 127);
+    }
+}
@@ -0,0 +1,172 @@
+//! `time`: how long a pipeline took and the resources it used.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::Shell;
+use crate::ast::Timing;
+use crate::exec::Usage;
+
+/// The format used when `TIMEFORMAT` is unset.
+const DEFAULT_FORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+const POSIX_FORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+impl Shell {
+
+    /// Runs a pipeline with `f` and reports the time it took and the CPU
+    /// time the shell and the processes it waited for used meanwhile.
+    pub(crate) fn timed(&mut self, timing: Timing, f: impl FnOnce(&mut Self) -> i32) -> i32 {
+        let start = Instant::now();
+        let before = self.executor.usage();
+        let children = std::mem::take(&mut self.children_usage);
+
+        let status = f(self);
+
+        let real = start.elapsed();
+        let waited = std::mem::replace(&mut self.children_usage, children);
+        self.children_usage.add(&waited);
+        // The shell's own resident set spans its whole life, so it only
+        // stands for the pipeline's when no process was started.
+        let mut usage = self.executor.usage().since(&before);
+        if waited.max_rss > 0 {
+            usage.max_rss = 0;
+        }
+        usage.add(&waited);
+
+        self.report_time(timing, real, &usage);
+        status
+    }
+
+    /// A bare `time`: reports the time since the shell started and the CPU
+    /// time it and the processes it waited for have used.
+    pub(crate) fn time_shell(&mut self, timing: Timing) -> i32 {
+        let mut usage = self.executor.usage();
+        usage.add(&self.children_usage);
+        self.report_time(timing, self.started.elapsed(), &usage);
+        0
+    }
+
+    fn report_time(&mut self, timing: Timing, real: Duration, usage: &Usage) {
+        let format = match timing {
+            Timing::Posix => Some(POSIX_FORMAT.to_string()),
+            Timing::Default => match self.var("TIMEFORMAT") {
+                Some("") => None,
+                Some(format) => Some(format.to_string()),
+                None => Some(DEFAULT_FORMAT.to_string()),
+            },
+        };
+        if let Some(format) = format {
+            let mut io = self.executor.stdio();
+            let _ = writeln!(io.stderr, "{}", report(&format, real, usage));
+            let _ = io.stderr.flush();
+        }
+    }
+}
+
+/// Fills in a `TIMEFORMAT`: `%R`, `%U` and `%S` are the real, user and
+/// system time, with an optional number of decimals from 0 to 3 and an `l`
+/// for minutes and seconds, `%P` is the CPU percentage, and `%M`, `%w` and
+/// `%c` are the largest resident set in kilobytes and the voluntary and
+/// involuntary context switches.
+fn report(format: &str, real: Duration, usage: &Usage) -> String {
+    let mut out = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let precision = chars.next_if(|c| c.is_ascii_digit());
+        let long = chars.next_if_eq(&'l').is_some();
+        let time = match chars.next() {
+            Some('R') => real,
+            Some('U') => usage.user,
+            Some('S') => usage.system,
+            Some(c) if precision.is_none() && !long => {
+                let _ = match c {
+                    '%' => write!(out, "%"),
+                    'P' => write!(out, "{:.2}", percent(usage.user + usage.system, real)),
+                    'M' => write!(out, "{}", usage.max_rss),
+                    'w' => write!(out, "{}", usage.voluntary_switches),
+                    'c' => write!(out, "{}", usage.involuntary_switches),
+                    c => write!(out, "%{}", c),
+                };
+                continue;
+            },
+            next => {
+                out.push('%');
+                out.extend(precision);
+                if long {
+                    out.push('l');
+                }
+                out.extend(next);
+                continue;
+            },
+        };
+        let precision = precision.map_or(3, |digit| (digit as usize - '0' as usize).min(3));
+        out.push_str(&seconds(time, precision, long));
+    }
+    out
+}
+
+fn percent(cpu: Duration, real: Duration) -> f64 {
+    if real.is_zero() { 0.0 } else { cpu.as_secs_f64() * 100.0 / real.as_secs_f64() }
+}
+
+/// `time` in seconds with `precision` decimals, or in minutes and seconds
+/// as in `1m2.500s` if `long` is set.
+pub(crate) fn seconds(time: Duration, precision: usize, long: bool) -> String {
+    if long {
+        let minutes = time.as_secs() / 60;
+        let rest = time - Duration::from_secs(minutes * 60);
+        format!("{}m{:.*}s", minutes, precision, rest.as_secs_f64())
+    } else {
+        format!("{:.*}", precision, time.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::report;
+    use crate::Shell;
+    use crate::exec::{Fd, Recorder, Usage};
+
+    #[test]
+    fn formats_times() {
+        let usage = Usage {
+            user: Duration::from_millis(1250),
+            system: Duration::from_millis(250),
+            max_rss: 2048,
+            voluntary_switches: 3,
+            involuntary_switches: 4,
+        };
+        let real = Duration::from_millis(62_500);
+        assert_eq!(report(super::DEFAULT_FORMAT, real, &usage), "\nreal\t1m2.500s\nuser\t0m1.250s\nsys\t0m0.250s");
+        assert_eq!(report(super::POSIX_FORMAT, real, &usage), "real 62.50\nuser 1.25\nsys 0.25");
+        assert_eq!(report("%0R %9lU %P%% %M %w %c %x %2q", real, &usage), "62 0m1.250s 2.40% 2048 3 4 %x %2q");
+    }
+
+    #[test]
+    fn times_pipelines() {
+        let usage = |millis, max_rss| Usage { user: Duration::from_millis(millis), max_rss, ..Usage::default() };
+        let recorder = Recorder::new().with_usage("ls", usage(100, 300)).with_usage("wc", usage(20, 500));
+        let mut shell = Shell::with_executor(recorder.clone());
+        shell.run("TIMEFORMAT='%3U %M'; time ls | wc; time -p ! true; TIMEFORMAT=; time ls").unwrap();
+        let stderr = String::from_utf8_lossy(&recorder.written(&Fd::Inherited(2))).into_owned();
+        assert_eq!(stderr, "0.120 500\nreal 0.00\nuser 0.00\nsys 0.00\n");
+        assert_eq!(shell.last_status(), 0);
+
+        let captured = shell.capture("times").unwrap();
+        assert_eq!(captured.stdout_lossy(), "0m0.000s 0m0.000s\n0m0.220s 0m0.000s\n");
+
+        // A bare `time` reports on the shell so far.
+        shell.run("TIMEFORMAT='%3U %3S'; time; ! time ls").unwrap();
+        let stderr = String::from_utf8_lossy(&recorder.written(&Fd::Inherited(2))).into_owned();
+        assert_eq!(stderr, "0.120 500\nreal 0.00\nuser 0.00\nsys 0.00\n0.220 0.000\n0.100 0.000\n");
+        assert_eq!(shell.last_status(), 1);
+    }
+}
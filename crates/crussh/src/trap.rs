@@ -0,0 +1,216 @@
+//! Traps: commands the shell runs when it receives a signal, exits, or a
+//! command fails or is about to run.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use nix::sys::signal::Signal;
+
+use crate::Shell;
+use crate::exec::Disposition;
+
+/// The signals an interactive shell ignores so that keys typed at the
+/// terminal reach only the command it is running.
+const TERMINAL_SIGNALS: [Signal; 3] = [Signal::SIGINT, Signal::SIGQUIT, Signal::SIGTSTP];
+
+/// When a trap runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Condition {
+    /// When the shell exits.
+    Exit,
+    Signal(Signal),
+    /// Before each simple command.
+    Debug,
+    /// After a command fails, where `set -e` would exit.
+    Err,
+}
+
+impl FromStr for Condition {
+    type Err = ();
+
+    /// Reads a condition the way `trap` takes it: a signal by name, with
+    /// or without `SIG`, or by number, where 0 is `EXIT`.
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let name = s.to_ascii_uppercase();
+        match name.as_str() {
+            "EXIT" | "0" => return Ok(Self::Exit),
+            "DEBUG" => return Ok(Self::Debug),
+            "ERR" => return Ok(Self::Err),
+            _ => {},
+        }
+        parse_signal(&name).map(Self::Signal).ok_or(())
+    }
+}
+
+/// Reads a signal by name, in any case and with or without `SIG`, or by
+/// number.
+pub(crate) fn parse_signal(s: &str) -> Option<Signal> {
+    let name = s.to_ascii_uppercase();
+    match name.parse::<i32>() {
+        Ok(number) => Signal::try_from(number).ok(),
+        Err(_) if name.starts_with("SIG") => name.parse().ok(),
+        Err(_) => format!("SIG{}", name).parse().ok(),
+    }
+}
+
+/// Lists the signals with their numbers, as `trap -l` and `kill -l` do.
+pub(crate) fn list_signals(out: &mut dyn Write) -> io::Result<()> {
+    for signal in Signal::iterator() {
+        writeln!(out, "{}) {}", signal as i32, signal)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exit => f.write_str("EXIT"),
+            Self::Signal(signal) => f.write_str(signal.as_str()),
+            Self::Debug => f.write_str("DEBUG"),
+            Self::Err => f.write_str("ERR"),
+        }
+    }
+}
+
+impl Shell {
+
+    /// Sets the command to run for `condition`; an empty one ignores the
+    /// signal and `None` puts back what the shell does without a trap.
+    pub(crate) fn set_trap(&mut self, condition: Condition, action: Option<String>) {
+        if let Condition::Signal(signal) = condition {
+            let disposition = match action.as_deref() {
+                None => self.untrapped_disposition(signal),
+                Some("") => Disposition::Ignore,
+                Some(_) => Disposition::Catch,
+            };
+            self.executor.set_disposition(signal, disposition);
+        }
+        match action {
+            Some(action) => self.traps.insert(condition, action),
+            None => self.traps.remove(&condition),
+        };
+    }
+
+    /// Runs the `EXIT` trap, if there is one, as the shell is about to exit.
+    /// The trap is removed first, so it runs only once.
+    pub fn run_exit_trap(&mut self) {
+        if let Some(action) = self.traps.remove(&Condition::Exit) {
+            self.run_action(&action);
+        }
+    }
+
+    /// Runs the traps of the signals that have arrived since this was last
+    /// called.
+    pub(crate) fn run_pending_traps(&mut self) {
+        if self.in_trap {
+            return;
+        }
+        for signal in self.executor.caught_signals() {
+            self.run_trap(Condition::Signal(signal));
+        }
+    }
+
+    /// Runs the trap for `condition` if there is one and no other trap is
+    /// running. `$?` is left as it was.
+    pub(crate) fn run_trap(&mut self, condition: Condition) {
+        if self.in_trap {
+            return;
+        }
+        match self.traps.get(&condition) {
+            Some(action) if !action.is_empty() => {
+                let action = action.clone();
+                self.run_action(&action);
+            },
+            _ => {},
+        }
+    }
+
+    /// Runs the `DEBUG` trap before a simple command outside a function.
+    pub(crate) fn before_command(&mut self) {
+        if self.function_depth == 0 {
+            self.run_trap(Condition::Debug);
+        }
+    }
+
+    /// Runs the `ERR` trap for a command that failed, unless it was tested
+    /// by a condition or ran in a function, and stops the shell under
+    /// `set -e` unless it was tested.
+    pub(crate) fn command_failed(&mut self) {
+        if self.conditions > 0 {
+            return;
+        }
+        if self.function_depth == 0 {
+            self.run_trap(Condition::Err);
+        }
+        if self.options.errexit {
+            self.exit_status = Some(self.last_status);
+        }
+    }
+
+    fn run_action(&mut self, action: &str) {
+        let status = self.last_status;
+        let pending = self.pending_error.take();
+        // A trap still runs once the shell has stopped, as `EXIT` does
+        // after `set -e`.
+        let exit_status = self.exit_status.take();
+        let aborted = std::mem::take(&mut self.aborted);
+        self.in_trap = true;
+        match self.parse(action) {
+            Ok(parse) => {
+                self.exec_list(parse.ast());
+                if let Some(e) = self.pending_error.take() {
+                    eprintln!("crussh: {}", e);
+                }
+            },
+            Err(e) => eprintln!("crussh: {}", e),
+        }
+        self.in_trap = false;
+        self.exit_status = exit_status.or(self.exit_status);
+        self.aborted |= aborted;
+        self.pending_error = pending;
+        self.last_status = status;
+    }
+
+    /// Has an interactive shell ignore the terminal's signals, and any
+    /// other shell take them the default way, where they are not trapped.
+    pub(crate) fn shield_signals(&mut self) {
+        if self.shielded == self.options.interactive {
+            return;
+        }
+        self.shielded = self.options.interactive;
+        for signal in TERMINAL_SIGNALS {
+            if !self.traps.contains_key(&Condition::Signal(signal)) {
+                let disposition = self.untrapped_disposition(signal);
+                self.executor.set_disposition(signal, disposition);
+            }
+        }
+    }
+
+    fn untrapped_disposition(&self, signal: Signal) -> Disposition {
+        if self.shielded && TERMINAL_SIGNALS.contains(&signal) {
+            Disposition::IgnoreInShell
+        } else {
+            Disposition::Default
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nix::sys::signal::Signal;
+
+    use super::Condition;
+
+    #[test]
+    fn reads_conditions() {
+        assert_eq!("int".parse(), Ok(Condition::Signal(Signal::SIGINT)));
+        assert_eq!("SIGTERM".parse(), Ok(Condition::Signal(Signal::SIGTERM)));
+        assert_eq!("1".parse(), Ok(Condition::Signal(Signal::SIGHUP)));
+        assert_eq!("0".parse(), Ok(Condition::Exit));
+        assert_eq!("err".parse(), Ok(Condition::Err));
+        assert_eq!("nope".parse::<Condition>(), Err(()));
+        assert_eq!(Condition::Signal(Signal::SIGUSR1).to_string(), "SIGUSR1");
+    }
+}
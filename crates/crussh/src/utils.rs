@@ -1,62 +1,159 @@
+use crate::ParseError;
 
-const WHITESPACE: &[char] = &[' ', '\n'];
+const BLANK: &[char] = &[' ', '\t'];
+const METACHARS: &[char] = &[' ', '\t', '\n', '|', '&', ';', '<', '>', '(', ')'];
+const RESERVED: &[&str] = &[
+    "!", "{", "}", "case", "do", "done", "elif", "else", "esac", "fi", "for", "if", "in", "then",
+    "time", "until", "while",
+];
 
+pub(crate) fn is_metachar(c: char) -> bool {
+    METACHARS.contains(&c)
+}
+
+/// Skips blanks, backslash-newline continuations and comments, but not
+/// newlines, which separate commands.
 pub(crate) fn extract_whitespace(s: &str) -> (&str, &str) {
-    take_while(|c| WHITESPACE.contains(&c), s)
+    let mut rest = s;
+    loop {
+        let (r, _) = take_while(|c| BLANK.contains(&c), rest);
+        rest = r;
+        if let Some(r) = rest.strip_prefix("\\\n") {
+            rest = r;
+        } else if rest.starts_with('#') {
+            let (r, _) = take_while(|c| c != '\n', rest);
+            rest = r;
+        } else {
+            break;
+        }
+    }
+    (rest, &s[..s.len() - rest.len()])
+}
+
+/// The byte offset of the first comment in `s`: a `#` outside quotes that
+/// begins a token, as [`extract_whitespace`] would skip it.
+pub(crate) fn find_comment(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices();
+    let mut at_start = true;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '#' if at_start => return Some(i),
+            '\\' => {
+                chars.next();
+            },
+            '\'' => {
+                chars.by_ref().find(|&(_, c)| c == '\'');
+            },
+            '"' => {
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            chars.next();
+                        },
+                        _ => {},
+                    }
+                }
+            },
+            _ => {},
+        }
+        at_start = is_metachar(c);
+    }
+    None
 }
 
-fn take_while(accept: impl Fn(char) -> bool, s: &str) -> (&str, &str) {
+/// Skips whitespace including any number of newlines.
+pub(crate) fn extract_linebreak(s: &str) -> (&str, &str) {
+    let mut rest = s;
+    loop {
+        let (r, _) = extract_whitespace(rest);
+        match r.strip_prefix('\n') {
+            Some(r) => rest = r,
+            None => {
+                rest = r;
+                break;
+            }
+        }
+    }
+    (rest, &s[..s.len() - rest.len()])
+}
+
+pub(crate) fn take_while(accept: impl Fn(char) -> bool, s: &str) -> (&str, &str) {
     let extracted_end = s
         .char_indices()
         .find_map(|(idx, c)| if accept(c) { None } else { Some(idx) })
-        .unwrap_or_else(|| s.len());
+        .unwrap_or(s.len());
 
         let extracted = &s[0..extracted_end];
         let remainder = &s[extracted_end..];
         (remainder, extracted)
 }
 
-fn take_while1(
-    accept: impl Fn(char) -> bool,
-    s: &str,
-    error_msg: String,
-) -> Result<(&str, &str), String> {
-    let (remainder, extracted) = take_while(accept, s);
+pub(crate) fn tag<'b>(starting_text: &str, s: &'b str) -> Result<&'b str, ParseError> {
+    s.strip_prefix(starting_text)
+        .ok_or_else(|| ParseError::Expected(format!("`{}'", starting_text)))
+}
+
+pub(crate) fn is_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
 
-    if extracted.is_empty() {
-        Err(error_msg)
+/// Extracts a variable or function name.
+pub(crate) fn extract_name(s: &str) -> Result<(&str, &str), ParseError> {
+    let (rest, name) = take_while(|c| c == '_' || c.is_ascii_alphanumeric(), s);
+    if is_name(name) {
+        Ok((rest, name))
     } else {
-        Ok((remainder, extracted))
+        Err(ParseError::Expected("a name".to_string()))
     }
 }
 
-pub(crate) fn tag<'a, 'b>(starting_text: &'a str, s: &'b str) -> Result<&'b str, String> {
-    if s.starts_with(starting_text) {
-        Ok(&s[starting_text.len()..])
-    } else {
-        Err(format!("expected {}", starting_text))
+pub(crate) fn is_reserved(word: &str) -> bool {
+    RESERVED.contains(&word)
+}
+
+/// The reserved word at the start of `s`, if the next token is one.
+pub(crate) fn peek_reserved(s: &str) -> Option<&'static str> {
+    let (_, word) = take_while(|c| !is_metachar(c), s);
+    RESERVED.iter().copied().find(|reserved| *reserved == word)
+}
+
+/// Consumes the reserved word `word`, skipping leading whitespace and newlines.
+pub(crate) fn reserved<'a>(word: &str, s: &'a str) -> Result<&'a str, ParseError> {
+    let (s, _) = extract_linebreak(s);
+    match peek_reserved(s) {
+        Some(found) if found == word => Ok(&s[word.len()..]),
+        _ if s.is_empty() => Err(ParseError::Incomplete(format!("`{}'", word))),
+        _ => Err(unexpected(s)),
     }
 }
 
-pub(crate) fn extract_shell_ident(s: &str) -> Result<(&str, &str), String> {
-    let input_starts_with_quote = s
-        .chars()
-        .next()
-        .map(|c| c == '"')
-        .unwrap_or(false);
+/// The error for a token that cannot start or continue the current construct.
+pub(crate) fn unexpected(s: &str) -> ParseError {
+    const OPERATORS: &[&str] = &["&&", "||", ";;", ">>", "<&", ">&", ">|", "<>", "|", "&", ";", "<", ">", "(", ")"];
 
-    if input_starts_with_quote {
-        let s = tag("\"", s)?;
+    if s.is_empty() {
+        return ParseError::Incomplete("a command".to_string());
+    }
+    if s.starts_with('\n') {
+        return ParseError::Unexpected("newline".to_string());
+    }
+    let token = OPERATORS.iter()
+        .find(|op| s.starts_with(*op))
+        .map(|op| op.to_string())
+        .unwrap_or_else(|| take_while(|c| !is_metachar(c), s).1.to_string());
+    ParseError::Unexpected(token)
+}
 
-        let (s, id) = take_while1(
-            |c| c != '"', 
-            s, 
-            "expected at least one character".to_string()
-        )?;
-            
-        let s = tag("\"", s)?;
-        Ok((s, id))
-    } else {
-        take_while1(|c| !vec![' ', '<', '>', '|'].contains(&c), s, "expected at least one character".to_string())
+/// `s` quoted so that the shell reads it back as one word.
+pub(crate) fn quote(s: &str) -> String {
+    if s.is_empty() {
+        return "''".to_string();
     }
-}
\ No newline at end of file
+    if s.chars().all(|c| c.is_ascii_alphanumeric() || "_-./,:+@%=".contains(c)) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}